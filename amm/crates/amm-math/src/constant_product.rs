@@ -0,0 +1,92 @@
+//! Constant-product curve `x * y = k`. Every amount rounds in the pool's favour.
+
+/// Output for `amount_in` (after fees): `reserve_out - ceil(k / (reserve_in + amount_in))`.
+pub fn swap_amount_out(reserve_in: u64, reserve_out: u64, amount_in: u64) -> Option<u64> {
+    if reserve_in == 0 || reserve_out == 0 {
+        return None;
+    }
+    let k = reserve_in as u128 * reserve_out as u128;
+    let new_out = k.div_ceil(reserve_in as u128 + amount_in as u128);
+    u64::try_from(reserve_out as u128 - new_out).ok()
+}
+
+/// Output `amount_in` would buy at the current marginal price, i.e. without
+/// slippage.
+pub fn spot_amount_out(reserve_in: u64, reserve_out: u64, amount_in: u64) -> Option<u128> {
+    if reserve_in == 0 {
+        return None;
+    }
+    Some(amount_in as u128 * reserve_out as u128 / reserve_in as u128)
+}
+
+/// Tokens needed to mint `lp_amount`, rounded up.
+pub fn deposit_amounts(
+    reserve_x: u64,
+    reserve_y: u64,
+    lp_supply: u64,
+    lp_amount: u64,
+) -> Option<(u64, u64)> {
+    if lp_supply == 0 {
+        return None;
+    }
+    let share = |reserve: u64| {
+        let amount = (reserve as u128 * lp_amount as u128).div_ceil(lp_supply as u128);
+        u64::try_from(amount).ok()
+    };
+    Some((share(reserve_x)?, share(reserve_y)?))
+}
+
+/// Tokens released by burning `lp_amount`, rounded down.
+pub fn withdraw_amounts(
+    reserve_x: u64,
+    reserve_y: u64,
+    lp_supply: u64,
+    lp_amount: u64,
+) -> Option<(u64, u64)> {
+    if lp_supply == 0 || lp_amount > lp_supply {
+        return None;
+    }
+    let share = |reserve: u64| (reserve as u128 * lp_amount as u128 / lp_supply as u128) as u64;
+    Some((share(reserve_x), share(reserve_y)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn swap_output_rounds_down() {
+        assert_eq!(swap_amount_out(30_000, 20_000, 998), Some(643));
+        assert_eq!(swap_amount_out(1_000, 1_000, 1), Some(0));
+        assert_eq!(swap_amount_out(1_000, 1_000, 0), Some(0));
+        assert_eq!(swap_amount_out(0, 1_000, 10), None);
+        assert_eq!(
+            swap_amount_out(u64::MAX, u64::MAX, u64::MAX),
+            Some(u64::MAX / 2)
+        );
+    }
+
+    #[test]
+    fn deposits_round_up_and_withdrawals_round_down() {
+        assert_eq!(
+            deposit_amounts(30_000, 20_000, 24_494, 1_000),
+            Some((1_225, 817))
+        );
+        assert_eq!(
+            withdraw_amounts(30_000, 20_000, 24_494, 1_000),
+            Some((1_224, 816))
+        );
+        assert_eq!(deposit_amounts(30_000, 20_000, 0, 1_000), None);
+        assert_eq!(withdraw_amounts(30_000, 20_000, 24_494, 24_495), None);
+        assert_eq!(
+            withdraw_amounts(30_000, 20_000, 24_494, 24_494),
+            Some((30_000, 20_000))
+        );
+    }
+
+    #[test]
+    fn spot_output_ignores_slippage() {
+        assert_eq!(spot_amount_out(30_000, 20_000, 3_000), Some(2_000));
+        assert_eq!(spot_amount_out(0, 20_000, 3_000), None);
+    }
+}
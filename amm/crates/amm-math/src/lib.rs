@@ -0,0 +1,26 @@
+//! Pool math shared by the `amm` program and off-chain clients, so a quote
+//! matches what the program executes down to the last unit.
+#![cfg_attr(not(any(test, feature = "wasm")), no_std)]
+
+pub mod constant_product;
+pub mod math;
+pub mod quote;
+pub mod stable_swap;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+
+/// Fees are expressed in basis points of this denominator.
+pub const FEE_DENOMINATOR: u16 = 10_000;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MathError {
+    /// A reserve or the LP supply is zero.
+    EmptyPool,
+    /// The amount rounds down to nothing.
+    ZeroOutput,
+    Overflow,
+    /// More than the pool holds was requested.
+    InsufficientLiquidity,
+    /// Fee above `FEE_DENOMINATOR`.
+    InvalidFee,
+}
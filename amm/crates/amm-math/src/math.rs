@@ -0,0 +1,309 @@
+use crate::FEE_DENOMINATOR;
+
+/// Splits `amount_in` into the part that is priced against the curve and the
+/// fee that stays in the vault. Rounds the fee up so dust always favours LPs.
+pub fn apply_fee(amount_in: u64, fee: u16) -> Option<(u64, u64)> {
+    if fee > FEE_DENOMINATOR {
+        return None;
+    }
+    let amount_after_fee = (amount_in as u128)
+        .checked_mul((FEE_DENOMINATOR - fee) as u128)?
+        .checked_div(FEE_DENOMINATOR as u128)? as u64;
+    let fee_amount = amount_in.checked_sub(amount_after_fee)?;
+    Some((amount_after_fee, fee_amount))
+}
+
+/// Smallest gross input whose post-fee amount is at least `amount_after_fee`.
+pub fn amount_before_fee(amount_after_fee: u64, fee: u16) -> Option<u64> {
+    if fee >= FEE_DENOMINATOR {
+        return None;
+    }
+    let numerator = (amount_after_fee as u128).checked_mul(FEE_DENOMINATOR as u128)?;
+    let amount_in = numerator.div_ceil((FEE_DENOMINATOR - fee) as u128);
+    u64::try_from(amount_in).ok()
+}
+
+/// Input needed on the constant-product curve to take exactly `amount_out`
+/// from the other side. Rounds up so the invariant never decreases.
+pub fn amount_in_for_exact_out(reserve_in: u64, reserve_out: u64, amount_out: u64) -> Option<u64> {
+    if amount_out >= reserve_out {
+        return None;
+    }
+    let numerator = (reserve_in as u128).checked_mul(amount_out as u128)?;
+    let amount_in = numerator.div_ceil((reserve_out - amount_out) as u128);
+    u64::try_from(amount_in).ok()
+}
+
+/// Splits a one-sided deposit of `amount_in` into the part swapped through the
+/// pool and the part deposited, so that the remainder and the swap output match
+/// the post-swap reserve ratio. `quote` maps a swap input (before fees) to its
+/// output. Returns `(swap_in, swap_out)`; the search stops within
+/// `amount_in / 1_000_000` of the optimum, erring towards swapping less.
+pub fn single_sided_swap<F>(
+    reserve_in: u64,
+    reserve_out: u64,
+    amount_in: u64,
+    quote: F,
+) -> Option<(u64, u64)>
+where
+    F: Fn(u64) -> Option<u64>,
+{
+    // After swapping s, depositing (amount_in - s, out) is balanced when
+    // (amount_in - s) / (reserve_in + s) == out / (reserve_out - out).
+    let remainder_covers_output = |s: u64, out: u64| -> Option<bool> {
+        let lhs = (amount_in - s) as u128 * (reserve_out.checked_sub(out)? as u128);
+        let rhs = out as u128 * (reserve_in as u128 + s as u128);
+        Some(lhs >= rhs)
+    };
+    let tolerance = amount_in / 1_000_000 + 1;
+    let (mut lo, mut hi) = (0u64, amount_in);
+    let mut lo_out = 0u64;
+    while hi - lo > tolerance {
+        let mid = lo + (hi - lo) / 2;
+        let out = quote(mid)?;
+        match remainder_covers_output(mid, out)? {
+            true => (lo, lo_out) = (mid, out),
+            false => hi = mid,
+        }
+    }
+    Some((lo, lo_out))
+}
+
+/// LP for seeding a constant-product pool: the geometric mean `sqrt(x * y)`,
+/// which does not depend on the ratio the first depositor picks.
+pub fn initial_lp(x: u64, y: u64) -> u64 {
+    let product = x as u128 * y as u128;
+    if product == 0 {
+        return 0;
+    }
+    // Newton's method on u128; converges from above.
+    let mut root = product;
+    let mut next = (root + product / root) / 2;
+    while next < root {
+        root = next;
+        next = (root + product / root) / 2;
+    }
+    root as u64
+}
+
+/// LP minted for depositing `(x, y)` on top of `(reserve_x, reserve_y)`, limited
+/// by whichever side is proportionally smaller.
+pub fn lp_for_deposit(supply: u64, reserve_x: u64, reserve_y: u64, x: u64, y: u64) -> Option<u64> {
+    if reserve_x == 0 || reserve_y == 0 {
+        return None;
+    }
+    let lp_x = (supply as u128).checked_mul(x as u128)? / reserve_x as u128;
+    let lp_y = (supply as u128).checked_mul(y as u128)? / reserve_y as u128;
+    u64::try_from(lp_x.min(lp_y)).ok()
+}
+
+/// `numerator / denominator` as a Q64.64 fixed-point number, used by the TWAP
+/// accumulators. `None` while either reserve is empty.
+pub fn price_q64(numerator: u64, denominator: u64) -> Option<u128> {
+    if numerator == 0 || denominator == 0 {
+        return None;
+    }
+    Some(((numerator as u128) << 64) / denominator as u128)
+}
+
+/// Portion of a swap fee owed to the protocol; rounds down so LPs keep the dust.
+pub fn protocol_fee_share(fee_amount: u64, protocol_fee: u16) -> Option<u64> {
+    if protocol_fee > FEE_DENOMINATOR {
+        return None;
+    }
+    Some(
+        (fee_amount as u128)
+            .checked_mul(protocol_fee as u128)?
+            .checked_div(FEE_DENOMINATOR as u128)? as u64,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn zero_fee_keeps_full_amount() {
+        assert_eq!(apply_fee(1_000_000, 0), Some((1_000_000, 0)));
+        assert_eq!(apply_fee(u64::MAX, 0), Some((u64::MAX, 0)));
+    }
+
+    #[test]
+    fn full_fee_takes_everything() {
+        assert_eq!(apply_fee(1_000_000, 10_000), Some((0, 1_000_000)));
+        assert_eq!(apply_fee(u64::MAX, 10_000), Some((0, u64::MAX)));
+    }
+
+    #[test]
+    fn fee_rounds_in_favour_of_pool() {
+        assert_eq!(apply_fee(10_000, 30), Some((9_970, 30)));
+        assert_eq!(apply_fee(10, 20), Some((9, 1)));
+        assert_eq!(apply_fee(1, 1), Some((0, 1)));
+        assert_eq!(apply_fee(0, 30), Some((0, 0)));
+    }
+
+    #[test]
+    fn fee_above_denominator_is_rejected() {
+        assert_eq!(apply_fee(1_000, 10_001), None);
+        assert_eq!(protocol_fee_share(1_000, 10_001), None);
+    }
+
+    #[test]
+    fn gross_up_inverts_fee() {
+        assert_eq!(amount_before_fee(1_000, 0), Some(1_000));
+        assert_eq!(amount_before_fee(1_000, 10_000), None);
+        for fee in [1u16, 20, 30, 100, 9_999] {
+            for net in [0u64, 1, 7, 9_970, 1_000_000] {
+                let gross = amount_before_fee(net, fee).unwrap();
+                let (after_fee, _) = apply_fee(gross, fee).unwrap();
+                assert!(after_fee >= net);
+                if gross > 0 {
+                    assert!(apply_fee(gross - 1, fee).unwrap().0 < net);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn exact_out_never_decreases_invariant() {
+        let cases = [
+            (1_000u64, 1_000u64, 1u64),
+            (25, 7, 3),
+            (1_000_000, 10, 9),
+            (10, 1_000_000, 999_999),
+        ];
+        for (reserve_in, reserve_out, amount_out) in cases {
+            let amount_in = amount_in_for_exact_out(reserve_in, reserve_out, amount_out).unwrap();
+            let k = reserve_in as u128 * reserve_out as u128;
+            let k_after = (reserve_in + amount_in) as u128 * (reserve_out - amount_out) as u128;
+            assert!(k_after >= k);
+        }
+        assert_eq!(amount_in_for_exact_out(1_000, 1_000, 1_000), None);
+        assert_eq!(amount_in_for_exact_out(1_000, 1_000, 0), Some(0));
+    }
+
+    fn constant_product_quote(
+        reserve_in: u64,
+        reserve_out: u64,
+        fee: u16,
+    ) -> impl Fn(u64) -> Option<u64> {
+        move |amount_in| {
+            let (amount_in, _) = apply_fee(amount_in, fee)?;
+            let k = reserve_in as u128 * reserve_out as u128;
+            let new_out = k.div_ceil(reserve_in as u128 + amount_in as u128);
+            Some((reserve_out as u128 - new_out) as u64)
+        }
+    }
+
+    #[test]
+    fn single_sided_deposit_matches_closed_form_without_fee() {
+        // With no fee, zapping a into a pool of reserve R mints
+        // L * (sqrt(1 + a / R) - 1) LP.
+        let (reserve_in, reserve_out, supply) =
+            (1_000_000_000u64, 4_000_000_000u64, 2_000_000_000u64);
+        for amount_in in [1_000_000u64, 250_000_000, 3_000_000_000] {
+            let quote = constant_product_quote(reserve_in, reserve_out, 0);
+            let (swap_in, swap_out) =
+                single_sided_swap(reserve_in, reserve_out, amount_in, quote).unwrap();
+            let lp = lp_for_deposit(
+                supply,
+                reserve_in + swap_in,
+                reserve_out - swap_out,
+                amount_in - swap_in,
+                swap_out,
+            )
+            .unwrap();
+            let expected =
+                supply as f64 * ((1.0 + amount_in as f64 / reserve_in as f64).sqrt() - 1.0);
+            let error = (lp as f64 - expected).abs() / expected;
+            assert!(
+                error < 1e-5,
+                "amount {amount_in}: lp {lp}, expected {expected}"
+            );
+            assert!((lp as f64) <= expected + 1.0);
+        }
+    }
+
+    #[test]
+    fn single_sided_deposit_pays_fee_on_swapped_part() {
+        let (reserve_in, reserve_out, supply) = (1_000_000u64, 1_000_000u64, 1_000_000u64);
+        let lp_with_fee = |fee: u16| {
+            let quote = constant_product_quote(reserve_in, reserve_out, fee);
+            let (swap_in, swap_out) =
+                single_sided_swap(reserve_in, reserve_out, 100_000, quote).unwrap();
+            lp_for_deposit(
+                supply,
+                reserve_in + swap_in,
+                reserve_out - swap_out,
+                100_000 - swap_in,
+                swap_out,
+            )
+            .unwrap()
+        };
+        assert!(lp_with_fee(30) < lp_with_fee(0));
+        assert_eq!(lp_with_fee(10_000), 0);
+    }
+
+    #[test]
+    fn initial_lp_is_geometric_mean() {
+        assert_eq!(initial_lp(0, 1_000), 0);
+        assert_eq!(initial_lp(1, 1), 1);
+        assert_eq!(initial_lp(30_000, 20_000), 24_494);
+        assert_eq!(initial_lp(4, 9), 6);
+        assert_eq!(initial_lp(u64::MAX, u64::MAX), u64::MAX);
+        assert_eq!(initial_lp(u64::MAX, 2), 6_074_000_999);
+    }
+
+    /// Attacker seeds the pool, donates straight to the vaults to inflate the
+    /// LP price, then lets a victim deposit and redeems. Returns the attacker's
+    /// profit (negative when the attack loses money).
+    fn donation_attack(minimum_liquidity: u64, seed: u64, donation: u64, victim: u64) -> i128 {
+        let attacker_lp = initial_lp(seed, seed) - minimum_liquidity;
+        let mut supply = attacker_lp + minimum_liquidity;
+        let mut reserve = seed + donation;
+        let victim_lp = lp_for_deposit(supply, reserve, reserve, victim, victim).unwrap();
+        supply += victim_lp;
+        reserve += victim;
+        let attacker_redeemed = reserve as u128 * attacker_lp as u128 / supply as u128;
+        2 * (attacker_redeemed as i128 - (seed + donation) as i128)
+    }
+
+    #[test]
+    fn donation_attack_profits_without_locked_liquidity() {
+        assert!(donation_attack(0, 1, 10_000, 15_000) > 0);
+    }
+
+    #[test]
+    fn donation_attack_loses_with_locked_liquidity() {
+        for (seed, donation, victim) in [
+            (1_001u64, 10_000u64, 15_000u64),
+            (1_001, 1_000_000_000, 1_500_000_000),
+            (2_000, 1_000_000, 1_999_999),
+        ] {
+            let profit = donation_attack(1_000, seed, donation, victim);
+            assert!(
+                profit < 0,
+                "seed {seed}, donation {donation}: profit {profit}"
+            );
+        }
+    }
+
+    #[test]
+    fn price_is_q64_fixed_point() {
+        assert_eq!(price_q64(1, 1), Some(1u128 << 64));
+        assert_eq!(price_q64(1, 2), Some(1u128 << 63));
+        assert_eq!(price_q64(3_000, 1), Some(3_000u128 << 64));
+        assert_eq!(price_q64(u64::MAX, 1), Some((u64::MAX as u128) << 64));
+        assert_eq!(price_q64(0, 1), None);
+        assert_eq!(price_q64(1, 0), None);
+    }
+
+    #[test]
+    fn protocol_share_rounds_in_favour_of_lps() {
+        assert_eq!(protocol_fee_share(30, 0), Some(0));
+        assert_eq!(protocol_fee_share(30, 10_000), Some(30));
+        assert_eq!(protocol_fee_share(30, 5_000), Some(15));
+        assert_eq!(protocol_fee_share(3, 5_000), Some(1));
+        assert_eq!(protocol_fee_share(u64::MAX, 10_000), Some(u64::MAX));
+    }
+}
@@ -0,0 +1,326 @@
+//! Quotes for the pool instructions, built from the same functions the
+//! program calls. Transfer-fee mints are not accounted for: the program prices
+//! what the vault actually receives.
+
+use crate::math::{amount_in_for_exact_out, apply_fee, protocol_fee_share};
+use crate::{constant_product, stable_swap, MathError, FEE_DENOMINATOR};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Curve {
+    ConstantProduct,
+    /// `amp` is the amplification at the time of the trade; it moves while a
+    /// ramp is in progress.
+    StableSwap {
+        amp: u64,
+    },
+}
+
+/// A pool as the curve sees it. Reserves exclude protocol fees that are owed
+/// to the treasury.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Pool {
+    pub reserve_x: u64,
+    pub reserve_y: u64,
+    pub lp_supply: u64,
+    pub fee: u16,
+    pub protocol_fee: u16,
+    pub curve: Curve,
+}
+
+impl Pool {
+    fn reserves(&self, is_x_to_y: bool) -> (u64, u64) {
+        match is_x_to_y {
+            true => (self.reserve_x, self.reserve_y),
+            false => (self.reserve_y, self.reserve_x),
+        }
+    }
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen::prelude::wasm_bindgen)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SwapQuote {
+    pub amount_out: u64,
+    /// Swap fee charged on the input; it stays in the pool.
+    pub fee: u64,
+    /// Part of `fee` set aside for the treasury.
+    pub protocol_fee: u64,
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen::prelude::wasm_bindgen)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LiquidityQuote {
+    pub amount_x: u64,
+    pub amount_y: u64,
+}
+
+/// Curve output for `amount_in_after_fee`.
+pub fn curve_amount_out(
+    curve: Curve,
+    reserve_in: u64,
+    reserve_out: u64,
+    amount_in_after_fee: u64,
+) -> Result<u64, MathError> {
+    if reserve_in == 0 || reserve_out == 0 {
+        return Err(MathError::EmptyPool);
+    }
+    let amount_out = match curve {
+        Curve::ConstantProduct => {
+            constant_product::swap_amount_out(reserve_in, reserve_out, amount_in_after_fee)
+        }
+        Curve::StableSwap { amp } => {
+            stable_swap::swap_amount_out(amp, reserve_in, reserve_out, amount_in_after_fee)
+        }
+    }
+    .ok_or(MathError::Overflow)?;
+    match amount_out {
+        0 => Err(MathError::ZeroOutput),
+        _ => Ok(amount_out),
+    }
+}
+
+/// Post-fee input the curve needs to release exactly `amount_out`.
+pub fn curve_amount_in(
+    curve: Curve,
+    reserve_in: u64,
+    reserve_out: u64,
+    amount_out: u64,
+) -> Result<u64, MathError> {
+    if reserve_in == 0 || reserve_out == 0 {
+        return Err(MathError::EmptyPool);
+    }
+    if amount_out >= reserve_out {
+        return Err(MathError::InsufficientLiquidity);
+    }
+    match curve {
+        Curve::ConstantProduct => amount_in_for_exact_out(reserve_in, reserve_out, amount_out),
+        Curve::StableSwap { amp } => {
+            stable_swap::amount_in_for_exact_out(amp, reserve_in, reserve_out, amount_out)
+        }
+    }
+    .ok_or(MathError::Overflow)
+}
+
+/// Result of `swap` with `amount_in`.
+pub fn quote_swap(pool: &Pool, is_x_to_y: bool, amount_in: u64) -> Result<SwapQuote, MathError> {
+    let (reserve_in, reserve_out) = pool.reserves(is_x_to_y);
+    let (amount_in_after_fee, fee) = apply_fee(amount_in, pool.fee).ok_or(MathError::InvalidFee)?;
+    let amount_out = curve_amount_out(pool.curve, reserve_in, reserve_out, amount_in_after_fee)?;
+    let protocol_fee = protocol_fee_share(fee, pool.protocol_fee).ok_or(MathError::InvalidFee)?;
+    Ok(SwapQuote {
+        amount_out,
+        fee,
+        protocol_fee,
+    })
+}
+
+/// Tokens `deposit` takes to mint `lp_amount` into a pool that already has
+/// liquidity.
+pub fn quote_deposit(pool: &Pool, lp_amount: u64) -> Result<LiquidityQuote, MathError> {
+    if pool.lp_supply == 0 {
+        return Err(MathError::EmptyPool);
+    }
+    let (amount_x, amount_y) = constant_product::deposit_amounts(
+        pool.reserve_x,
+        pool.reserve_y,
+        pool.lp_supply,
+        lp_amount,
+    )
+    .ok_or(MathError::Overflow)?;
+    Ok(LiquidityQuote { amount_x, amount_y })
+}
+
+/// Tokens `withdraw` releases for burning `lp_amount`.
+pub fn quote_withdraw(pool: &Pool, lp_amount: u64) -> Result<LiquidityQuote, MathError> {
+    if pool.lp_supply == 0 {
+        return Err(MathError::EmptyPool);
+    }
+    let (amount_x, amount_y) = constant_product::withdraw_amounts(
+        pool.reserve_x,
+        pool.reserve_y,
+        pool.lp_supply,
+        lp_amount,
+    )
+    .ok_or(MathError::InsufficientLiquidity)?;
+    if amount_x == 0 && amount_y == 0 {
+        return Err(MathError::ZeroOutput);
+    }
+    Ok(LiquidityQuote { amount_x, amount_y })
+}
+
+/// How much worse than the pre-trade marginal price a swap of `amount_in`
+/// executes, in basis points. The swap fee is not included.
+pub fn price_impact_bps(pool: &Pool, is_x_to_y: bool, amount_in: u64) -> Result<u64, MathError> {
+    let (reserve_in, reserve_out) = pool.reserves(is_x_to_y);
+    let (amount_in_after_fee, _) = apply_fee(amount_in, pool.fee).ok_or(MathError::InvalidFee)?;
+    let amount_out = curve_amount_out(pool.curve, reserve_in, reserve_out, amount_in_after_fee)?;
+    let spot_out = match pool.curve {
+        Curve::ConstantProduct => {
+            constant_product::spot_amount_out(reserve_in, reserve_out, amount_in_after_fee)
+        }
+        Curve::StableSwap { amp } => {
+            stable_swap::spot_amount_out(amp, reserve_in, reserve_out, amount_in_after_fee)
+        }
+    }
+    .ok_or(MathError::Overflow)?;
+    let shortfall = spot_out.saturating_sub(amount_out as u128);
+    Ok((shortfall * FEE_DENOMINATOR as u128 / spot_out) as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    fn pool(reserve_x: u64, reserve_y: u64, lp_supply: u64, curve: Curve) -> Pool {
+        Pool {
+            reserve_x,
+            reserve_y,
+            lp_supply,
+            fee: 30,
+            protocol_fee: 5_000,
+            curve,
+        }
+    }
+
+    #[test]
+    fn swap_quote_splits_fee() {
+        let pool = pool(30_000, 20_000, 24_494, Curve::ConstantProduct);
+        assert_eq!(
+            quote_swap(&pool, true, 1_000),
+            Ok(SwapQuote {
+                amount_out: 643,
+                fee: 3,
+                protocol_fee: 1,
+            })
+        );
+        assert_eq!(quote_swap(&pool, true, 1), Err(MathError::ZeroOutput));
+        let empty = Pool {
+            reserve_x: 0,
+            ..pool
+        };
+        assert_eq!(quote_swap(&empty, true, 1_000), Err(MathError::EmptyPool));
+    }
+
+    #[test]
+    fn liquidity_quotes_round_in_favour_of_the_pool() {
+        let pool = pool(30_000, 20_000, 24_494, Curve::ConstantProduct);
+        assert_eq!(
+            quote_deposit(&pool, 1_000),
+            Ok(LiquidityQuote {
+                amount_x: 1_225,
+                amount_y: 817,
+            })
+        );
+        assert_eq!(
+            quote_withdraw(&pool, 1_000),
+            Ok(LiquidityQuote {
+                amount_x: 1_224,
+                amount_y: 816,
+            })
+        );
+        assert_eq!(
+            quote_withdraw(&pool, 24_495),
+            Err(MathError::InsufficientLiquidity)
+        );
+        assert_eq!(quote_withdraw(&pool, 0), Err(MathError::ZeroOutput));
+    }
+
+    #[test]
+    fn price_impact_of_constant_product_swap() {
+        let pool = Pool {
+            fee: 0,
+            ..pool(1_000_000, 1_000_000, 1_000_000, Curve::ConstantProduct)
+        };
+        // Buying with 10% of the reserve gets 1 / 1.1 of the spot amount.
+        assert_eq!(price_impact_bps(&pool, true, 100_000), Ok(909));
+        assert_eq!(price_impact_bps(&pool, true, 1_000), Ok(10));
+    }
+
+    fn curve() -> impl Strategy<Value = Curve> {
+        prop_oneof![
+            Just(Curve::ConstantProduct),
+            (1u64..=1_000_000).prop_map(|amp| Curve::StableSwap { amp }),
+        ]
+    }
+
+    proptest! {
+        #[test]
+        fn swaps_never_decrease_the_invariant(
+            reserve_x in 1_000u64..1_000_000_000_000,
+            reserve_y in 1_000u64..1_000_000_000_000,
+            amount_in in 1u64..1_000_000_000_000,
+            fee in 0u16..1_000,
+            is_x_to_y: bool,
+        ) {
+            let pool = Pool { fee, ..pool(reserve_x, reserve_y, 1, Curve::ConstantProduct) };
+            if let Ok(quote) = quote_swap(&pool, is_x_to_y, amount_in) {
+                let (reserve_in, reserve_out) = pool.reserves(is_x_to_y);
+                let k = reserve_in as u128 * reserve_out as u128;
+                let new_in = reserve_in as u128 + amount_in as u128 - quote.protocol_fee as u128;
+                let new_out = (reserve_out - quote.amount_out) as u128;
+                prop_assert!(new_in * new_out >= k);
+            }
+        }
+
+        #[test]
+        fn exact_out_input_buys_at_least_the_output(
+            reserve_in in 1_000u64..1_000_000_000_000,
+            reserve_out in 1_000u64..1_000_000_000_000,
+            amount_out in 1u64..1_000_000_000,
+            curve in curve(),
+        ) {
+            prop_assume!(amount_out < reserve_out);
+            if let Ok(amount_in) = curve_amount_in(curve, reserve_in, reserve_out, amount_out) {
+                let bought = curve_amount_out(curve, reserve_in, reserve_out, amount_in).unwrap_or(0);
+                // StableSwap rounds each direction by one unit.
+                prop_assert!(bought + 2 >= amount_out);
+            }
+        }
+
+        #[test]
+        fn deposit_then_withdraw_never_profits(
+            reserve_x in 1u64..1_000_000_000_000,
+            reserve_y in 1u64..1_000_000_000_000,
+            lp_supply in 1u64..1_000_000_000_000,
+            lp_amount in 1u64..1_000_000_000_000,
+        ) {
+            let before = pool(reserve_x, reserve_y, lp_supply, Curve::ConstantProduct);
+            let Ok(deposit) = quote_deposit(&before, lp_amount) else {
+                return Ok(());
+            };
+            let (Some(new_x), Some(new_y)) = (
+                reserve_x.checked_add(deposit.amount_x),
+                reserve_y.checked_add(deposit.amount_y),
+            ) else {
+                return Ok(());
+            };
+            let after = Pool {
+                reserve_x: new_x,
+                reserve_y: new_y,
+                lp_supply: lp_supply + lp_amount,
+                ..before
+            };
+            if let Ok(withdraw) = quote_withdraw(&after, lp_amount) {
+                prop_assert!(withdraw.amount_x <= deposit.amount_x);
+                prop_assert!(withdraw.amount_y <= deposit.amount_y);
+            }
+        }
+
+        #[test]
+        fn price_impact_grows_with_trade_size(
+            reserve_x in 1_000_000_000_000u64..10_000_000_000_000,
+            reserve_y in 1_000_000_000_000u64..10_000_000_000_000,
+            amount_in in 1_000_000_000u64..10_000_000_000,
+            curve in curve(),
+        ) {
+            // Large enough that rounding stays far below one basis point.
+            let pool = pool(reserve_x, reserve_y, 1, curve);
+            let small = price_impact_bps(&pool, true, amount_in);
+            let large = price_impact_bps(&pool, true, amount_in.saturating_mul(10));
+            if let (Ok(small), Ok(large)) = (small, large) {
+                prop_assert!(small <= large);
+                prop_assert!(large <= FEE_DENOMINATOR as u64);
+            }
+        }
+    }
+}
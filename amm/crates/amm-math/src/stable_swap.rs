@@ -0,0 +1,226 @@
+//! Curve-style StableSwap invariant for two-token pools:
+//! `A·n^n·(x + y) + D = A·D·n^n + D^(n+1) / (n^n·x·y)` with `n = 2`.
+
+#[allow(clippy::manual_div_ceil)]
+mod u256 {
+    uint::construct_uint! {
+        pub struct U256(4);
+    }
+    uint::construct_uint! {
+        pub struct U512(8);
+    }
+}
+pub use u256::{U256, U512};
+
+const N_COINS: u64 = 2;
+const MAX_ITERATIONS: usize = 255;
+
+/// Solves the invariant `D` for the given reserves with Newton's method.
+pub fn compute_d(amp: u64, x: u64, y: u64) -> Option<u128> {
+    let sum = U256::from(x) + U256::from(y);
+    if sum.is_zero() {
+        return Some(0);
+    }
+    if x == 0 || y == 0 || amp == 0 {
+        return None;
+    }
+    let n = U256::from(N_COINS);
+    let ann = U256::from(amp) * n * n;
+    let (x, y) = (U256::from(x), U256::from(y));
+
+    let mut d = sum;
+    for _ in 0..MAX_ITERATIONS {
+        let mut d_p = d;
+        d_p = d_p * d / (x * n);
+        d_p = d_p * d / (y * n);
+        let d_prev = d;
+        let numerator = (ann * sum + d_p * n) * d;
+        let denominator = (ann - 1) * d + (n + 1) * d_p;
+        d = numerator / denominator;
+        if abs_diff(d, d_prev) <= U256::one() {
+            return u128::try_from(d).ok();
+        }
+    }
+    None
+}
+
+/// Solves the other reserve given one reserve `x` and the invariant `d`.
+pub fn compute_y(amp: u64, x: u128, d: u128) -> Option<u128> {
+    if x == 0 || amp == 0 {
+        return None;
+    }
+    let n = U256::from(N_COINS);
+    let ann = U256::from(amp) * n * n;
+    let (x, d) = (U256::from(x), U256::from(d));
+
+    let mut c = d;
+    c = c * d / (x * n);
+    c = c * d / (ann * n);
+    let b = x + d / ann;
+
+    let mut y = d;
+    for _ in 0..MAX_ITERATIONS {
+        let y_prev = y;
+        let denominator = (y * n + b).checked_sub(d)?;
+        y = (y * y + c) / denominator;
+        if abs_diff(y, y_prev) <= U256::one() {
+            return u128::try_from(y).ok();
+        }
+    }
+    None
+}
+
+/// Output for `amount_in`, rounded down by one unit so the pool never loses
+/// value to Newton's method precision.
+pub fn swap_amount_out(amp: u64, reserve_in: u64, reserve_out: u64, amount_in: u64) -> Option<u64> {
+    let d = compute_d(amp, reserve_in, reserve_out)?;
+    swap_amount_out_with_d(amp, d, reserve_in, reserve_out, amount_in)
+}
+
+/// Same as [`swap_amount_out`] with the invariant already known, for callers
+/// that quote many amounts against the same reserves.
+pub fn swap_amount_out_with_d(
+    amp: u64,
+    d: u128,
+    reserve_in: u64,
+    reserve_out: u64,
+    amount_in: u64,
+) -> Option<u64> {
+    let new_in = (reserve_in as u128).checked_add(amount_in as u128)?;
+    let new_out = compute_y(amp, new_in, d)?;
+    let amount_out = (reserve_out as u128)
+        .checked_sub(new_out)?
+        .saturating_sub(1);
+    u64::try_from(amount_out).ok()
+}
+
+/// Input needed to take exactly `amount_out`, rounded up by one unit.
+pub fn amount_in_for_exact_out(
+    amp: u64,
+    reserve_in: u64,
+    reserve_out: u64,
+    amount_out: u64,
+) -> Option<u64> {
+    if amount_out >= reserve_out {
+        return None;
+    }
+    let d = compute_d(amp, reserve_in, reserve_out)?;
+    let new_out = (reserve_out - amount_out) as u128;
+    let new_in = compute_y(amp, new_out, d)?;
+    let amount_in = new_in.checked_sub(reserve_in as u128)?.checked_add(1)?;
+    u64::try_from(amount_in).ok()
+}
+
+/// Output `amount_in` would buy at the marginal price `-dy/dx`, i.e. without
+/// slippage. Differentiating the invariant gives
+/// `(4·Ann·x²·y² + D³·y) / (4·Ann·x²·y² + D³·x)` for `x = reserve_in`.
+pub fn spot_amount_out(
+    amp: u64,
+    reserve_in: u64,
+    reserve_out: u64,
+    amount_in: u64,
+) -> Option<u128> {
+    let d = U512::from(compute_d(amp, reserve_in, reserve_out)?);
+    let (x, y) = (U512::from(reserve_in), U512::from(reserve_out));
+    let ann = U512::from(amp) * U512::from(N_COINS * N_COINS);
+    let xy = x * y;
+    let d_cubed = d * d * d;
+    let numerator = U512::from(4) * ann * xy * xy + d_cubed * y;
+    let denominator = U512::from(4) * ann * xy * xy + d_cubed * x;
+    if denominator.is_zero() {
+        return None;
+    }
+    u128::try_from(U512::from(amount_in) * numerator / denominator).ok()
+}
+
+fn abs_diff(a: U256, b: U256) -> U256 {
+    if a > b {
+        a - b
+    } else {
+        b - a
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn balanced_pool_invariant_is_sum() {
+        for amp in [1u64, 10, 100, 1_000, 1_000_000] {
+            let d = compute_d(amp, 1_000_000, 1_000_000).unwrap();
+            assert!(d.abs_diff(2_000_000) <= 1, "amp {amp}: d = {d}");
+        }
+        assert_eq!(compute_d(100, 0, 0), Some(0));
+        assert_eq!(compute_d(100, 0, 1_000), None);
+    }
+
+    #[test]
+    fn invariant_converges_for_imbalanced_and_large_reserves() {
+        let cases = [
+            (1u64, 1u64, u64::MAX / 2),
+            (100, 1_000, 1_000_000_000_000),
+            (2_000, u64::MAX / 4, u64::MAX / 4),
+            (1_000_000, 1, 1_000_000),
+        ];
+        for (amp, x, y) in cases {
+            let d = compute_d(amp, x, y).unwrap();
+            // D lies between the constant-product and constant-sum invariants.
+            let sum = x as u128 + y as u128;
+            assert!(d <= sum);
+            let y_back = compute_y(amp, x as u128, d).unwrap();
+            assert!(y_back.abs_diff(y as u128) <= y as u128 / 1_000_000 + 2);
+        }
+    }
+
+    #[test]
+    fn swaps_never_decrease_invariant() {
+        let amp = 100;
+        let (mut x, mut y) = (5_000_000u64, 5_000_000u64);
+        let d_start = compute_d(amp, x, y).unwrap();
+        for amount in [1u64, 1_000, 250_000, 3_000_000] {
+            let out = swap_amount_out(amp, x, y, amount).unwrap();
+            x += amount;
+            y -= out;
+            assert!(compute_d(amp, x, y).unwrap() >= d_start);
+        }
+    }
+
+    #[test]
+    fn stable_pool_has_less_slippage_than_constant_product() {
+        let (x, y, amount) = (1_000_000u64, 1_000_000u64, 100_000u64);
+        let stable_out = swap_amount_out(200, x, y, amount).unwrap();
+        let cp_out = y - (x as u128 * y as u128).div_ceil((x + amount) as u128) as u64;
+        assert!(stable_out > cp_out);
+        assert!(stable_out < amount);
+    }
+
+    #[test]
+    fn exact_out_round_trips() {
+        let amp = 100;
+        let (x, y) = (2_000_000u64, 3_000_000u64);
+        for amount_out in [1u64, 10_000, 1_500_000] {
+            let amount_in = amount_in_for_exact_out(amp, x, y, amount_out).unwrap();
+            assert!(swap_amount_out(amp, x, y, amount_in).unwrap() + 2 >= amount_out);
+            let d = compute_d(amp, x, y).unwrap();
+            assert!(compute_d(amp, x + amount_in, y - amount_out).unwrap() >= d);
+        }
+        assert_eq!(amount_in_for_exact_out(amp, x, y, y), None);
+    }
+
+    #[test]
+    fn spot_price_is_one_when_balanced_and_favours_the_scarce_side() {
+        assert_eq!(
+            spot_amount_out(100, 1_000_000, 1_000_000, 1_000),
+            Some(1_000)
+        );
+        // Selling the abundant token gets less than one for one.
+        let abundant = spot_amount_out(100, 3_000_000, 1_000_000, 1_000_000).unwrap();
+        let scarce = spot_amount_out(100, 1_000_000, 3_000_000, 1_000_000).unwrap();
+        assert!(abundant < 1_000_000 && scarce > 1_000_000);
+        // A trade never beats the spot price.
+        let out = swap_amount_out(100, 3_000_000, 1_000_000, 10_000).unwrap();
+        assert!((out as u128) <= spot_amount_out(100, 3_000_000, 1_000_000, 10_000).unwrap());
+        assert_eq!(spot_amount_out(100, 0, 1_000, 10), None);
+    }
+}
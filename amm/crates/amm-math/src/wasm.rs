@@ -0,0 +1,61 @@
+//! JavaScript bindings. Build with
+//! `cargo rustc --lib --release --features wasm --target wasm32-unknown-unknown --crate-type cdylib`
+//! and run `wasm-bindgen` on the output.
+
+use crate::quote::{self, Curve, LiquidityQuote, SwapQuote};
+use crate::MathError;
+use wasm_bindgen::prelude::*;
+
+fn to_js(error: MathError) -> JsError {
+    JsError::new(&format!("{error:?}"))
+}
+
+#[wasm_bindgen(js_name = Pool)]
+pub struct JsPool(quote::Pool);
+
+#[wasm_bindgen(js_class = Pool)]
+impl JsPool {
+    /// `amp` is the current amplification of a StableSwap pool and is left
+    /// undefined for constant-product pools.
+    #[wasm_bindgen(constructor)]
+    pub fn new(
+        reserve_x: u64,
+        reserve_y: u64,
+        lp_supply: u64,
+        fee: u16,
+        protocol_fee: u16,
+        amp: Option<u64>,
+    ) -> JsPool {
+        JsPool(quote::Pool {
+            reserve_x,
+            reserve_y,
+            lp_supply,
+            fee,
+            protocol_fee,
+            curve: match amp {
+                Some(amp) => Curve::StableSwap { amp },
+                None => Curve::ConstantProduct,
+            },
+        })
+    }
+
+    #[wasm_bindgen(js_name = quoteSwap)]
+    pub fn quote_swap(&self, is_x_to_y: bool, amount_in: u64) -> Result<SwapQuote, JsError> {
+        quote::quote_swap(&self.0, is_x_to_y, amount_in).map_err(to_js)
+    }
+
+    #[wasm_bindgen(js_name = quoteDeposit)]
+    pub fn quote_deposit(&self, lp_amount: u64) -> Result<LiquidityQuote, JsError> {
+        quote::quote_deposit(&self.0, lp_amount).map_err(to_js)
+    }
+
+    #[wasm_bindgen(js_name = quoteWithdraw)]
+    pub fn quote_withdraw(&self, lp_amount: u64) -> Result<LiquidityQuote, JsError> {
+        quote::quote_withdraw(&self.0, lp_amount).map_err(to_js)
+    }
+
+    #[wasm_bindgen(js_name = priceImpactBps)]
+    pub fn price_impact_bps(&self, is_x_to_y: bool, amount_in: u64) -> Result<u64, JsError> {
+        quote::price_impact_bps(&self.0, is_x_to_y, amount_in).map_err(to_js)
+    }
+}
@@ -0,0 +1,383 @@
+//! Concentrated-liquidity math. Prices are stored as `sqrt(price)` in Q64.64
+//! and a tick `i` corresponds to `sqrt(1.0001^i)`.
+
+use crate::constants::{FEE_DENOMINATOR, MAX_TICK, MIN_TICK};
+use crate::stable_swap::U256;
+
+/// `sqrt_price_at_tick(MIN_TICK)` and `sqrt_price_at_tick(MAX_TICK)`.
+pub const MIN_SQRT_PRICE_X64: u128 = 4_295_048_017;
+pub const MAX_SQRT_PRICE_X64: u128 = 79_226_673_515_401_279_992_447_579_062;
+
+/// `2^128 / sqrt(1.0001)^(2^i)`: multiplying the bits of `|tick|` together
+/// yields `sqrt(1.0001^-|tick|)` in Q128.
+const TICK_RATIOS_X128: [u128; 19] = [
+    0xfffcb933bd6fad37aa2d162d1a594001,
+    0xfff97272373d413259a46990580e2139,
+    0xfff2e50f5f656932ef12357cf3c7fdcb,
+    0xffe5caca7e10e4e61c3624eaa0941ccf,
+    0xffcb9843d60f6159c9db58835c926643,
+    0xff973b41fa98c081472e6896dfb254bf,
+    0xff2ea16466c96a3843ec78b326b52860,
+    0xfe5dee046a99a2a811c461f1969c3052,
+    0xfcbe86c7900a88aedcffc83b479aa3a3,
+    0xf987a7253ac413176f2b074cf7815e53,
+    0xf3392b0822b70005940c7a398e4b70f2,
+    0xe7159475a2c29b7443b29c7fa6e889d8,
+    0xd097f3bdfd2022b8845ad8f792aa5825,
+    0xa9f746462d870fdf8a65dc1f90e061e4,
+    0x70d869a156d2a1b890bb3df62baf32f6,
+    0x31be135f97d08fd981231505542fcfa5,
+    0x09aa508b5b7a84e1c677de54f3e99bc8,
+    0x005d6af8dedb81196699c329225ee604,
+    0x00002216e584f5fa1ea926041bedfe97,
+];
+
+/// `2^32 / log2(sqrt(1.0001))`, converts a Q32 log2 into ticks.
+const TICKS_PER_LOG2_X32: i128 = 59_543_866_431_248;
+
+/// `sqrt(1.0001^tick)` in Q64.64, rounded up.
+pub fn sqrt_price_at_tick(tick: i32) -> Option<u128> {
+    if !(MIN_TICK..=MAX_TICK).contains(&tick) {
+        return None;
+    }
+    let abs_tick = tick.unsigned_abs();
+    let mut ratio = U256::one() << 128;
+    for (bit, factor) in TICK_RATIOS_X128.iter().enumerate() {
+        if abs_tick & (1 << bit) != 0 {
+            ratio = (ratio * U256::from(*factor)) >> 128;
+        }
+    }
+    if tick > 0 {
+        ratio = U256::MAX / ratio;
+    }
+    let round_up = !(ratio & U256::from(u64::MAX)).is_zero();
+    u128::try_from((ratio >> 64) + U256::from(round_up as u8)).ok()
+}
+
+/// Greatest tick whose sqrt price is at most `sqrt_price_x64`.
+pub fn tick_at_sqrt_price(sqrt_price_x64: u128) -> Option<i32> {
+    if !(MIN_SQRT_PRICE_X64..=MAX_SQRT_PRICE_X64).contains(&sqrt_price_x64) {
+        return None;
+    }
+    // log2 of the Q64.64 price: integer part from the msb, then 32 fractional
+    // bits by repeated squaring of the mantissa kept in [1, 2) as Q1.63.
+    let msb = 127 - sqrt_price_x64.leading_zeros() as i32;
+    let mut log2_x32 = ((msb - 64) as i128) << 32;
+    let mut r = match msb >= 63 {
+        true => sqrt_price_x64 >> (msb - 63),
+        false => sqrt_price_x64 << (63 - msb),
+    };
+    let mut bit: i128 = 1 << 31;
+    while bit > 0 {
+        r *= r;
+        let above_two = (r >> 127) as u32;
+        r >>= 63 + above_two;
+        if above_two == 1 {
+            log2_x32 += bit;
+        }
+        bit >>= 1;
+    }
+
+    // The estimate is off by at most one tick; settle it against the exact
+    // forward mapping.
+    let mut tick = (((log2_x32 * TICKS_PER_LOG2_X32) >> 64) as i32).clamp(MIN_TICK, MAX_TICK);
+    while tick > MIN_TICK && sqrt_price_at_tick(tick)? > sqrt_price_x64 {
+        tick -= 1;
+    }
+    while tick < MAX_TICK && sqrt_price_at_tick(tick + 1)? <= sqrt_price_x64 {
+        tick += 1;
+    }
+    Some(tick)
+}
+
+/// Token x spanned by `liquidity` between two prices: `L·(b − a)·2^64 / (a·b)`.
+pub fn amount_x_delta(sqrt_a: u128, sqrt_b: u128, liquidity: u128, round_up: bool) -> Option<u64> {
+    let (lower, upper) = (sqrt_a.min(sqrt_b), sqrt_a.max(sqrt_b));
+    if lower == 0 {
+        return None;
+    }
+    let numerator = (U256::from(liquidity) << 64).checked_mul(U256::from(upper - lower))?;
+    let (lower, upper) = (U256::from(lower), U256::from(upper));
+    let amount = match round_up {
+        true => div_ceil(div_ceil(numerator, upper), lower),
+        false => numerator / upper / lower,
+    };
+    u64::try_from(amount).ok()
+}
+
+/// Token y spanned by `liquidity` between two prices: `L·(b − a) / 2^64`.
+pub fn amount_y_delta(sqrt_a: u128, sqrt_b: u128, liquidity: u128, round_up: bool) -> Option<u64> {
+    let diff = sqrt_a.abs_diff(sqrt_b);
+    let product = U256::from(liquidity) * U256::from(diff);
+    let round_up = round_up && !(product & U256::from(u64::MAX)).is_zero();
+    u64::try_from((product >> 64) + U256::from(round_up as u8)).ok()
+}
+
+/// Token amounts backing `liquidity` over `[sqrt_lower, sqrt_upper)` at the
+/// current price. Deposits round up, withdrawals round down.
+pub fn amounts_for_liquidity(
+    sqrt_price: u128,
+    sqrt_lower: u128,
+    sqrt_upper: u128,
+    liquidity: u128,
+    round_up: bool,
+) -> Option<(u64, u64)> {
+    if sqrt_price <= sqrt_lower {
+        Some((
+            amount_x_delta(sqrt_lower, sqrt_upper, liquidity, round_up)?,
+            0,
+        ))
+    } else if sqrt_price >= sqrt_upper {
+        Some((
+            0,
+            amount_y_delta(sqrt_lower, sqrt_upper, liquidity, round_up)?,
+        ))
+    } else {
+        Some((
+            amount_x_delta(sqrt_price, sqrt_upper, liquidity, round_up)?,
+            amount_y_delta(sqrt_lower, sqrt_price, liquidity, round_up)?,
+        ))
+    }
+}
+
+/// Price after adding `amount` of x: `L·P / (L + amount·P)`, rounded up so the
+/// pool never gives out more y than the x paid for.
+fn next_sqrt_price_from_x(sqrt_price: u128, liquidity: u128, amount: u64) -> Option<u128> {
+    let numerator = U256::from(liquidity) << 64;
+    let denominator = numerator.checked_add(U256::from(amount) * U256::from(sqrt_price))?;
+    u128::try_from(div_ceil(
+        numerator.checked_mul(U256::from(sqrt_price))?,
+        denominator,
+    ))
+    .ok()
+}
+
+/// Price after adding `amount` of y: `P + amount / L`, rounded down.
+fn next_sqrt_price_from_y(sqrt_price: u128, liquidity: u128, amount: u64) -> Option<u128> {
+    let delta = u128::try_from((U256::from(amount) << 64) / U256::from(liquidity)).ok()?;
+    sqrt_price.checked_add(delta)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SwapStep {
+    pub sqrt_price_next: u128,
+    pub amount_in: u64,
+    pub amount_out: u64,
+    pub fee_amount: u64,
+}
+
+/// Swaps as much of `amount_remaining` (fee included) as fits before the
+/// price reaches `sqrt_price_target`. The direction follows from the target:
+/// a lower price means x in, a higher one means y in.
+pub fn compute_swap_step(
+    sqrt_price: u128,
+    sqrt_price_target: u128,
+    liquidity: u128,
+    amount_remaining: u64,
+    fee: u16,
+) -> Option<SwapStep> {
+    let x_to_y = sqrt_price_target < sqrt_price;
+    let fee_denominator = FEE_DENOMINATOR as u128;
+    let remaining_less_fee =
+        (amount_remaining as u128 * (fee_denominator - fee as u128) / fee_denominator) as u64;
+
+    let amount_in_to_target = match x_to_y {
+        true => amount_x_delta(sqrt_price_target, sqrt_price, liquidity, true),
+        false => amount_y_delta(sqrt_price, sqrt_price_target, liquidity, true),
+    };
+    // An amount that doesn't fit in u64 can't be covered by the remainder.
+    let sqrt_price_next = match amount_in_to_target {
+        Some(amount) if remaining_less_fee >= amount => sqrt_price_target,
+        _ if x_to_y => next_sqrt_price_from_x(sqrt_price, liquidity, remaining_less_fee)?,
+        _ => next_sqrt_price_from_y(sqrt_price, liquidity, remaining_less_fee)?,
+    };
+    let reached_target = sqrt_price_next == sqrt_price_target;
+
+    let (amount_in, amount_out) = match x_to_y {
+        true => (
+            amount_x_delta(sqrt_price_next, sqrt_price, liquidity, true)?,
+            amount_y_delta(sqrt_price_next, sqrt_price, liquidity, false)?,
+        ),
+        false => (
+            amount_y_delta(sqrt_price, sqrt_price_next, liquidity, true)?,
+            amount_x_delta(sqrt_price, sqrt_price_next, liquidity, false)?,
+        ),
+    };
+    // Without reaching the target the whole remainder is consumed, so what
+    // isn't swapped is the fee.
+    let fee_amount = match reached_target {
+        true => (amount_in as u128 * fee as u128).div_ceil(fee_denominator - fee as u128) as u64,
+        false => amount_remaining.checked_sub(amount_in)?,
+    };
+    Some(SwapStep {
+        sqrt_price_next,
+        amount_in,
+        amount_out,
+        fee_amount,
+    })
+}
+
+/// Fees earned per unit of liquidity, in Q64.64.
+pub fn fee_growth(fee_amount: u64, liquidity: u128) -> u128 {
+    match liquidity {
+        0 => 0,
+        _ => ((fee_amount as u128) << 64) / liquidity,
+    }
+}
+
+/// Tokens owed to `liquidity` for a fee growth delta in Q64.64.
+pub fn fees_owed(liquidity: u128, fee_growth_delta: u128) -> Option<u64> {
+    u64::try_from((U256::from(liquidity) * U256::from(fee_growth_delta)) >> 64).ok()
+}
+
+pub fn add_liquidity_delta(liquidity: u128, delta: i128) -> Option<u128> {
+    match delta >= 0 {
+        true => liquidity.checked_add(delta.unsigned_abs()),
+        false => liquidity.checked_sub(delta.unsigned_abs()),
+    }
+}
+
+fn div_ceil(numerator: U256, denominator: U256) -> U256 {
+    let (quotient, remainder) = numerator.div_mod(denominator);
+    match remainder.is_zero() {
+        true => quotient,
+        false => quotient + 1,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const Q64: u128 = 1 << 64;
+
+    #[test]
+    fn tick_zero_is_price_one() {
+        assert_eq!(sqrt_price_at_tick(0), Some(Q64));
+        assert_eq!(tick_at_sqrt_price(Q64), Some(0));
+    }
+
+    #[test]
+    fn tick_bounds_match_price_bounds() {
+        assert_eq!(sqrt_price_at_tick(MIN_TICK), Some(MIN_SQRT_PRICE_X64));
+        assert_eq!(sqrt_price_at_tick(MAX_TICK), Some(MAX_SQRT_PRICE_X64));
+        assert_eq!(sqrt_price_at_tick(MIN_TICK - 1), None);
+        assert_eq!(sqrt_price_at_tick(MAX_TICK + 1), None);
+        assert_eq!(tick_at_sqrt_price(MIN_SQRT_PRICE_X64 - 1), None);
+        assert_eq!(tick_at_sqrt_price(MAX_SQRT_PRICE_X64 + 1), None);
+    }
+
+    #[test]
+    fn sqrt_price_matches_float_reference() {
+        for tick in [-100_000, -1_000, -1, 1, 10, 1_000, 100_000] {
+            let expected = 1.0001f64.powf(tick as f64 / 2.0) * Q64 as f64;
+            let actual = sqrt_price_at_tick(tick).unwrap() as f64;
+            assert!((actual - expected).abs() / expected < 1e-12, "tick {tick}");
+        }
+    }
+
+    #[test]
+    fn tick_at_sqrt_price_inverts_sqrt_price_at_tick() {
+        let mut tick = MIN_TICK;
+        while tick <= MAX_TICK {
+            let sqrt_price = sqrt_price_at_tick(tick).unwrap();
+            assert_eq!(tick_at_sqrt_price(sqrt_price), Some(tick));
+            if tick > MIN_TICK {
+                assert_eq!(tick_at_sqrt_price(sqrt_price - 1), Some(tick - 1));
+            }
+            tick += 997;
+        }
+    }
+
+    #[test]
+    fn amounts_for_liquidity_depend_on_price_position() {
+        let lower = sqrt_price_at_tick(-1_000).unwrap();
+        let upper = sqrt_price_at_tick(1_000).unwrap();
+        let liquidity = 1_000_000_000u128;
+
+        let (x, y) = amounts_for_liquidity(Q64, lower, upper, liquidity, true).unwrap();
+        assert!(x > 0 && y > 0);
+        // Symmetric range around price 1 needs about the same of each side.
+        assert!(x.abs_diff(y) <= 1);
+
+        let (x, y) = amounts_for_liquidity(lower - 1, lower, upper, liquidity, true).unwrap();
+        assert!(x > 0 && y == 0);
+        let (x, y) = amounts_for_liquidity(upper, lower, upper, liquidity, true).unwrap();
+        assert!(x == 0 && y > 0);
+
+        let (x_up, y_up) = amounts_for_liquidity(Q64, lower, upper, liquidity, true).unwrap();
+        let (x_down, y_down) = amounts_for_liquidity(Q64, lower, upper, liquidity, false).unwrap();
+        assert!(x_up >= x_down && y_up >= y_down);
+    }
+
+    #[test]
+    fn swap_step_stops_at_target_when_amount_suffices() {
+        let liquidity = 1_000_000_000u128;
+        let target = sqrt_price_at_tick(-10).unwrap();
+        let step = compute_swap_step(Q64, target, liquidity, 10_000_000, 30).unwrap();
+        assert_eq!(step.sqrt_price_next, target);
+        assert!(step.amount_in + step.fee_amount <= 10_000_000);
+        assert!(step.amount_out < step.amount_in);
+    }
+
+    #[test]
+    fn swap_step_consumes_remainder_before_target() {
+        let liquidity = 1_000_000_000u128;
+        for (target, amount) in [
+            (MIN_SQRT_PRICE_X64, 100_000u64),
+            (MAX_SQRT_PRICE_X64, 100_000),
+        ] {
+            let step = compute_swap_step(Q64, target, liquidity, amount, 30).unwrap();
+            assert_ne!(step.sqrt_price_next, target);
+            assert_eq!(step.amount_in + step.fee_amount, amount);
+            assert!(step.fee_amount >= amount * 30 / 10_000);
+            assert!(step.amount_out > 0 && step.amount_out < amount);
+        }
+    }
+
+    #[test]
+    fn swap_step_without_liquidity_moves_price_for_free() {
+        let target = sqrt_price_at_tick(500).unwrap();
+        let step = compute_swap_step(Q64, target, 0, 1_000, 30).unwrap();
+        assert_eq!(
+            step,
+            SwapStep {
+                sqrt_price_next: target,
+                amount_in: 0,
+                amount_out: 0,
+                fee_amount: 0,
+            }
+        );
+    }
+
+    #[test]
+    fn swap_round_trip_does_not_create_value() {
+        let liquidity = 5_000_000_000u128;
+        let down = compute_swap_step(Q64, MIN_SQRT_PRICE_X64, liquidity, 1_000_000, 0).unwrap();
+        let back = compute_swap_step(
+            down.sqrt_price_next,
+            MAX_SQRT_PRICE_X64,
+            liquidity,
+            down.amount_out,
+            0,
+        )
+        .unwrap();
+        assert!(back.amount_out <= down.amount_in);
+        assert!(back.sqrt_price_next <= Q64);
+    }
+
+    #[test]
+    fn fee_growth_round_trips_to_owed_tokens() {
+        let liquidity = 3_000_000u128;
+        let growth = fee_growth(9_000, liquidity);
+        assert_eq!(fees_owed(liquidity, growth), Some(8_999));
+        assert_eq!(fee_growth(9_000, 0), 0);
+    }
+
+    #[test]
+    fn liquidity_delta_is_checked() {
+        assert_eq!(add_liquidity_delta(10, -4), Some(6));
+        assert_eq!(add_liquidity_delta(10, 5), Some(15));
+        assert_eq!(add_liquidity_delta(3, -4), None);
+    }
+}
@@ -1 +1,44 @@
+use anchor_lang::prelude::*;
 
+#[constant]
+pub const FEE_DENOMINATOR: u16 = 10_000;
+const _: () = assert!(FEE_DENOMINATOR == amm_math::FEE_DENOMINATOR);
+
+/// LP permanently locked by the first deposit so share price cannot be inflated.
+#[constant]
+pub const MINIMUM_LIQUIDITY: u64 = 1_000;
+
+/// Owner of the LP account holding the locked minimum liquidity. Nobody can
+/// sign for the System Program, so those LP tokens can never move.
+#[constant]
+pub const LOCKED_LIQUIDITY_OWNER: Pubkey = pubkey!("11111111111111111111111111111111");
+
+#[constant]
+pub const MAX_AMP: u64 = 1_000_000;
+
+#[constant]
+pub const MIN_AMP_RAMP_DURATION: i64 = 86_400;
+
+#[constant]
+pub const MAX_AMP_CHANGE: u64 = 10;
+
+/// Tick bounds for concentrated-liquidity pools; tick `i` is price `1.0001^i`.
+#[constant]
+pub const MIN_TICK: i32 = -443_636;
+
+#[constant]
+pub const MAX_TICK: i32 = 443_636;
+
+/// Spaced ticks each `TickBitmap` account covers, one bit per tick.
+#[constant]
+pub const TICKS_PER_BITMAP: i32 = 256;
+
+/// Smallest non-zero liquidity a position may hold. Every initialized tick is
+/// referenced by a position, so each tick a swap has to cross is backed by
+/// real liquidity rather than dust.
+#[constant]
+pub const MIN_POSITION_LIQUIDITY: u128 = 1_000_000;
+
+/// Pools recorded per `PoolListPage` account.
+#[constant]
+pub const POOLS_PER_PAGE: usize = 32;
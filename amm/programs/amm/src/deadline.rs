@@ -0,0 +1,14 @@
+use crate::error::AMMError;
+use anchor_lang::prelude::*;
+
+/// Fails once the cluster clock has passed `expires_at`, so a transaction
+/// that sat unconfirmed can't execute at a price the user no longer expects.
+pub fn check_deadline(expires_at: Option<i64>) -> Result<()> {
+    if let Some(expires_at) = expires_at {
+        require!(
+            Clock::get()?.unix_timestamp <= expires_at,
+            AMMError::Expired
+        );
+    }
+    Ok(())
+}
@@ -1,3 +1,4 @@
+use amm_math::MathError;
 use anchor_lang::prelude::*;
 
 #[error_code]
@@ -10,4 +11,86 @@ pub enum AMMError {
     InsufficientLiquidity,
     #[msg("Exceeded maximum slippage")]
     ExceededMaxSlippage,
+    #[msg("Fee must not exceed 10000 basis points")]
+    InvalidFee,
+    #[msg("Signer is not allowed to perform this action")]
+    Unauthorized,
+    #[msg("Transferred amount does not match the amount the pool received")]
+    TransferFeeMismatch,
+    #[msg("Route accounts do not describe a valid chain of pools")]
+    InvalidRoute,
+    #[msg("Amplification coefficient or ramp is out of bounds")]
+    InvalidAmp,
+    #[msg("Operation is not supported by this pool's curve")]
+    InvalidCurve,
+    #[msg("Pool has no liquidity")]
+    EmptyPool,
+    #[msg("Amount is too small to produce any output")]
+    ZeroOutput,
+    #[msg("Arithmetic overflow in curve math")]
+    Overflow,
+    #[msg("Tick range is out of bounds or not aligned to the tick spacing")]
+    InvalidTickRange,
+    #[msg("Sqrt price is out of bounds")]
+    InvalidSqrtPrice,
+    #[msg("Tick account does not match the next tick the swap crosses")]
+    InvalidTickAccount,
+    #[msg("Pool has an outstanding flash loan")]
+    FlashLoanActive,
+    #[msg("Flash loan is not repaid later in the transaction")]
+    FlashLoanNotRepaid,
+    #[msg("Repayment does not match the outstanding flash loan")]
+    FlashLoanMismatch,
+    #[msg("Flash loans must be top-level instructions")]
+    FlashLoanCpi,
+    #[msg("mint_x must sort before mint_y")]
+    MintsNotSorted,
+    #[msg("Amount exceeds the staked LP")]
+    InsufficientStake,
+    #[msg("Transaction expired")]
+    Expired,
+    #[msg("Treasury does not match the pool config")]
+    InvalidTreasury,
+    #[msg("Position liquidity is below the minimum")]
+    LiquidityBelowMinimum,
+    #[msg("Position still holds liquidity or uncollected fees")]
+    PositionNotEmpty,
+    #[msg("Amount exceeds the unemitted farm rewards")]
+    ExceedsUnemittedRewards,
+    #[msg("Farm still holds unemitted rewards")]
+    FarmNotDrained,
+}
+
+impl From<MathError> for AMMError {
+    fn from(error: MathError) -> Self {
+        match error {
+            MathError::EmptyPool => AMMError::EmptyPool,
+            MathError::ZeroOutput => AMMError::ZeroOutput,
+            MathError::Overflow => AMMError::Overflow,
+            MathError::InsufficientLiquidity => AMMError::InsufficientLiquidity,
+            MathError::InvalidFee => AMMError::InvalidFee,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn math_errors_map_to_descriptive_variants() {
+        let cases = [
+            (MathError::EmptyPool, AMMError::EmptyPool),
+            (MathError::ZeroOutput, AMMError::ZeroOutput),
+            (MathError::Overflow, AMMError::Overflow),
+            (
+                MathError::InsufficientLiquidity,
+                AMMError::InsufficientLiquidity,
+            ),
+            (MathError::InvalidFee, AMMError::InvalidFee),
+        ];
+        for (math_error, expected) in cases {
+            assert_eq!(AMMError::from(math_error) as u32, expected as u32);
+        }
+    }
 }
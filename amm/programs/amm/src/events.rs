@@ -0,0 +1,231 @@
+use crate::state::CurveType;
+use anchor_lang::prelude::*;
+
+#[event]
+pub struct PoolInitialized {
+    pub config: Pubkey,
+    pub registry: Pubkey,
+    pub pool_index: u64,
+    pub authority: Pubkey,
+    pub seed: u64,
+    pub mint_x: Pubkey,
+    pub mint_y: Pubkey,
+    pub mint_lp: Pubkey,
+    pub curve_type: CurveType,
+    pub amp: u64,
+    pub fee: u16,
+}
+
+/// Reserves are the LP reserves (vaults minus protocol fees) after the action.
+#[event]
+pub struct LiquidityAdded {
+    pub config: Pubkey,
+    pub user: Pubkey,
+    pub amount_x: u64,
+    pub amount_y: u64,
+    pub lp_minted: u64,
+    pub reserve_x: u64,
+    pub reserve_y: u64,
+    pub lp_supply: u64,
+}
+
+#[event]
+pub struct LiquidityRemoved {
+    pub config: Pubkey,
+    pub user: Pubkey,
+    pub amount_x: u64,
+    pub amount_y: u64,
+    pub lp_burned: u64,
+    pub reserve_x: u64,
+    pub reserve_y: u64,
+    pub lp_supply: u64,
+}
+
+#[event]
+pub struct Swapped {
+    pub config: Pubkey,
+    pub user: Pubkey,
+    pub is_x_to_y: bool,
+    pub amount_in: u64,
+    pub amount_out: u64,
+    pub fee: u64,
+    pub protocol_fee: u64,
+    pub reserve_x: u64,
+    pub reserve_y: u64,
+}
+
+#[event]
+pub struct PoolLockUpdated {
+    pub config: Pubkey,
+    pub locked: bool,
+}
+
+#[event]
+pub struct FeeUpdated {
+    pub config: Pubkey,
+    pub fee: u16,
+    pub protocol_fee: u16,
+}
+
+#[event]
+pub struct AmpRampUpdated {
+    pub config: Pubkey,
+    pub amp_initial: u64,
+    pub amp_target: u64,
+    pub ramp_start_ts: i64,
+    pub ramp_end_ts: i64,
+}
+
+#[event]
+pub struct AuthorityTransferStarted {
+    pub config: Pubkey,
+    pub authority: Pubkey,
+    pub pending_authority: Pubkey,
+}
+
+/// `new_authority` is `None` once the authority has been renounced.
+#[event]
+pub struct AuthorityUpdated {
+    pub config: Pubkey,
+    pub old_authority: Pubkey,
+    pub new_authority: Option<Pubkey>,
+}
+
+#[event]
+pub struct TreasuryUpdated {
+    pub config: Pubkey,
+    pub treasury: Pubkey,
+}
+
+#[event]
+pub struct ProtocolFeesCollected {
+    pub config: Pubkey,
+    pub treasury: Pubkey,
+    pub amount_x: u64,
+    pub amount_y: u64,
+}
+
+#[event]
+pub struct ClPoolInitialized {
+    pub pool: Pubkey,
+    pub seed: u64,
+    pub mint_x: Pubkey,
+    pub mint_y: Pubkey,
+    pub fee: u16,
+    pub tick_spacing: u16,
+    pub sqrt_price_x64: u128,
+    pub tick_current: i32,
+}
+
+/// `liquidity_delta` is negative when liquidity is removed.
+#[event]
+pub struct PositionLiquidityUpdated {
+    pub pool: Pubkey,
+    pub position: Pubkey,
+    pub owner: Pubkey,
+    pub tick_lower: i32,
+    pub tick_upper: i32,
+    pub liquidity_delta: i128,
+    pub amount_x: u64,
+    pub amount_y: u64,
+    pub pool_liquidity: u128,
+}
+
+#[event]
+pub struct PositionFeesCollected {
+    pub pool: Pubkey,
+    pub position: Pubkey,
+    pub owner: Pubkey,
+    pub amount_x: u64,
+    pub amount_y: u64,
+}
+
+#[event]
+pub struct PositionClosed {
+    pub pool: Pubkey,
+    pub position: Pubkey,
+    pub owner: Pubkey,
+}
+
+#[event]
+pub struct ClSwapped {
+    pub pool: Pubkey,
+    pub user: Pubkey,
+    pub is_x_to_y: bool,
+    pub amount_in: u64,
+    pub amount_out: u64,
+    pub fee: u64,
+    pub sqrt_price_x64: u128,
+    pub tick_current: i32,
+    pub liquidity: u128,
+}
+
+#[event]
+pub struct FlashBorrowed {
+    pub config: Pubkey,
+    pub borrower: Pubkey,
+    pub is_x: bool,
+    pub amount: u64,
+}
+
+#[event]
+pub struct FlashRepaid {
+    pub config: Pubkey,
+    pub borrower: Pubkey,
+    pub is_x: bool,
+    pub amount: u64,
+    pub fee: u64,
+}
+
+#[event]
+pub struct FarmInitialized {
+    pub farm: Pubkey,
+    pub config: Pubkey,
+    pub reward_mint: Pubkey,
+    pub emission_per_second: u64,
+}
+
+#[event]
+pub struct FarmFunded {
+    pub farm: Pubkey,
+    pub amount: u64,
+    pub rewards_remaining: u64,
+}
+
+#[event]
+pub struct FarmRewardsWithdrawn {
+    pub farm: Pubkey,
+    pub amount: u64,
+    pub rewards_remaining: u64,
+}
+
+#[event]
+pub struct FarmEmissionUpdated {
+    pub farm: Pubkey,
+    pub emission_per_second: u64,
+}
+
+#[event]
+pub struct LpStaked {
+    pub farm: Pubkey,
+    pub owner: Pubkey,
+    pub amount: u64,
+    pub staked: u64,
+    pub total_staked: u64,
+}
+
+#[event]
+pub struct LpUnstaked {
+    pub farm: Pubkey,
+    pub owner: Pubkey,
+    pub amount: u64,
+    pub staked: u64,
+    pub total_staked: u64,
+}
+
+#[event]
+pub struct RewardsHarvested {
+    pub farm: Pubkey,
+    pub owner: Pubkey,
+    pub amount: u64,
+}
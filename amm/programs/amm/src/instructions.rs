@@ -1,9 +1,37 @@
+pub mod admin;
+pub mod cl_swap;
+pub mod close_position;
+pub mod collect_protocol_fees;
 pub mod deposit;
+pub mod farm_admin;
+pub mod flash_loan;
+pub mod harvest;
 pub mod initialize;
+pub mod initialize_cl_pool;
+pub mod initialize_farm;
+pub mod modify_position;
+pub mod observe;
+pub mod open_position;
+pub mod route_swap;
+pub mod stake_lp;
 pub mod swap;
 pub mod withdraw;
 
+pub use admin::*;
+pub use cl_swap::*;
+pub use close_position::*;
+pub use collect_protocol_fees::*;
 pub use deposit::*;
+pub use farm_admin::*;
+pub use flash_loan::*;
+pub use harvest::*;
 pub use initialize::*;
+pub use initialize_cl_pool::*;
+pub use initialize_farm::*;
+pub use modify_position::*;
+pub use observe::*;
+pub use open_position::*;
+pub use route_swap::*;
+pub use stake_lp::*;
 pub use swap::*;
 pub use withdraw::*;
@@ -0,0 +1,185 @@
+use crate::constants::{FEE_DENOMINATOR, MAX_AMP, MAX_AMP_CHANGE, MIN_AMP_RAMP_DURATION};
+use crate::error::AMMError;
+use crate::events::{
+    AmpRampUpdated, AuthorityTransferStarted, AuthorityUpdated, FeeUpdated, PoolLockUpdated,
+    TreasuryUpdated,
+};
+use crate::state::{Config, CurveType, Farm};
+use anchor_lang::prelude::*;
+
+#[derive(Accounts)]
+pub struct UpdateConfig<'info> {
+    pub authority: Signer<'info>,
+    #[account(
+        mut,
+        constraint = config.authority == Some(authority.key()) @ AMMError::Unauthorized,
+        seeds = [b"config", config.seed.to_le_bytes().as_ref()],
+        bump = config.config_bump
+    )]
+    pub config: Account<'info, Config>,
+}
+
+impl<'info> UpdateConfig<'info> {
+    pub fn lock(&mut self) -> Result<()> {
+        self.config.locked = true;
+        self.emit_lock_updated();
+        Ok(())
+    }
+    pub fn unlock(&mut self) -> Result<()> {
+        self.config.locked = false;
+        self.emit_lock_updated();
+        Ok(())
+    }
+    pub fn update_fee(&mut self, fee: u16) -> Result<()> {
+        require!(fee <= FEE_DENOMINATOR, AMMError::InvalidFee);
+        self.config.fee = fee;
+        self.emit_fee_updated();
+        Ok(())
+    }
+    pub fn update_protocol_fee(&mut self, protocol_fee: u16) -> Result<()> {
+        require!(protocol_fee <= FEE_DENOMINATOR, AMMError::InvalidFee);
+        self.config.protocol_fee = protocol_fee;
+        self.emit_fee_updated();
+        Ok(())
+    }
+    pub fn ramp_amp(&mut self, target_amp: u64, ramp_end_ts: i64) -> Result<()> {
+        require!(
+            self.config.curve_type == CurveType::StableSwap,
+            AMMError::InvalidCurve
+        );
+        require!((1..=MAX_AMP).contains(&target_amp), AMMError::InvalidAmp);
+        let now = Clock::get()?.unix_timestamp;
+        require!(
+            ramp_end_ts >= now.saturating_add(MIN_AMP_RAMP_DURATION),
+            AMMError::InvalidAmp
+        );
+        let current_amp = self.config.amp(now);
+        require!(
+            target_amp <= current_amp.saturating_mul(MAX_AMP_CHANGE)
+                && target_amp.saturating_mul(MAX_AMP_CHANGE) >= current_amp,
+            AMMError::InvalidAmp
+        );
+
+        self.config.amp_initial = current_amp;
+        self.config.amp_target = target_amp;
+        self.config.amp_ramp_start_ts = now;
+        self.config.amp_ramp_end_ts = ramp_end_ts;
+        self.emit_amp_ramp_updated();
+        Ok(())
+    }
+    pub fn stop_ramp_amp(&mut self) -> Result<()> {
+        require!(
+            self.config.curve_type == CurveType::StableSwap,
+            AMMError::InvalidCurve
+        );
+        let now = Clock::get()?.unix_timestamp;
+        let current_amp = self.config.amp(now);
+        self.config.amp_initial = current_amp;
+        self.config.amp_target = current_amp;
+        self.config.amp_ramp_start_ts = now;
+        self.config.amp_ramp_end_ts = now;
+        self.emit_amp_ramp_updated();
+        Ok(())
+    }
+    pub fn set_treasury(&mut self, treasury: Pubkey) -> Result<()> {
+        self.config.treasury = treasury;
+        emit!(TreasuryUpdated {
+            config: self.config.key(),
+            treasury,
+        });
+        Ok(())
+    }
+    pub fn transfer_authority(&mut self, new_authority: Pubkey) -> Result<()> {
+        self.config.pending_authority = Some(new_authority);
+        emit!(AuthorityTransferStarted {
+            config: self.config.key(),
+            authority: self.authority.key(),
+            pending_authority: new_authority,
+        });
+        Ok(())
+    }
+    fn emit_lock_updated(&self) {
+        emit!(PoolLockUpdated {
+            config: self.config.key(),
+            locked: self.config.locked,
+        });
+    }
+    fn emit_fee_updated(&self) {
+        emit!(FeeUpdated {
+            config: self.config.key(),
+            fee: self.config.fee,
+            protocol_fee: self.config.protocol_fee,
+        });
+    }
+    fn emit_amp_ramp_updated(&self) {
+        emit!(AmpRampUpdated {
+            config: self.config.key(),
+            amp_initial: self.config.amp_initial,
+            amp_target: self.config.amp_target,
+            ramp_start_ts: self.config.amp_ramp_start_ts,
+            ramp_end_ts: self.config.amp_ramp_end_ts,
+        });
+    }
+}
+
+#[derive(Accounts)]
+pub struct RenounceAuthority<'info> {
+    pub authority: Signer<'info>,
+    #[account(
+        mut,
+        constraint = config.authority == Some(authority.key()) @ AMMError::Unauthorized,
+        seeds = [b"config", config.seed.to_le_bytes().as_ref()],
+        bump = config.config_bump
+    )]
+    pub config: Account<'info, Config>,
+    /// CHECK: The pool's farm address; it only has to exist if a farm was created.
+    #[account(seeds = [b"farm", config.key().as_ref()], bump)]
+    pub farm: UncheckedAccount<'info>,
+}
+
+impl<'info> RenounceAuthority<'info> {
+    pub fn renounce_authority(&mut self) -> Result<()> {
+        // Nobody could unlock the pool or withdraw unemitted farm rewards
+        // afterwards, so refuse to strand LP funds or rewards.
+        require!(!self.config.locked, AMMError::ConfigLocked);
+        if self.farm.owner == &crate::ID {
+            let farm = Farm::try_deserialize(&mut &self.farm.try_borrow_data()?[..])?;
+            require!(farm.rewards_remaining == 0, AMMError::FarmNotDrained);
+        }
+        self.config.authority = None;
+        self.config.pending_authority = None;
+        emit!(AuthorityUpdated {
+            config: self.config.key(),
+            old_authority: self.authority.key(),
+            new_authority: None,
+        });
+        Ok(())
+    }
+}
+
+#[derive(Accounts)]
+pub struct AcceptAuthority<'info> {
+    pub new_authority: Signer<'info>,
+    #[account(
+        mut,
+        constraint = config.pending_authority == Some(new_authority.key()) @ AMMError::Unauthorized,
+        seeds = [b"config", config.seed.to_le_bytes().as_ref()],
+        bump = config.config_bump
+    )]
+    pub config: Account<'info, Config>,
+}
+
+impl<'info> AcceptAuthority<'info> {
+    pub fn accept_authority(&mut self) -> Result<()> {
+        // A pending authority only exists while an authority is set.
+        let old_authority = self.config.authority.ok_or(AMMError::Unauthorized)?;
+        self.config.authority = Some(self.new_authority.key());
+        self.config.pending_authority = None;
+        emit!(AuthorityUpdated {
+            config: self.config.key(),
+            old_authority,
+            new_authority: self.config.authority,
+        });
+        Ok(())
+    }
+}
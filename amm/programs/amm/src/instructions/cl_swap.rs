@@ -0,0 +1,304 @@
+use crate::cl_math::{
+    add_liquidity_delta, compute_swap_step, fee_growth, sqrt_price_at_tick, tick_at_sqrt_price,
+    MAX_SQRT_PRICE_X64, MIN_SQRT_PRICE_X64,
+};
+use crate::constants::{MAX_TICK, MIN_TICK};
+use crate::error::AMMError;
+use crate::events::ClSwapped;
+use crate::state::{ClPool, Tick, TickBitmap};
+use crate::transfer_fee::amount_with_transfer_fee;
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::{
+    transfer_checked, Mint, TokenAccount, TokenInterface, TransferChecked,
+};
+
+/// `remaining_accounts` holds, in the order the swap reaches them, the
+/// `TickBitmap` words it searches, starting with
+/// `TickBitmap::word_to_search` for the current tick, and the `Tick` accounts
+/// it crosses. Words no tick was ever initialized in have no account and are
+/// passed by address.
+#[derive(Accounts)]
+pub struct ClSwap<'info> {
+    pub user: Signer<'info>,
+    pub mint_x: Box<InterfaceAccount<'info, Mint>>,
+    pub mint_y: Box<InterfaceAccount<'info, Mint>>,
+    #[account(
+        mut,
+        has_one = mint_x,
+        has_one = mint_y,
+        seeds = [b"cl_pool", pool.seed.to_le_bytes().as_ref()],
+        bump = pool.bump
+    )]
+    pub pool: Box<Account<'info, ClPool>>,
+    #[account(
+        mut,
+        associated_token::mint = mint_x,
+        associated_token::authority = pool,
+        associated_token::token_program = token_program,
+    )]
+    pub vault_x: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        mut,
+        associated_token::mint = mint_y,
+        associated_token::authority = pool,
+        associated_token::token_program = token_program,
+    )]
+    pub vault_y: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        mut,
+        associated_token::mint = mint_x,
+        associated_token::authority = user,
+        associated_token::token_program = token_program,
+    )]
+    pub user_ata_x: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        mut,
+        associated_token::mint = mint_y,
+        associated_token::authority = user,
+        associated_token::token_program = token_program,
+    )]
+    pub user_ata_y: Box<InterfaceAccount<'info, TokenAccount>>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+impl<'info> ClSwap<'info> {
+    /// Exact-input swap that walks the price towards `sqrt_price_limit_x64`,
+    /// crossing initialized ticks on the way. Input left over once the limit
+    /// is reached is not taken from the user.
+    pub fn cl_swap(
+        &mut self,
+        remaining_accounts: &'info [AccountInfo<'info>],
+        is_x_to_y: bool,
+        amount_in: u64,
+        min_amount_out: u64,
+        sqrt_price_limit_x64: u128,
+    ) -> Result<()> {
+        require!(amount_in > 0, AMMError::InvalidAmount);
+        let sqrt_price = self.pool.sqrt_price_x64;
+        require!(
+            match is_x_to_y {
+                true => (MIN_SQRT_PRICE_X64..sqrt_price).contains(&sqrt_price_limit_x64),
+                false =>
+                    sqrt_price < sqrt_price_limit_x64 && sqrt_price_limit_x64 <= MAX_SQRT_PRICE_X64,
+            },
+            AMMError::InvalidSqrtPrice
+        );
+
+        let spacing = self.pool.tick_spacing;
+        let mut accounts = remaining_accounts.iter();
+        let word = TickBitmap::word_to_search(self.pool.tick_current, spacing, is_x_to_y);
+        let info = accounts.next().ok_or(AMMError::InvalidTickAccount)?;
+        let mut bitmap = self.load_bitmap(info, word)?;
+        let mut amount_remaining = amount_in;
+        let (mut amount_out, mut fee) = (0u64, 0u64);
+        while amount_remaining > 0 && self.pool.sqrt_price_x64 != sqrt_price_limit_x64 {
+            let word = TickBitmap::word_to_search(self.pool.tick_current, spacing, is_x_to_y);
+            if bitmap.word != word {
+                let info = accounts.next().ok_or(AMMError::InvalidTickAccount)?;
+                bitmap = self.load_bitmap(info, word)?;
+            }
+            // Without an initialized tick left in the word, step to its edge.
+            let (tick_next, initialized) =
+                bitmap.next_tick(self.pool.tick_current, spacing, is_x_to_y);
+            let tick_next = tick_next.clamp(MIN_TICK, MAX_TICK);
+            let sqrt_price_next_tick =
+                sqrt_price_at_tick(tick_next).ok_or(AMMError::InvalidTickRange)?;
+            let sqrt_price_target = match is_x_to_y {
+                true => sqrt_price_next_tick.max(sqrt_price_limit_x64),
+                false => sqrt_price_next_tick.min(sqrt_price_limit_x64),
+            };
+
+            let step = compute_swap_step(
+                self.pool.sqrt_price_x64,
+                sqrt_price_target,
+                self.pool.liquidity,
+                amount_remaining,
+                self.pool.fee,
+            )
+            .ok_or(AMMError::Overflow)?;
+            amount_remaining -= step.amount_in + step.fee_amount;
+            amount_out = amount_out
+                .checked_add(step.amount_out)
+                .ok_or(AMMError::Overflow)?;
+            fee += step.fee_amount;
+            let growth = fee_growth(step.fee_amount, self.pool.liquidity);
+            match is_x_to_y {
+                true => {
+                    self.pool.fee_growth_global_x =
+                        self.pool.fee_growth_global_x.wrapping_add(growth)
+                }
+                false => {
+                    self.pool.fee_growth_global_y =
+                        self.pool.fee_growth_global_y.wrapping_add(growth)
+                }
+            }
+            self.pool.sqrt_price_x64 = step.sqrt_price_next;
+
+            match (step.sqrt_price_next == sqrt_price_next_tick, initialized) {
+                (true, true) => {
+                    let info = accounts.next().ok_or(AMMError::InvalidTickAccount)?;
+                    self.cross_tick(info, tick_next, is_x_to_y)?;
+                }
+                // Below a tick the current tick is the one before it.
+                (true, false) => {
+                    self.pool.tick_current = match is_x_to_y {
+                        true => tick_next - 1,
+                        false => tick_next,
+                    };
+                }
+                (false, _) => {
+                    self.pool.tick_current = tick_at_sqrt_price(self.pool.sqrt_price_x64)
+                        .ok_or(AMMError::InvalidSqrtPrice)?;
+                }
+            }
+        }
+
+        let amount_used = amount_in - amount_remaining;
+        require!(amount_out > 0, AMMError::ZeroOutput);
+
+        // The pool priced `amount_used`, so the user also covers the input
+        // transfer fee; slippage is checked on what the user receives.
+        let mint_in = match is_x_to_y {
+            true => self.mint_x.to_account_info(),
+            false => self.mint_y.to_account_info(),
+        };
+        let amount_paid = amount_with_transfer_fee(&mint_in, amount_used)?;
+        let received_in = self.deposit_tokens(is_x_to_y, amount_paid)?;
+        require!(received_in >= amount_used, AMMError::TransferFeeMismatch);
+        let received_out = self.withdraw_tokens(!is_x_to_y, amount_out)?;
+        require!(
+            received_out >= min_amount_out,
+            AMMError::ExceededMaxSlippage
+        );
+
+        emit!(ClSwapped {
+            pool: self.pool.key(),
+            user: self.user.key(),
+            is_x_to_y,
+            amount_in: amount_used,
+            amount_out,
+            fee,
+            sqrt_price_x64: self.pool.sqrt_price_x64,
+            tick_current: self.pool.tick_current,
+            liquidity: self.pool.liquidity,
+        });
+        Ok(())
+    }
+    fn cross_tick(
+        &mut self,
+        info: &'info AccountInfo<'info>,
+        index: i32,
+        downwards: bool,
+    ) -> Result<()> {
+        let mut tick = Account::<Tick>::try_from(info)?;
+        require_keys_eq!(tick.pool, self.pool.key(), AMMError::InvalidTickAccount);
+        require!(tick.index == index, AMMError::InvalidTickAccount);
+        require!(info.is_writable, AMMError::InvalidTickAccount);
+
+        let liquidity_net = self.pool.cross_tick(&mut tick);
+        let liquidity_delta = match downwards {
+            true => liquidity_net.checked_neg().ok_or(AMMError::Overflow)?,
+            false => liquidity_net,
+        };
+        self.pool.liquidity =
+            add_liquidity_delta(self.pool.liquidity, liquidity_delta).ok_or(AMMError::Overflow)?;
+        // Below a tick the current tick is the one before it.
+        self.pool.tick_current = match downwards {
+            true => index - 1,
+            false => index,
+        };
+        tick.exit(&crate::ID)
+    }
+    /// Reads bitmap `word`, which reads as empty while it has no account.
+    fn load_bitmap(&self, info: &'info AccountInfo<'info>, word: i32) -> Result<TickBitmap> {
+        let pool = self.pool.key();
+        if info.data_is_empty() {
+            let (address, bump) = Pubkey::find_program_address(
+                &[b"tick_bitmap", pool.as_ref(), word.to_le_bytes().as_ref()],
+                &crate::ID,
+            );
+            require_keys_eq!(info.key(), address, AMMError::InvalidTickAccount);
+            return Ok(TickBitmap {
+                pool,
+                word,
+                bits: [0; 4],
+                bump,
+            });
+        }
+        let bitmap = Account::<TickBitmap>::try_from(info)?;
+        require!(
+            bitmap.pool == pool && bitmap.word == word,
+            AMMError::InvalidTickAccount
+        );
+        Ok(bitmap.into_inner())
+    }
+    /// Returns the amount the vault actually received.
+    pub fn deposit_tokens(&mut self, is_x: bool, amount: u64) -> Result<u64> {
+        let (from, to, mint, decimals) = match is_x {
+            true => (
+                self.user_ata_x.to_account_info(),
+                self.vault_x.to_account_info(),
+                self.mint_x.to_account_info(),
+                self.mint_x.decimals,
+            ),
+            false => (
+                self.user_ata_y.to_account_info(),
+                self.vault_y.to_account_info(),
+                self.mint_y.to_account_info(),
+                self.mint_y.decimals,
+            ),
+        };
+        let cpi_accounts = TransferChecked {
+            from,
+            mint,
+            to,
+            authority: self.user.to_account_info(),
+        };
+        let cpi_ctx = CpiContext::new(self.token_program.to_account_info(), cpi_accounts);
+        transfer_checked(cpi_ctx, amount, decimals)?;
+
+        let vault = match is_x {
+            true => &mut self.vault_x,
+            false => &mut self.vault_y,
+        };
+        let before = vault.amount;
+        vault.reload()?;
+        Ok(vault.amount - before)
+    }
+    /// Returns the amount the user actually received.
+    pub fn withdraw_tokens(&mut self, is_x: bool, amount: u64) -> Result<u64> {
+        let (from, to, mint, decimals) = match is_x {
+            true => (
+                self.vault_x.to_account_info(),
+                self.user_ata_x.to_account_info(),
+                self.mint_x.to_account_info(),
+                self.mint_x.decimals,
+            ),
+            false => (
+                self.vault_y.to_account_info(),
+                self.user_ata_y.to_account_info(),
+                self.mint_y.to_account_info(),
+                self.mint_y.decimals,
+            ),
+        };
+        let cpi_accounts = TransferChecked {
+            from,
+            mint,
+            to,
+            authority: self.pool.to_account_info(),
+        };
+        let pool_seed = self.pool.seed.to_le_bytes();
+        let seeds: &[&[&[u8]]] = &[&[b"cl_pool", pool_seed.as_ref(), &[self.pool.bump]]];
+        let cpi_ctx =
+            CpiContext::new_with_signer(self.token_program.to_account_info(), cpi_accounts, seeds);
+        transfer_checked(cpi_ctx, amount, decimals)?;
+
+        let ata = match is_x {
+            true => &mut self.user_ata_x,
+            false => &mut self.user_ata_y,
+        };
+        let before = ata.amount;
+        ata.reload()?;
+        Ok(ata.amount - before)
+    }
+}
@@ -0,0 +1,78 @@
+use crate::error::AMMError;
+use crate::events::PositionClosed;
+use crate::state::{ClPool, Position, Tick};
+use anchor_lang::prelude::*;
+
+#[derive(Accounts)]
+pub struct ClosePosition<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    #[account(
+        seeds = [b"cl_pool", pool.seed.to_le_bytes().as_ref()],
+        bump = pool.bump
+    )]
+    pub pool: Box<Account<'info, ClPool>>,
+    #[account(
+        mut,
+        close = owner,
+        has_one = pool,
+        has_one = owner,
+        seeds = [
+            b"position",
+            pool.key().as_ref(),
+            owner.key().as_ref(),
+            position.tick_lower.to_le_bytes().as_ref(),
+            position.tick_upper.to_le_bytes().as_ref(),
+        ],
+        bump = position.bump
+    )]
+    pub position: Box<Account<'info, Position>>,
+    #[account(
+        mut,
+        seeds = [b"tick", pool.key().as_ref(), position.tick_lower.to_le_bytes().as_ref()],
+        bump = lower_tick.bump
+    )]
+    pub lower_tick: Box<Account<'info, Tick>>,
+    #[account(
+        mut,
+        seeds = [b"tick", pool.key().as_ref(), position.tick_upper.to_le_bytes().as_ref()],
+        bump = upper_tick.bump
+    )]
+    pub upper_tick: Box<Account<'info, Tick>>,
+    /// CHECK: Only receives the lower tick's rent.
+    #[account(mut, address = lower_tick.funder @ AMMError::InvalidTickAccount)]
+    pub lower_tick_funder: UncheckedAccount<'info>,
+    /// CHECK: Only receives the upper tick's rent.
+    #[account(mut, address = upper_tick.funder @ AMMError::InvalidTickAccount)]
+    pub upper_tick_funder: UncheckedAccount<'info>,
+}
+
+impl<'info> ClosePosition<'info> {
+    /// Closes an emptied position, refunding its rent to the owner. Ticks no
+    /// other position is bounded by are closed too, refunding whoever paid
+    /// for them.
+    pub fn close_position(&mut self) -> Result<()> {
+        require!(
+            self.position.liquidity == 0
+                && self.position.tokens_owed_x == 0
+                && self.position.tokens_owed_y == 0,
+            AMMError::PositionNotEmpty
+        );
+        for (tick, funder) in [
+            (&mut self.lower_tick, &self.lower_tick_funder),
+            (&mut self.upper_tick, &self.upper_tick_funder),
+        ] {
+            tick.position_count -= 1;
+            if tick.position_count == 0 {
+                tick.close(funder.to_account_info())?;
+            }
+        }
+
+        emit!(PositionClosed {
+            pool: self.pool.key(),
+            position: self.position.key(),
+            owner: self.owner.key(),
+        });
+        Ok(())
+    }
+}
@@ -0,0 +1,120 @@
+use crate::error::AMMError;
+use crate::events::ProtocolFeesCollected;
+use crate::state::Config;
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    associated_token::AssociatedToken,
+    token_interface::{transfer_checked, Mint, TokenAccount, TokenInterface, TransferChecked},
+};
+
+#[derive(Accounts)]
+pub struct CollectProtocolFees<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    /// CHECK: Only used as the owner of the treasury token accounts.
+    #[account(address = config.treasury @ AMMError::InvalidTreasury)]
+    pub treasury: UncheckedAccount<'info>,
+    #[account(mint::token_program = token_program_x)]
+    pub mint_x: Box<InterfaceAccount<'info, Mint>>,
+    #[account(mint::token_program = token_program_y)]
+    pub mint_y: Box<InterfaceAccount<'info, Mint>>,
+    #[account(
+        mut,
+        associated_token::mint = mint_x,
+        associated_token::authority = config,
+        associated_token::token_program = token_program_x,
+    )]
+    pub vault_x: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        mut,
+        associated_token::mint = mint_y,
+        associated_token::authority = config,
+        associated_token::token_program = token_program_y,
+    )]
+    pub vault_y: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        init_if_needed,
+        payer = authority,
+        associated_token::mint = mint_x,
+        associated_token::authority = treasury,
+        associated_token::token_program = token_program_x,
+    )]
+    pub treasury_ata_x: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        init_if_needed,
+        payer = authority,
+        associated_token::mint = mint_y,
+        associated_token::authority = treasury,
+        associated_token::token_program = token_program_y,
+    )]
+    pub treasury_ata_y: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        mut,
+        has_one = mint_x,
+        has_one = mint_y,
+        constraint = config.authority == Some(authority.key()) @ AMMError::Unauthorized,
+        seeds = [b"config", config.seed.to_le_bytes().as_ref()],
+        bump = config.config_bump
+    )]
+    pub config: Account<'info, Config>,
+
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub token_program_x: Interface<'info, TokenInterface>,
+    pub token_program_y: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> CollectProtocolFees<'info> {
+    pub fn collect_protocol_fees(&mut self) -> Result<()> {
+        require!(self.config.flash_loan.is_none(), AMMError::FlashLoanActive);
+        let (amount_x, amount_y) = (self.config.protocol_fees_x, self.config.protocol_fees_y);
+        self.config.protocol_fees_x = 0;
+        self.config.protocol_fees_y = 0;
+
+        if amount_x > 0 {
+            self.withdraw_tokens(true, amount_x)?;
+        }
+        if amount_y > 0 {
+            self.withdraw_tokens(false, amount_y)?;
+        }
+
+        emit!(ProtocolFeesCollected {
+            config: self.config.key(),
+            treasury: self.treasury.key(),
+            amount_x,
+            amount_y,
+        });
+        Ok(())
+    }
+    pub fn withdraw_tokens(&mut self, is_x: bool, amount: u64) -> Result<()> {
+        let (from, to, mint, decimals, token_program) = match is_x {
+            true => (
+                self.vault_x.to_account_info(),
+                self.treasury_ata_x.to_account_info(),
+                self.mint_x.to_account_info(),
+                self.mint_x.decimals,
+                self.token_program_x.to_account_info(),
+            ),
+            false => (
+                self.vault_y.to_account_info(),
+                self.treasury_ata_y.to_account_info(),
+                self.mint_y.to_account_info(),
+                self.mint_y.decimals,
+                self.token_program_y.to_account_info(),
+            ),
+        };
+        let cpi_accounts = TransferChecked {
+            from,
+            mint,
+            to,
+            authority: self.config.to_account_info(),
+        };
+        let config_seed = self.config.seed.to_le_bytes();
+        let signer_seeds: &[&[&[u8]]] =
+            &[&[b"config", config_seed.as_ref(), &[self.config.config_bump]]];
+
+        let cpi_ctx = CpiContext::new_with_signer(token_program, cpi_accounts, signer_seeds);
+        transfer_checked(cpi_ctx, amount, decimals)?;
+        Ok(())
+    }
+}
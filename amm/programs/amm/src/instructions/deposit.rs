@@ -1,35 +1,48 @@
+use crate::constants::{LOCKED_LIQUIDITY_OWNER, MINIMUM_LIQUIDITY};
+use crate::deadline::check_deadline;
 use crate::error::AMMError;
-use crate::state::Config;
+use crate::events::LiquidityAdded;
+use crate::math::{apply_fee, initial_lp, lp_for_deposit, protocol_fee_share, single_sided_swap};
+use crate::stable_swap;
+use crate::state::{Config, CurveType};
+use crate::transfer_fee::amount_with_transfer_fee;
+use amm_math::constant_product;
 use anchor_lang::prelude::*;
 use anchor_spl::{
     associated_token::AssociatedToken,
-    token::{mint_to, transfer, Mint, MintTo, Token, TokenAccount, Transfer},
+    token_interface::{
+        mint_to, transfer_checked, Mint, MintTo, TokenAccount, TokenInterface, TransferChecked,
+    },
 };
-use constant_product_curve::ConstantProduct;
 
 #[derive(Accounts)]
 pub struct Deposit<'info> {
-    pub mint_x: Box<Account<'info, Mint>>,
-    pub mint_y: Box<Account<'info, Mint>>,
+    #[account(mint::token_program = token_program_x)]
+    pub mint_x: Box<InterfaceAccount<'info, Mint>>,
+    #[account(mint::token_program = token_program_y)]
+    pub mint_y: Box<InterfaceAccount<'info, Mint>>,
     #[account(
         mut,
         seeds = [b"lp", config.key().as_ref()],
         bump = config.lp_bump
     )]
-    pub mint_lp: Box<Account<'info, Mint>>,
+    pub mint_lp: Box<InterfaceAccount<'info, Mint>>,
     #[account(
         mut,
         associated_token::mint = mint_x,
         associated_token::authority = config,
+        associated_token::token_program = token_program_x,
     )]
-    pub vault_x: Box<Account<'info, TokenAccount>>,
+    pub vault_x: Box<InterfaceAccount<'info, TokenAccount>>,
     #[account(
         mut,
         associated_token::mint = mint_y,
         associated_token::authority = config,
+        associated_token::token_program = token_program_y,
     )]
-    pub vault_y: Box<Account<'info, TokenAccount>>,
+    pub vault_y: Box<InterfaceAccount<'info, TokenAccount>>,
     #[account(
+        mut,
         has_one = mint_x,
         has_one = mint_y,
         seeds = [b"config", config.seed.to_le_bytes().as_ref()],
@@ -40,89 +53,259 @@ pub struct Deposit<'info> {
         mut,
         associated_token::mint = mint_x,
         associated_token::authority = depositer,
+        associated_token::token_program = token_program_x,
     )]
-    pub depositer_ata_x: Account<'info, TokenAccount>,
+    pub depositer_ata_x: InterfaceAccount<'info, TokenAccount>,
     #[account(
         mut,
         associated_token::mint = mint_y,
         associated_token::authority = depositer,
+        associated_token::token_program = token_program_y,
     )]
-    pub depositer_ata_y: Account<'info, TokenAccount>,
+    pub depositer_ata_y: InterfaceAccount<'info, TokenAccount>,
 
     #[account(
         init_if_needed,
         payer = depositer,
         associated_token::mint = mint_lp,
         associated_token::authority = depositer,
+        associated_token::token_program = token_program,
+    )]
+    pub depositer_ata_lp: InterfaceAccount<'info, TokenAccount>,
+    /// CHECK: Fixed burn address, only used as the owner of `locked_lp`.
+    #[account(address = LOCKED_LIQUIDITY_OWNER)]
+    pub locked_liquidity_owner: UncheckedAccount<'info>,
+    #[account(
+        init_if_needed,
+        payer = depositer,
+        associated_token::mint = mint_lp,
+        associated_token::authority = locked_liquidity_owner,
+        associated_token::token_program = token_program,
     )]
-    pub depositer_ata_lp: Account<'info, TokenAccount>,
+    pub locked_lp: Box<InterfaceAccount<'info, TokenAccount>>,
 
     #[account(mut)]
     pub depositer: Signer<'info>,
     pub system_program: Program<'info, System>,
-    pub token_program: Program<'info, Token>,
+    /// Program of the LP mint.
+    pub token_program: Interface<'info, TokenInterface>,
+    pub token_program_x: Interface<'info, TokenInterface>,
+    pub token_program_y: Interface<'info, TokenInterface>,
     pub associated_token_program: Program<'info, AssociatedToken>,
 }
 
 impl<'info> Deposit<'info> {
-    pub fn deposit_to_pool(&mut self, amount: u64, max_x: u64, max_y: u64) -> Result<()> {
-        require!(self.config.locked == false, AMMError::ConfigLocked);
+    pub fn deposit_to_pool(
+        &mut self,
+        amount: u64,
+        max_x: u64,
+        max_y: u64,
+        expires_at: Option<i64>,
+    ) -> Result<()> {
+        check_deadline(expires_at)?;
+        require!(!self.config.locked, AMMError::ConfigLocked);
+        require!(self.config.flash_loan.is_none(), AMMError::FlashLoanActive);
         require!(amount > 0, AMMError::InvalidAmount);
 
-        let (x, y) = match self.mint_lp.supply == 0
-            && self.vault_x.amount == 0
-            && self.vault_y.amount == 0
-        {
+        let (reserve_x, reserve_y) = self
+            .config
+            .reserves(self.vault_x.amount, self.vault_y.amount)?;
+        self.config.update_oracle()?;
+        // Tokens donated to the vaults before the first deposit simply go to
+        // the first depositor instead of bricking the pool.
+        let is_initial = self.mint_lp.supply == 0;
+        let (x, y) = match is_initial {
             true => (max_x, max_y),
             false => {
-                let amounts = match ConstantProduct::xy_deposit_amounts_from_l(
-                    self.vault_x.amount,
-                    self.vault_y.amount,
-                    self.mint_lp.supply,
-                    amount,
-                    6,
-                ) {
-                    Ok(a) => a,
-                    Err(_) => return err!(AMMError::InsufficientLiquidity),
-                };
-                (amounts.x, amounts.y)
+                constant_product::deposit_amounts(reserve_x, reserve_y, self.mint_lp.supply, amount)
+                    .ok_or(AMMError::Overflow)?
             }
         };
 
-        require!(x <= max_x && y <= max_y, AMMError::ExceededMaxSlippage);
-        self.deposit_tokens(true, x)?;
-        self.deposit_tokens(false, y)?;
-        self.mint_lp_tokens(amount)?;
+        // Gross up for transfer-fee mints so the vaults end up with exactly x and y.
+        let (x_in, y_in) = match is_initial {
+            true => (x, y),
+            false => (
+                amount_with_transfer_fee(&self.mint_x.to_account_info(), x)?,
+                amount_with_transfer_fee(&self.mint_y.to_account_info(), y)?,
+            ),
+        };
+        require!(
+            x_in <= max_x && y_in <= max_y,
+            AMMError::ExceededMaxSlippage
+        );
+        let received_x = self.deposit_tokens(true, x_in)?;
+        let received_y = self.deposit_tokens(false, y_in)?;
+        require!(
+            is_initial || (received_x >= x && received_y >= y),
+            AMMError::TransferFeeMismatch
+        );
 
-        Ok(())
+        if !is_initial {
+            self.mint_lp_tokens(false, amount)?;
+            return self.emit_liquidity_added(received_x, received_y, amount);
+        }
+
+        // The first depositor doesn't choose the LP amount: it is sqrt(x * y)
+        // for constant-product pools and the invariant D for StableSwap pools.
+        // MINIMUM_LIQUIDITY of it is locked forever so the LP price can't be
+        // inflated by donating to a nearly empty pool. `amount` is a minimum.
+        let lp_total = match self.config.curve_type {
+            CurveType::ConstantProduct => initial_lp(received_x, received_y),
+            CurveType::StableSwap => {
+                let amp = self.config.amp(Clock::get()?.unix_timestamp);
+                let d = stable_swap::compute_d(amp, received_x, received_y)
+                    .ok_or(AMMError::Overflow)?;
+                u64::try_from(d).map_err(|_| AMMError::Overflow)?
+            }
+        };
+        require!(
+            lp_total > MINIMUM_LIQUIDITY,
+            AMMError::InsufficientLiquidity
+        );
+        let lp_amount = lp_total - MINIMUM_LIQUIDITY;
+        require!(lp_amount >= amount, AMMError::ExceededMaxSlippage);
+
+        self.mint_lp_tokens(true, MINIMUM_LIQUIDITY)?;
+        self.mint_lp_tokens(false, lp_amount)?;
+        self.emit_liquidity_added(received_x, received_y, lp_total)
     }
-    pub fn deposit_tokens(&mut self, is_x: bool, amount: u64) -> Result<()> {
-        let (from, to) = match is_x {
+    /// Deposits only one side: the optimal portion is swapped through the
+    /// pool's own reserves (paying the swap fee) and the balanced remainder is
+    /// added as liquidity. Tokens only ever move into the vault.
+    pub fn deposit_single_sided(
+        &mut self,
+        is_x: bool,
+        amount_in: u64,
+        min_lp_out: u64,
+        expires_at: Option<i64>,
+    ) -> Result<()> {
+        check_deadline(expires_at)?;
+        require!(!self.config.locked, AMMError::ConfigLocked);
+        require!(self.config.flash_loan.is_none(), AMMError::FlashLoanActive);
+        require!(amount_in > 0, AMMError::InvalidAmount);
+
+        let (reserve_x, reserve_y) = self
+            .config
+            .reserves(self.vault_x.amount, self.vault_y.amount)?;
+        require!(
+            self.mint_lp.supply > 0 && reserve_x > 0 && reserve_y > 0,
+            AMMError::EmptyPool
+        );
+        self.config.update_oracle()?;
+
+        let received = self.deposit_tokens(is_x, amount_in)?;
+        let (reserve_in, reserve_out) = match is_x {
+            true => (reserve_x, reserve_y),
+            false => (reserve_y, reserve_x),
+        };
+        let fee = self.config.fee;
+        let (swap_in, swap_out) = match self.config.curve_type {
+            CurveType::ConstantProduct => {
+                single_sided_swap(reserve_in, reserve_out, received, |s| {
+                    let (s, _) = apply_fee(s, fee)?;
+                    constant_product::swap_amount_out(reserve_in, reserve_out, s)
+                })
+            }
+            CurveType::StableSwap => {
+                let amp = self.config.amp(Clock::get()?.unix_timestamp);
+                let d = stable_swap::compute_d(amp, reserve_in, reserve_out)
+                    .ok_or(AMMError::Overflow)?;
+                single_sided_swap(reserve_in, reserve_out, received, |s| {
+                    let (s, _) = apply_fee(s, fee)?;
+                    stable_swap::swap_amount_out_with_d(amp, d, reserve_in, reserve_out, s)
+                })
+            }
+        }
+        .ok_or(AMMError::InsufficientLiquidity)?;
+
+        // The protocol share of the swap fee leaves the LP reserves.
+        let (_, swap_fee) = apply_fee(swap_in, fee).ok_or(AMMError::InvalidFee)?;
+        let protocol_fee =
+            protocol_fee_share(swap_fee, self.config.protocol_fee).ok_or(AMMError::InvalidFee)?;
+        let lp_amount = lp_for_deposit(
+            self.mint_lp.supply,
+            reserve_in + swap_in - protocol_fee,
+            reserve_out - swap_out,
+            received - swap_in,
+            swap_out,
+        )
+        .ok_or(AMMError::Overflow)?;
+        require!(lp_amount > 0, AMMError::ZeroOutput);
+        require!(lp_amount >= min_lp_out, AMMError::ExceededMaxSlippage);
+
+        self.config.accrue_fee(is_x, swap_in)?;
+        self.mint_lp_tokens(false, lp_amount)?;
+        match is_x {
+            true => self.emit_liquidity_added(received, 0, lp_amount),
+            false => self.emit_liquidity_added(0, received, lp_amount),
+        }
+    }
+    /// Returns the amount the vault actually received.
+    pub fn deposit_tokens(&mut self, is_x: bool, amount: u64) -> Result<u64> {
+        let (from, to, mint, decimals, token_program) = match is_x {
             true => (
                 self.depositer_ata_x.to_account_info(),
                 self.vault_x.to_account_info(),
+                self.mint_x.to_account_info(),
+                self.mint_x.decimals,
+                self.token_program_x.to_account_info(),
             ),
             false => (
                 self.depositer_ata_y.to_account_info(),
                 self.vault_y.to_account_info(),
+                self.mint_y.to_account_info(),
+                self.mint_y.decimals,
+                self.token_program_y.to_account_info(),
             ),
         };
 
-        let cpi_accounts = Transfer {
+        let cpi_accounts = TransferChecked {
             from,
+            mint,
             to,
             authority: self.depositer.to_account_info(),
         };
 
-        let cpi_ctx = CpiContext::new(self.token_program.to_account_info(), cpi_accounts);
-        transfer(cpi_ctx, amount)?;
+        let cpi_ctx = CpiContext::new(token_program, cpi_accounts);
+        transfer_checked(cpi_ctx, amount, decimals)?;
 
+        let vault = match is_x {
+            true => &mut self.vault_x,
+            false => &mut self.vault_y,
+        };
+        let before = vault.amount;
+        vault.reload()?;
+        Ok(vault.amount - before)
+    }
+    /// Records and emits the post-deposit pool state; the vaults were reloaded
+    /// by deposit_tokens.
+    fn emit_liquidity_added(&mut self, amount_x: u64, amount_y: u64, lp_minted: u64) -> Result<()> {
+        self.mint_lp.reload()?;
+        let (reserve_x, reserve_y) = self
+            .config
+            .sync_reserves(self.vault_x.amount, self.vault_y.amount)?;
+        emit!(LiquidityAdded {
+            config: self.config.key(),
+            user: self.depositer.key(),
+            amount_x,
+            amount_y,
+            lp_minted,
+            reserve_x,
+            reserve_y,
+            lp_supply: self.mint_lp.supply,
+        });
         Ok(())
     }
-    pub fn mint_lp_tokens(&mut self, amount: u64) -> Result<()> {
+    /// Mints LP to the depositer, or to the permanently locked account.
+    pub fn mint_lp_tokens(&mut self, locked: bool, amount: u64) -> Result<()> {
+        let to = match locked {
+            true => self.locked_lp.to_account_info(),
+            false => self.depositer_ata_lp.to_account_info(),
+        };
         let cpi_accounts = MintTo {
             mint: self.mint_lp.to_account_info(),
-            to: self.depositer_ata_lp.to_account_info(),
+            to,
             authority: self.config.to_account_info(),
         };
         let config_seed = self.config.seed.to_le_bytes();
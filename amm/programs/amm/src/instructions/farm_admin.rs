@@ -0,0 +1,179 @@
+use crate::error::AMMError;
+use crate::events::{FarmEmissionUpdated, FarmFunded, FarmRewardsWithdrawn};
+use crate::state::{Config, Farm};
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::{
+    transfer_checked, Mint, TokenAccount, TokenInterface, TransferChecked,
+};
+
+#[derive(Accounts)]
+pub struct FundFarm<'info> {
+    pub authority: Signer<'info>,
+    #[account(
+        constraint = config.authority == Some(authority.key()) @ AMMError::Unauthorized,
+        seeds = [b"config", config.seed.to_le_bytes().as_ref()],
+        bump = config.config_bump
+    )]
+    pub config: Account<'info, Config>,
+    #[account(
+        mut,
+        has_one = config,
+        has_one = reward_mint,
+        seeds = [b"farm", config.key().as_ref()],
+        bump = farm.bump
+    )]
+    pub farm: Box<Account<'info, Farm>>,
+    pub reward_mint: Box<InterfaceAccount<'info, Mint>>,
+    #[account(
+        mut,
+        associated_token::mint = reward_mint,
+        associated_token::authority = farm,
+        associated_token::token_program = token_program,
+    )]
+    pub reward_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        mut,
+        associated_token::mint = reward_mint,
+        associated_token::authority = authority,
+        associated_token::token_program = token_program,
+    )]
+    pub authority_ata_reward: Box<InterfaceAccount<'info, TokenAccount>>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+impl<'info> FundFarm<'info> {
+    /// Adds rewards to be emitted. Only what the vault actually receives is
+    /// counted, so transfer-fee reward mints can't over-promise.
+    pub fn fund_farm(&mut self, amount: u64) -> Result<()> {
+        require!(amount > 0, AMMError::InvalidAmount);
+        self.farm.accrue(Clock::get()?.unix_timestamp);
+
+        let cpi_accounts = TransferChecked {
+            from: self.authority_ata_reward.to_account_info(),
+            mint: self.reward_mint.to_account_info(),
+            to: self.reward_vault.to_account_info(),
+            authority: self.authority.to_account_info(),
+        };
+        let cpi_ctx = CpiContext::new(self.token_program.to_account_info(), cpi_accounts);
+        transfer_checked(cpi_ctx, amount, self.reward_mint.decimals)?;
+        let before = self.reward_vault.amount;
+        self.reward_vault.reload()?;
+        let received = self.reward_vault.amount - before;
+
+        self.farm.rewards_remaining = self
+            .farm
+            .rewards_remaining
+            .checked_add(received)
+            .ok_or(AMMError::Overflow)?;
+
+        emit!(FarmFunded {
+            farm: self.farm.key(),
+            amount: received,
+            rewards_remaining: self.farm.rewards_remaining,
+        });
+        Ok(())
+    }
+}
+
+#[derive(Accounts)]
+pub struct WithdrawUnemitted<'info> {
+    pub authority: Signer<'info>,
+    #[account(
+        constraint = config.authority == Some(authority.key()) @ AMMError::Unauthorized,
+        seeds = [b"config", config.seed.to_le_bytes().as_ref()],
+        bump = config.config_bump
+    )]
+    pub config: Account<'info, Config>,
+    #[account(
+        mut,
+        has_one = config,
+        has_one = reward_mint,
+        seeds = [b"farm", config.key().as_ref()],
+        bump = farm.bump
+    )]
+    pub farm: Box<Account<'info, Farm>>,
+    pub reward_mint: Box<InterfaceAccount<'info, Mint>>,
+    #[account(
+        mut,
+        associated_token::mint = reward_mint,
+        associated_token::authority = farm,
+        associated_token::token_program = token_program,
+    )]
+    pub reward_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        mut,
+        associated_token::mint = reward_mint,
+        associated_token::authority = authority,
+        associated_token::token_program = token_program,
+    )]
+    pub authority_ata_reward: Box<InterfaceAccount<'info, TokenAccount>>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+impl<'info> WithdrawUnemitted<'info> {
+    /// Takes back funded rewards that have not been emitted yet; rewards
+    /// already emitted stay claimable by stakers.
+    pub fn withdraw_unemitted(&mut self, amount: u64) -> Result<()> {
+        require!(amount > 0, AMMError::InvalidAmount);
+        self.farm.accrue(Clock::get()?.unix_timestamp);
+        self.farm.rewards_remaining = self
+            .farm
+            .rewards_remaining
+            .checked_sub(amount)
+            .ok_or(AMMError::ExceedsUnemittedRewards)?;
+
+        let cpi_accounts = TransferChecked {
+            from: self.reward_vault.to_account_info(),
+            mint: self.reward_mint.to_account_info(),
+            to: self.authority_ata_reward.to_account_info(),
+            authority: self.farm.to_account_info(),
+        };
+        let signer_seeds: &[&[&[u8]]] = &[&[b"farm", self.farm.config.as_ref(), &[self.farm.bump]]];
+        let cpi_ctx = CpiContext::new_with_signer(
+            self.token_program.to_account_info(),
+            cpi_accounts,
+            signer_seeds,
+        );
+        transfer_checked(cpi_ctx, amount, self.reward_mint.decimals)?;
+
+        emit!(FarmRewardsWithdrawn {
+            farm: self.farm.key(),
+            amount,
+            rewards_remaining: self.farm.rewards_remaining,
+        });
+        Ok(())
+    }
+}
+
+#[derive(Accounts)]
+pub struct SetEmission<'info> {
+    pub authority: Signer<'info>,
+    #[account(
+        constraint = config.authority == Some(authority.key()) @ AMMError::Unauthorized,
+        seeds = [b"config", config.seed.to_le_bytes().as_ref()],
+        bump = config.config_bump
+    )]
+    pub config: Account<'info, Config>,
+    #[account(
+        mut,
+        has_one = config,
+        seeds = [b"farm", config.key().as_ref()],
+        bump = farm.bump
+    )]
+    pub farm: Box<Account<'info, Farm>>,
+}
+
+impl<'info> SetEmission<'info> {
+    /// The new rate only applies from now on; time already elapsed is
+    /// emitted at the old rate first.
+    pub fn set_emission(&mut self, emission_per_second: u64) -> Result<()> {
+        self.farm.accrue(Clock::get()?.unix_timestamp);
+        self.farm.emission_per_second = emission_per_second;
+
+        emit!(FarmEmissionUpdated {
+            farm: self.farm.key(),
+            emission_per_second,
+        });
+        Ok(())
+    }
+}
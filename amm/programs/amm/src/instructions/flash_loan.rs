@@ -0,0 +1,213 @@
+use crate::error::AMMError;
+use crate::events::{FlashBorrowed, FlashRepaid};
+use crate::state::{Config, FlashLoanState};
+use crate::transfer_fee::amount_with_transfer_fee;
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::sysvar::instructions::{
+    load_current_index_checked, load_instruction_at_checked,
+};
+use anchor_lang::InstructionData;
+use anchor_spl::token_interface::{
+    transfer_checked, Mint, TokenAccount, TokenInterface, TransferChecked,
+};
+
+/// Position of `config` in the accounts of both flash loan instructions.
+const CONFIG_ACCOUNT_INDEX: usize = 1;
+
+#[derive(Accounts)]
+pub struct FlashLoan<'info> {
+    pub borrower: Signer<'info>,
+    #[account(
+        mut,
+        has_one = mint_x,
+        has_one = mint_y,
+        seeds = [b"config", config.seed.to_le_bytes().as_ref()],
+        bump = config.config_bump
+    )]
+    pub config: Account<'info, Config>,
+    #[account(mint::token_program = token_program_x)]
+    pub mint_x: Box<InterfaceAccount<'info, Mint>>,
+    #[account(mint::token_program = token_program_y)]
+    pub mint_y: Box<InterfaceAccount<'info, Mint>>,
+    #[account(
+        mut,
+        associated_token::mint = mint_x,
+        associated_token::authority = config,
+        associated_token::token_program = token_program_x,
+    )]
+    pub vault_x: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        mut,
+        associated_token::mint = mint_y,
+        associated_token::authority = config,
+        associated_token::token_program = token_program_y,
+    )]
+    pub vault_y: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        mut,
+        associated_token::mint = mint_x,
+        associated_token::authority = borrower,
+        associated_token::token_program = token_program_x,
+    )]
+    pub borrower_ata_x: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        mut,
+        associated_token::mint = mint_y,
+        associated_token::authority = borrower,
+        associated_token::token_program = token_program_y,
+    )]
+    pub borrower_ata_y: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(address = anchor_lang::solana_program::sysvar::instructions::ID)]
+    /// CHECK: Checked by address
+    pub instruction_sysvar: UncheckedAccount<'info>,
+    pub token_program_x: Interface<'info, TokenInterface>,
+    pub token_program_y: Interface<'info, TokenInterface>,
+}
+
+impl<'info> FlashLoan<'info> {
+    /// Lends `amount` out of a vault. Only succeeds when a `flash_repay` for
+    /// the same pool, side and amount follows later in this transaction.
+    pub fn flash_borrow(&mut self, is_x: bool, amount: u64) -> Result<()> {
+        require!(!self.config.locked, AMMError::ConfigLocked);
+        require!(self.config.flash_loan.is_none(), AMMError::FlashLoanActive);
+        require!(amount > 0, AMMError::InvalidAmount);
+        self.config.update_oracle()?;
+        let (reserve_x, reserve_y) = self
+            .config
+            .reserves(self.vault_x.amount, self.vault_y.amount)?;
+        let reserve = match is_x {
+            true => reserve_x,
+            false => reserve_y,
+        };
+        require!(amount <= reserve, AMMError::InsufficientLiquidity);
+        self.verify_repay_follows(is_x, amount)?;
+
+        self.config.flash_loan = Some(FlashLoanState { is_x, amount });
+        self.withdraw_tokens(is_x, amount)?;
+
+        emit!(FlashBorrowed {
+            config: self.config.key(),
+            borrower: self.borrower.key(),
+            is_x,
+            amount,
+        });
+        Ok(())
+    }
+    /// Returns the loan plus the swap fee. The fee stays in the vault for LPs,
+    /// minus the protocol share.
+    pub fn flash_repay(&mut self, is_x: bool, amount: u64) -> Result<()> {
+        require!(
+            self.config.flash_loan == Some(FlashLoanState { is_x, amount }),
+            AMMError::FlashLoanMismatch
+        );
+        let fee = self.config.accrue_fee(is_x, amount)?;
+        let owed = amount.checked_add(fee).ok_or(AMMError::Overflow)?;
+        let mint = match is_x {
+            true => self.mint_x.to_account_info(),
+            false => self.mint_y.to_account_info(),
+        };
+        let amount_in = amount_with_transfer_fee(&mint, owed)?;
+        let received = self.deposit_tokens(is_x, amount_in)?;
+        require!(received >= owed, AMMError::TransferFeeMismatch);
+        self.config.flash_loan = None;
+        self.config
+            .sync_reserves(self.vault_x.amount, self.vault_y.amount)?;
+
+        emit!(FlashRepaid {
+            config: self.config.key(),
+            borrower: self.borrower.key(),
+            is_x,
+            amount,
+            fee,
+        });
+        Ok(())
+    }
+    fn verify_repay_follows(&self, is_x: bool, amount: u64) -> Result<()> {
+        let sysvar = self.instruction_sysvar.to_account_info();
+        let current = load_current_index_checked(&sysvar)?;
+        // Called through CPI the outer program could skip the repay check.
+        let ix = load_instruction_at_checked(current as usize, &sysvar)?;
+        require_keys_eq!(ix.program_id, crate::ID, AMMError::FlashLoanCpi);
+
+        let repay_data = crate::instruction::FlashRepay { is_x, amount }.data();
+        let mut index = current as usize + 1;
+        while let Ok(ix) = load_instruction_at_checked(index, &sysvar) {
+            if ix.program_id == crate::ID
+                && ix.data == repay_data
+                && ix
+                    .accounts
+                    .get(CONFIG_ACCOUNT_INDEX)
+                    .is_some_and(|meta| meta.pubkey == self.config.key())
+            {
+                return Ok(());
+            }
+            index += 1;
+        }
+        err!(AMMError::FlashLoanNotRepaid)
+    }
+    /// Returns the amount the vault actually received.
+    pub fn deposit_tokens(&mut self, is_x: bool, amount: u64) -> Result<u64> {
+        let (from, to, mint, decimals, token_program) = match is_x {
+            true => (
+                self.borrower_ata_x.to_account_info(),
+                self.vault_x.to_account_info(),
+                self.mint_x.to_account_info(),
+                self.mint_x.decimals,
+                self.token_program_x.to_account_info(),
+            ),
+            false => (
+                self.borrower_ata_y.to_account_info(),
+                self.vault_y.to_account_info(),
+                self.mint_y.to_account_info(),
+                self.mint_y.decimals,
+                self.token_program_y.to_account_info(),
+            ),
+        };
+        let cpi_accounts = TransferChecked {
+            from,
+            mint,
+            to,
+            authority: self.borrower.to_account_info(),
+        };
+        let cpi_ctx = CpiContext::new(token_program, cpi_accounts);
+        transfer_checked(cpi_ctx, amount, decimals)?;
+
+        let vault = match is_x {
+            true => &mut self.vault_x,
+            false => &mut self.vault_y,
+        };
+        let before = vault.amount;
+        vault.reload()?;
+        Ok(vault.amount - before)
+    }
+    pub fn withdraw_tokens(&mut self, is_x: bool, amount: u64) -> Result<()> {
+        let (from, to, mint, decimals, token_program) = match is_x {
+            true => (
+                self.vault_x.to_account_info(),
+                self.borrower_ata_x.to_account_info(),
+                self.mint_x.to_account_info(),
+                self.mint_x.decimals,
+                self.token_program_x.to_account_info(),
+            ),
+            false => (
+                self.vault_y.to_account_info(),
+                self.borrower_ata_y.to_account_info(),
+                self.mint_y.to_account_info(),
+                self.mint_y.decimals,
+                self.token_program_y.to_account_info(),
+            ),
+        };
+        let cpi_accounts = TransferChecked {
+            from,
+            mint,
+            to,
+            authority: self.config.to_account_info(),
+        };
+        let config_seed = self.config.seed.to_le_bytes();
+        let signer_seeds: &[&[&[u8]]] =
+            &[&[b"config", config_seed.as_ref(), &[self.config.config_bump]]];
+        let cpi_ctx = CpiContext::new_with_signer(token_program, cpi_accounts, signer_seeds);
+        transfer_checked(cpi_ctx, amount, decimals)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,80 @@
+use crate::events::RewardsHarvested;
+use crate::state::{Farm, LpStake};
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    associated_token::AssociatedToken,
+    token_interface::{transfer_checked, Mint, TokenAccount, TokenInterface, TransferChecked},
+};
+
+#[derive(Accounts)]
+pub struct Harvest<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    #[account(
+        mut,
+        has_one = reward_mint,
+        seeds = [b"farm", farm.config.as_ref()],
+        bump = farm.bump
+    )]
+    pub farm: Box<Account<'info, Farm>>,
+    #[account(
+        mut,
+        has_one = farm,
+        has_one = owner,
+        seeds = [b"stake", farm.key().as_ref(), owner.key().as_ref()],
+        bump = stake.bump
+    )]
+    pub stake: Box<Account<'info, LpStake>>,
+    pub reward_mint: Box<InterfaceAccount<'info, Mint>>,
+    #[account(
+        mut,
+        associated_token::mint = reward_mint,
+        associated_token::authority = farm,
+        associated_token::token_program = token_program,
+    )]
+    pub reward_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        init_if_needed,
+        payer = owner,
+        associated_token::mint = reward_mint,
+        associated_token::authority = owner,
+        associated_token::token_program = token_program,
+    )]
+    pub owner_ata_reward: Box<InterfaceAccount<'info, TokenAccount>>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> Harvest<'info> {
+    pub fn harvest(&mut self) -> Result<()> {
+        self.farm.accrue(Clock::get()?.unix_timestamp);
+        self.farm.settle(&mut self.stake)?;
+        let amount = self.stake.rewards_owed;
+        self.stake.rewards_owed = 0;
+
+        if amount > 0 {
+            let cpi_accounts = TransferChecked {
+                from: self.reward_vault.to_account_info(),
+                mint: self.reward_mint.to_account_info(),
+                to: self.owner_ata_reward.to_account_info(),
+                authority: self.farm.to_account_info(),
+            };
+            let signer_seeds: &[&[&[u8]]] =
+                &[&[b"farm", self.farm.config.as_ref(), &[self.farm.bump]]];
+            let cpi_ctx = CpiContext::new_with_signer(
+                self.token_program.to_account_info(),
+                cpi_accounts,
+                signer_seeds,
+            );
+            transfer_checked(cpi_ctx, amount, self.reward_mint.decimals)?;
+        }
+
+        emit!(RewardsHarvested {
+            farm: self.farm.key(),
+            owner: self.owner.key(),
+            amount,
+        });
+        Ok(())
+    }
+}
@@ -1,8 +1,11 @@
+use crate::constants::{FEE_DENOMINATOR, MAX_AMP, POOLS_PER_PAGE};
+use crate::error::AMMError;
+use crate::events::PoolInitialized;
 use crate::state::*;
 use anchor_lang::prelude::*;
 use anchor_spl::{
     associated_token::AssociatedToken,
-    token::{Mint, Token, TokenAccount},
+    token_interface::{Mint, TokenAccount, TokenInterface},
 };
 
 #[derive(Accounts)]
@@ -15,25 +18,30 @@ pub struct InitializeConfig<'info> {
         bump,
         mint::decimals = 6,
         mint::authority = config,
+        mint::token_program = token_program,
     )]
-    pub mint_lp: Account<'info, Mint>,
-    pub mint_x: Account<'info, Mint>,
-    pub mint_y: Account<'info, Mint>,
+    pub mint_lp: InterfaceAccount<'info, Mint>,
+    #[account(mint::token_program = token_program_x)]
+    pub mint_x: InterfaceAccount<'info, Mint>,
+    #[account(mint::token_program = token_program_y)]
+    pub mint_y: InterfaceAccount<'info, Mint>,
 
     #[account(
         init,
         payer = user,
         associated_token::mint = mint_x,
-        associated_token::authority = config
+        associated_token::authority = config,
+        associated_token::token_program = token_program_x
     )]
-    pub vault_x: Account<'info, TokenAccount>,
+    pub vault_x: InterfaceAccount<'info, TokenAccount>,
     #[account(
         init,
         payer = user,
         associated_token::mint = mint_y,
-        associated_token::authority = config
+        associated_token::authority = config,
+        associated_token::token_program = token_program_y
     )]
-    pub vault_y: Account<'info, TokenAccount>,
+    pub vault_y: InterfaceAccount<'info, TokenAccount>,
 
     #[account(
         init,
@@ -44,9 +52,42 @@ pub struct InitializeConfig<'info> {
     )]
     pub config: Account<'info, Config>,
 
+    /// One registry per sorted mint pair, so a second pool for the pair
+    /// fails here.
+    #[account(
+        init,
+        payer = user,
+        space = PoolRegistry::DISCRIMINATOR.len() + PoolRegistry::INIT_SPACE,
+        seeds = [b"registry", mint_x.key().as_ref(), mint_y.key().as_ref()],
+        bump
+    )]
+    pub registry: Box<Account<'info, PoolRegistry>>,
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = PoolDirectory::DISCRIMINATOR.len() + PoolDirectory::INIT_SPACE,
+        seeds = [b"pool_directory"],
+        bump
+    )]
+    pub pool_directory: Box<Account<'info, PoolDirectory>>,
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = PoolListPage::DISCRIMINATOR.len() + PoolListPage::INIT_SPACE,
+        seeds = [
+            b"pool_list",
+            (pool_directory.pool_count / POOLS_PER_PAGE as u64).to_le_bytes().as_ref(),
+        ],
+        bump
+    )]
+    pub pool_list_page: Box<Account<'info, PoolListPage>>,
+
     #[account(mut)]
     pub user: Signer<'info>,
-    pub token_program: Program<'info, Token>,
+    /// Program of the LP mint.
+    pub token_program: Interface<'info, TokenInterface>,
+    pub token_program_x: Interface<'info, TokenInterface>,
+    pub token_program_y: Interface<'info, TokenInterface>,
     pub associated_token_program: Program<'info, AssociatedToken>,
     pub system_program: Program<'info, System>,
 }
@@ -56,18 +97,88 @@ impl<'info> InitializeConfig<'info> {
         &mut self,
         seed: u64,
         fee: u16,
+        curve_type: CurveType,
+        amp: u64,
         bumps: &InitializeConfigBumps,
     ) -> Result<()> {
+        require!(
+            self.mint_x.key() < self.mint_y.key(),
+            AMMError::MintsNotSorted
+        );
+        require!(fee <= FEE_DENOMINATOR, AMMError::InvalidFee);
+        let amp = match curve_type {
+            CurveType::ConstantProduct => 0,
+            CurveType::StableSwap => {
+                require!((1..=MAX_AMP).contains(&amp), AMMError::InvalidAmp);
+                amp
+            }
+        };
+        let now = Clock::get()?.unix_timestamp;
         self.config.set_inner(Config {
-            seed: seed,
+            seed,
             authority: Some(self.user.key()),
+            pending_authority: None,
             mint_x: self.mint_x.key(),
             mint_y: self.mint_y.key(),
+            curve_type,
+            amp_initial: amp,
+            amp_target: amp,
+            amp_ramp_start_ts: now,
+            amp_ramp_end_ts: now,
             fee,
+            protocol_fee: 0,
+            protocol_fees_x: 0,
+            protocol_fees_y: 0,
+            treasury: self.user.key(),
             locked: false,
+            price_x_cumulative: 0,
+            price_y_cumulative: 0,
+            last_update_ts: now,
+            reserve_x_last: 0,
+            reserve_y_last: 0,
+            flash_loan: None,
             config_bump: bumps.config,
             lp_bump: bumps.mint_lp,
         });
+
+        self.registry.set_inner(PoolRegistry {
+            config: self.config.key(),
+            mint_x: self.mint_x.key(),
+            mint_y: self.mint_y.key(),
+            bump: bumps.registry,
+        });
+        let pool_index = self.register_pool(bumps)?;
+
+        emit!(PoolInitialized {
+            config: self.config.key(),
+            registry: self.registry.key(),
+            pool_index,
+            authority: self.user.key(),
+            seed,
+            mint_x: self.mint_x.key(),
+            mint_y: self.mint_y.key(),
+            mint_lp: self.mint_lp.key(),
+            curve_type,
+            amp,
+            fee,
+        });
         Ok(())
     }
+    /// Appends the pool to the current list page and returns its index.
+    fn register_pool(&mut self, bumps: &InitializeConfigBumps) -> Result<u64> {
+        let pool_index = self.pool_directory.pool_count;
+        if pool_index == 0 {
+            self.pool_directory.bump = bumps.pool_directory;
+        }
+        if pool_index % POOLS_PER_PAGE as u64 == 0 {
+            self.pool_list_page.set_inner(PoolListPage {
+                page: pool_index / POOLS_PER_PAGE as u64,
+                pools: Vec::with_capacity(POOLS_PER_PAGE),
+                bump: bumps.pool_list_page,
+            });
+        }
+        self.pool_list_page.pools.push(self.config.key());
+        self.pool_directory.pool_count += 1;
+        Ok(pool_index)
+    }
 }
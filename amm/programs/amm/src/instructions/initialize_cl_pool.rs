@@ -0,0 +1,93 @@
+use crate::cl_math::tick_at_sqrt_price;
+use crate::constants::FEE_DENOMINATOR;
+use crate::error::AMMError;
+use crate::events::ClPoolInitialized;
+use crate::state::ClPool;
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    associated_token::AssociatedToken,
+    token_interface::{Mint, TokenAccount, TokenInterface},
+};
+
+#[derive(Accounts)]
+#[instruction(seed: u64)]
+pub struct InitializeClPool<'info> {
+    #[account(mint::token_program = token_program)]
+    pub mint_x: Box<InterfaceAccount<'info, Mint>>,
+    #[account(mint::token_program = token_program)]
+    pub mint_y: Box<InterfaceAccount<'info, Mint>>,
+
+    #[account(
+        init,
+        payer = user,
+        associated_token::mint = mint_x,
+        associated_token::authority = pool,
+        associated_token::token_program = token_program
+    )]
+    pub vault_x: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        init,
+        payer = user,
+        associated_token::mint = mint_y,
+        associated_token::authority = pool,
+        associated_token::token_program = token_program
+    )]
+    pub vault_y: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        init,
+        payer = user,
+        space = ClPool::DISCRIMINATOR.len() + ClPool::INIT_SPACE,
+        seeds = [b"cl_pool", seed.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub pool: Box<Account<'info, ClPool>>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> InitializeClPool<'info> {
+    pub fn init_cl_pool(
+        &mut self,
+        seed: u64,
+        fee: u16,
+        tick_spacing: u16,
+        sqrt_price_x64: u128,
+        bumps: &InitializeClPoolBumps,
+    ) -> Result<()> {
+        // The swap step divides by `FEE_DENOMINATOR - fee`.
+        require!(fee < FEE_DENOMINATOR, AMMError::InvalidFee);
+        require!(tick_spacing > 0, AMMError::InvalidTickRange);
+        let tick_current = tick_at_sqrt_price(sqrt_price_x64).ok_or(AMMError::InvalidSqrtPrice)?;
+
+        self.pool.set_inner(ClPool {
+            seed,
+            mint_x: self.mint_x.key(),
+            mint_y: self.mint_y.key(),
+            fee,
+            tick_spacing,
+            sqrt_price_x64,
+            tick_current,
+            liquidity: 0,
+            fee_growth_global_x: 0,
+            fee_growth_global_y: 0,
+            bump: bumps.pool,
+        });
+
+        emit!(ClPoolInitialized {
+            pool: self.pool.key(),
+            seed,
+            mint_x: self.mint_x.key(),
+            mint_y: self.mint_y.key(),
+            fee,
+            tick_spacing,
+            sqrt_price_x64,
+            tick_current,
+        });
+        Ok(())
+    }
+}
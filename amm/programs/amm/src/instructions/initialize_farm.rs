@@ -0,0 +1,86 @@
+use crate::error::AMMError;
+use crate::events::FarmInitialized;
+use crate::state::{Config, Farm};
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    associated_token::AssociatedToken,
+    token_interface::{Mint, TokenAccount, TokenInterface},
+};
+
+#[derive(Accounts)]
+pub struct InitializeFarm<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    #[account(
+        constraint = config.authority == Some(authority.key()) @ AMMError::Unauthorized,
+        seeds = [b"config", config.seed.to_le_bytes().as_ref()],
+        bump = config.config_bump
+    )]
+    pub config: Account<'info, Config>,
+    #[account(
+        seeds = [b"lp", config.key().as_ref()],
+        bump = config.lp_bump,
+        mint::token_program = lp_token_program,
+    )]
+    pub mint_lp: Box<InterfaceAccount<'info, Mint>>,
+    #[account(mint::token_program = reward_token_program)]
+    pub reward_mint: Box<InterfaceAccount<'info, Mint>>,
+    #[account(
+        init,
+        payer = authority,
+        space = Farm::DISCRIMINATOR.len() + Farm::INIT_SPACE,
+        seeds = [b"farm", config.key().as_ref()],
+        bump
+    )]
+    pub farm: Box<Account<'info, Farm>>,
+    #[account(
+        init,
+        payer = authority,
+        associated_token::mint = mint_lp,
+        associated_token::authority = farm,
+        associated_token::token_program = lp_token_program
+    )]
+    pub lp_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        init,
+        payer = authority,
+        associated_token::mint = reward_mint,
+        associated_token::authority = farm,
+        associated_token::token_program = reward_token_program
+    )]
+    pub reward_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+    pub lp_token_program: Interface<'info, TokenInterface>,
+    pub reward_token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> InitializeFarm<'info> {
+    /// Opens an unfunded farm for the pool's LP; rewards only start flowing
+    /// once `fund_farm` tops up the reward vault.
+    pub fn init_farm(
+        &mut self,
+        emission_per_second: u64,
+        bumps: &InitializeFarmBumps,
+    ) -> Result<()> {
+        self.farm.set_inner(Farm {
+            config: self.config.key(),
+            mint_lp: self.mint_lp.key(),
+            reward_mint: self.reward_mint.key(),
+            emission_per_second,
+            rewards_remaining: 0,
+            reward_per_share_x64: 0,
+            total_staked: 0,
+            last_update_ts: Clock::get()?.unix_timestamp,
+            bump: bumps.farm,
+        });
+
+        emit!(FarmInitialized {
+            farm: self.farm.key(),
+            config: self.config.key(),
+            reward_mint: self.reward_mint.key(),
+            emission_per_second,
+        });
+        Ok(())
+    }
+}
@@ -0,0 +1,307 @@
+use crate::cl_math::{amounts_for_liquidity, sqrt_price_at_tick};
+use crate::error::AMMError;
+use crate::events::{PositionFeesCollected, PositionLiquidityUpdated};
+use crate::state::{ClPool, Position, Tick, TickBitmap};
+use crate::transfer_fee::amount_with_transfer_fee;
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::{
+    transfer_checked, Mint, TokenAccount, TokenInterface, TransferChecked,
+};
+
+#[derive(Accounts)]
+pub struct ModifyPosition<'info> {
+    pub owner: Signer<'info>,
+    #[account(
+        mut,
+        has_one = mint_x,
+        has_one = mint_y,
+        seeds = [b"cl_pool", pool.seed.to_le_bytes().as_ref()],
+        bump = pool.bump
+    )]
+    pub pool: Box<Account<'info, ClPool>>,
+    #[account(
+        mut,
+        has_one = pool,
+        has_one = owner,
+        seeds = [
+            b"position",
+            pool.key().as_ref(),
+            owner.key().as_ref(),
+            position.tick_lower.to_le_bytes().as_ref(),
+            position.tick_upper.to_le_bytes().as_ref(),
+        ],
+        bump = position.bump
+    )]
+    pub position: Box<Account<'info, Position>>,
+    #[account(
+        mut,
+        seeds = [b"tick", pool.key().as_ref(), position.tick_lower.to_le_bytes().as_ref()],
+        bump = lower_tick.bump
+    )]
+    pub lower_tick: Box<Account<'info, Tick>>,
+    #[account(
+        mut,
+        seeds = [b"tick", pool.key().as_ref(), position.tick_upper.to_le_bytes().as_ref()],
+        bump = upper_tick.bump
+    )]
+    pub upper_tick: Box<Account<'info, Tick>>,
+    #[account(
+        mut,
+        seeds = [
+            b"tick_bitmap",
+            pool.key().as_ref(),
+            TickBitmap::word_of(position.tick_lower, pool.tick_spacing).to_le_bytes().as_ref(),
+        ],
+        bump = lower_bitmap.bump
+    )]
+    pub lower_bitmap: Box<Account<'info, TickBitmap>>,
+    /// Only passed when the upper tick falls in a different word.
+    #[account(
+        mut,
+        seeds = [
+            b"tick_bitmap",
+            pool.key().as_ref(),
+            TickBitmap::word_of(position.tick_upper, pool.tick_spacing).to_le_bytes().as_ref(),
+        ],
+        bump = upper_bitmap.bump
+    )]
+    pub upper_bitmap: Option<Box<Account<'info, TickBitmap>>>,
+    pub mint_x: Box<InterfaceAccount<'info, Mint>>,
+    pub mint_y: Box<InterfaceAccount<'info, Mint>>,
+    #[account(
+        mut,
+        associated_token::mint = mint_x,
+        associated_token::authority = pool,
+        associated_token::token_program = token_program,
+    )]
+    pub vault_x: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        mut,
+        associated_token::mint = mint_y,
+        associated_token::authority = pool,
+        associated_token::token_program = token_program,
+    )]
+    pub vault_y: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        mut,
+        associated_token::mint = mint_x,
+        associated_token::authority = owner,
+        associated_token::token_program = token_program,
+    )]
+    pub owner_ata_x: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        mut,
+        associated_token::mint = mint_y,
+        associated_token::authority = owner,
+        associated_token::token_program = token_program,
+    )]
+    pub owner_ata_y: Box<InterfaceAccount<'info, TokenAccount>>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+impl<'info> ModifyPosition<'info> {
+    pub fn increase_liquidity(&mut self, liquidity: u128, max_x: u64, max_y: u64) -> Result<()> {
+        require!(liquidity > 0, AMMError::InvalidAmount);
+        let liquidity_delta = i128::try_from(liquidity).map_err(|_| AMMError::Overflow)?;
+        self.update_position(liquidity_delta)?;
+        let (x, y) = self.amounts_for(liquidity, true)?;
+
+        // Gross up for transfer-fee mints so the vaults end up with exactly x and y.
+        let x_in = amount_with_transfer_fee(&self.mint_x.to_account_info(), x)?;
+        let y_in = amount_with_transfer_fee(&self.mint_y.to_account_info(), y)?;
+        require!(
+            x_in <= max_x && y_in <= max_y,
+            AMMError::ExceededMaxSlippage
+        );
+        if x_in > 0 {
+            let received_x = self.deposit_tokens(true, x_in)?;
+            require!(received_x >= x, AMMError::TransferFeeMismatch);
+        }
+        if y_in > 0 {
+            let received_y = self.deposit_tokens(false, y_in)?;
+            require!(received_y >= y, AMMError::TransferFeeMismatch);
+        }
+
+        self.emit_liquidity_updated(liquidity_delta, x, y);
+        Ok(())
+    }
+    /// Returns the principal straight to the owner; earned fees stay owed
+    /// until `collect_fees`.
+    pub fn decrease_liquidity(&mut self, liquidity: u128, min_x: u64, min_y: u64) -> Result<()> {
+        require!(liquidity > 0, AMMError::InvalidAmount);
+        require!(
+            liquidity <= self.position.liquidity,
+            AMMError::InsufficientLiquidity
+        );
+        let liquidity_delta = -i128::try_from(liquidity).map_err(|_| AMMError::Overflow)?;
+        self.update_position(liquidity_delta)?;
+        let (x, y) = self.amounts_for(liquidity, false)?;
+
+        let received_x = match x {
+            0 => 0,
+            _ => self.withdraw_tokens(true, x)?,
+        };
+        let received_y = match y {
+            0 => 0,
+            _ => self.withdraw_tokens(false, y)?,
+        };
+        require!(
+            received_x >= min_x && received_y >= min_y,
+            AMMError::ExceededMaxSlippage
+        );
+
+        self.emit_liquidity_updated(liquidity_delta, x, y);
+        Ok(())
+    }
+    pub fn collect_fees(&mut self) -> Result<()> {
+        // Settle fees earned since the position was last touched.
+        self.update_position(0)?;
+        let (amount_x, amount_y) = (self.position.tokens_owed_x, self.position.tokens_owed_y);
+        self.position.tokens_owed_x = 0;
+        self.position.tokens_owed_y = 0;
+
+        if amount_x > 0 {
+            self.withdraw_tokens(true, amount_x)?;
+        }
+        if amount_y > 0 {
+            self.withdraw_tokens(false, amount_y)?;
+        }
+
+        emit!(PositionFeesCollected {
+            pool: self.pool.key(),
+            position: self.position.key(),
+            owner: self.owner.key(),
+            amount_x,
+            amount_y,
+        });
+        Ok(())
+    }
+    /// Applies `liquidity_delta` to the position and marks the ticks it
+    /// flipped in their bitmap words.
+    fn update_position(&mut self, liquidity_delta: i128) -> Result<()> {
+        let spacing = self.pool.tick_spacing;
+        let (tick_lower, tick_upper) = (self.position.tick_lower, self.position.tick_upper);
+        // Two copies of one bitmap account would overwrite each other on exit.
+        require!(
+            self.upper_bitmap.is_some()
+                == (TickBitmap::word_of(tick_lower, spacing)
+                    != TickBitmap::word_of(tick_upper, spacing)),
+            AMMError::InvalidTickAccount
+        );
+        let (flipped_lower, flipped_upper) = self.pool.update_position(
+            &mut self.position,
+            &mut self.lower_tick,
+            &mut self.upper_tick,
+            liquidity_delta,
+        )?;
+        if flipped_lower {
+            self.lower_bitmap.flip(tick_lower, spacing)?;
+        }
+        if flipped_upper {
+            match self.upper_bitmap.as_mut() {
+                Some(upper_bitmap) => upper_bitmap.flip(tick_upper, spacing)?,
+                None => self.lower_bitmap.flip(tick_upper, spacing)?,
+            }
+        }
+        Ok(())
+    }
+    fn amounts_for(&self, liquidity: u128, round_up: bool) -> Result<(u64, u64)> {
+        let sqrt_lower =
+            sqrt_price_at_tick(self.position.tick_lower).ok_or(AMMError::InvalidTickRange)?;
+        let sqrt_upper =
+            sqrt_price_at_tick(self.position.tick_upper).ok_or(AMMError::InvalidTickRange)?;
+        Ok(amounts_for_liquidity(
+            self.pool.sqrt_price_x64,
+            sqrt_lower,
+            sqrt_upper,
+            liquidity,
+            round_up,
+        )
+        .ok_or(AMMError::Overflow)?)
+    }
+    fn emit_liquidity_updated(&self, liquidity_delta: i128, amount_x: u64, amount_y: u64) {
+        emit!(PositionLiquidityUpdated {
+            pool: self.pool.key(),
+            position: self.position.key(),
+            owner: self.owner.key(),
+            tick_lower: self.position.tick_lower,
+            tick_upper: self.position.tick_upper,
+            liquidity_delta,
+            amount_x,
+            amount_y,
+            pool_liquidity: self.pool.liquidity,
+        });
+    }
+    /// Returns the amount the vault actually received.
+    pub fn deposit_tokens(&mut self, is_x: bool, amount: u64) -> Result<u64> {
+        let (from, to, mint, decimals) = match is_x {
+            true => (
+                self.owner_ata_x.to_account_info(),
+                self.vault_x.to_account_info(),
+                self.mint_x.to_account_info(),
+                self.mint_x.decimals,
+            ),
+            false => (
+                self.owner_ata_y.to_account_info(),
+                self.vault_y.to_account_info(),
+                self.mint_y.to_account_info(),
+                self.mint_y.decimals,
+            ),
+        };
+        let cpi_accounts = TransferChecked {
+            from,
+            mint,
+            to,
+            authority: self.owner.to_account_info(),
+        };
+        let cpi_ctx = CpiContext::new(self.token_program.to_account_info(), cpi_accounts);
+        transfer_checked(cpi_ctx, amount, decimals)?;
+
+        let vault = match is_x {
+            true => &mut self.vault_x,
+            false => &mut self.vault_y,
+        };
+        let before = vault.amount;
+        vault.reload()?;
+        Ok(vault.amount - before)
+    }
+    /// Returns the amount the owner actually received.
+    pub fn withdraw_tokens(&mut self, is_x: bool, amount: u64) -> Result<u64> {
+        let (from, to, mint, decimals) = match is_x {
+            true => (
+                self.vault_x.to_account_info(),
+                self.owner_ata_x.to_account_info(),
+                self.mint_x.to_account_info(),
+                self.mint_x.decimals,
+            ),
+            false => (
+                self.vault_y.to_account_info(),
+                self.owner_ata_y.to_account_info(),
+                self.mint_y.to_account_info(),
+                self.mint_y.decimals,
+            ),
+        };
+        let cpi_accounts = TransferChecked {
+            from,
+            mint,
+            to,
+            authority: self.pool.to_account_info(),
+        };
+        let pool_seed = self.pool.seed.to_le_bytes();
+        let signer_seeds: &[&[&[u8]]] = &[&[b"cl_pool", pool_seed.as_ref(), &[self.pool.bump]]];
+        let cpi_ctx = CpiContext::new_with_signer(
+            self.token_program.to_account_info(),
+            cpi_accounts,
+            signer_seeds,
+        );
+        transfer_checked(cpi_ctx, amount, decimals)?;
+
+        let ata = match is_x {
+            true => &mut self.owner_ata_x,
+            false => &mut self.owner_ata_y,
+        };
+        let before = ata.amount;
+        ata.reload()?;
+        Ok(ata.amount - before)
+    }
+}
@@ -0,0 +1,23 @@
+use crate::error::AMMError;
+use crate::state::{Config, Observation};
+use anchor_lang::prelude::*;
+
+#[derive(Accounts)]
+pub struct Observe<'info> {
+    #[account(
+        seeds = [b"config", config.seed.to_le_bytes().as_ref()],
+        bump = config.config_bump
+    )]
+    pub config: Account<'info, Config>,
+}
+
+impl<'info> Observe<'info> {
+    /// Returned through `set_return_data`, so consumer programs can CPI into
+    /// `observe` at two points in time and divide the accumulator delta by the
+    /// elapsed seconds to get a TWAP.
+    pub fn observe(&self) -> Result<Observation> {
+        require!(self.config.flash_loan.is_none(), AMMError::FlashLoanActive);
+        let now = Clock::get()?.unix_timestamp;
+        Ok(self.config.observe(now))
+    }
+}
@@ -0,0 +1,158 @@
+use crate::constants::{MAX_TICK, MIN_TICK};
+use crate::error::AMMError;
+use crate::state::{ClPool, Position, Tick, TickBitmap};
+use anchor_lang::prelude::*;
+
+#[derive(Accounts)]
+#[instruction(tick_lower: i32, tick_upper: i32)]
+pub struct OpenPosition<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    #[account(
+        seeds = [b"cl_pool", pool.seed.to_le_bytes().as_ref()],
+        bump = pool.bump
+    )]
+    pub pool: Box<Account<'info, ClPool>>,
+    #[account(
+        init,
+        payer = owner,
+        space = Position::DISCRIMINATOR.len() + Position::INIT_SPACE,
+        seeds = [
+            b"position",
+            pool.key().as_ref(),
+            owner.key().as_ref(),
+            tick_lower.to_le_bytes().as_ref(),
+            tick_upper.to_le_bytes().as_ref(),
+        ],
+        bump
+    )]
+    pub position: Box<Account<'info, Position>>,
+    #[account(
+        init_if_needed,
+        payer = owner,
+        space = Tick::DISCRIMINATOR.len() + Tick::INIT_SPACE,
+        seeds = [b"tick", pool.key().as_ref(), tick_lower.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub lower_tick: Box<Account<'info, Tick>>,
+    #[account(
+        init_if_needed,
+        payer = owner,
+        space = Tick::DISCRIMINATOR.len() + Tick::INIT_SPACE,
+        seeds = [b"tick", pool.key().as_ref(), tick_upper.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub upper_tick: Box<Account<'info, Tick>>,
+    #[account(
+        init_if_needed,
+        payer = owner,
+        space = TickBitmap::DISCRIMINATOR.len() + TickBitmap::INIT_SPACE,
+        seeds = [
+            b"tick_bitmap",
+            pool.key().as_ref(),
+            TickBitmap::word_of(tick_lower, pool.tick_spacing).to_le_bytes().as_ref(),
+        ],
+        bump
+    )]
+    pub lower_bitmap: Box<Account<'info, TickBitmap>>,
+    /// Only passed when the upper tick falls in a different word.
+    #[account(
+        init_if_needed,
+        payer = owner,
+        space = TickBitmap::DISCRIMINATOR.len() + TickBitmap::INIT_SPACE,
+        seeds = [
+            b"tick_bitmap",
+            pool.key().as_ref(),
+            TickBitmap::word_of(tick_upper, pool.tick_spacing).to_le_bytes().as_ref(),
+        ],
+        bump
+    )]
+    pub upper_bitmap: Option<Box<Account<'info, TickBitmap>>>,
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> OpenPosition<'info> {
+    /// Creates an empty position over `[tick_lower, tick_upper)`; liquidity is
+    /// added with `increase_liquidity`.
+    pub fn open_position(
+        &mut self,
+        tick_lower: i32,
+        tick_upper: i32,
+        bumps: &OpenPositionBumps,
+    ) -> Result<()> {
+        let spacing = self.pool.tick_spacing as i32;
+        require!(
+            MIN_TICK <= tick_lower
+                && tick_lower < tick_upper
+                && tick_upper <= MAX_TICK
+                && tick_lower % spacing == 0
+                && tick_upper % spacing == 0,
+            AMMError::InvalidTickRange
+        );
+
+        let (pool, owner) = (self.pool.key(), self.owner.key());
+        let lower_word = TickBitmap::word_of(tick_lower, self.pool.tick_spacing);
+        let upper_word = TickBitmap::word_of(tick_upper, self.pool.tick_spacing);
+        require!(
+            self.upper_bitmap.is_some() == (lower_word != upper_word),
+            AMMError::InvalidTickAccount
+        );
+        // Bitmap words are created by the first position with a tick in them
+        // and stay open, as any later position may need them.
+        if self.lower_bitmap.pool == Pubkey::default() {
+            self.lower_bitmap.set_inner(TickBitmap {
+                pool,
+                word: lower_word,
+                bits: [0; 4],
+                bump: bumps.lower_bitmap,
+            });
+        }
+        if let (Some(upper_bitmap), Some(bump)) = (self.upper_bitmap.as_mut(), bumps.upper_bitmap) {
+            if upper_bitmap.pool == Pubkey::default() {
+                upper_bitmap.set_inner(TickBitmap {
+                    pool,
+                    word: upper_word,
+                    bits: [0; 4],
+                    bump,
+                });
+            }
+        }
+        for (tick, index, bump) in [
+            (&mut self.lower_tick, tick_lower, bumps.lower_tick),
+            (&mut self.upper_tick, tick_upper, bumps.upper_tick),
+        ] {
+            // Ticks are shared between positions and only set up once.
+            if tick.pool == Pubkey::default() {
+                tick.set_inner(Tick {
+                    pool,
+                    index,
+                    liquidity_gross: 0,
+                    liquidity_net: 0,
+                    fee_growth_outside_x: 0,
+                    fee_growth_outside_y: 0,
+                    funder: owner,
+                    position_count: 0,
+                    bump,
+                });
+            }
+            tick.position_count = tick
+                .position_count
+                .checked_add(1)
+                .ok_or(AMMError::Overflow)?;
+        }
+
+        self.position.set_inner(Position {
+            pool,
+            owner,
+            tick_lower,
+            tick_upper,
+            liquidity: 0,
+            fee_growth_inside_x_last: 0,
+            fee_growth_inside_y_last: 0,
+            tokens_owed_x: 0,
+            tokens_owed_y: 0,
+            bump: bumps.position,
+        });
+        Ok(())
+    }
+}
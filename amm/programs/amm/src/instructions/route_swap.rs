@@ -0,0 +1,218 @@
+use crate::deadline::check_deadline;
+use crate::error::AMMError;
+use crate::events::Swapped;
+use crate::instructions::swap_amount_out;
+use crate::math::apply_fee;
+use crate::state::Config;
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    associated_token::get_associated_token_address_with_program_id,
+    token_interface::{transfer_checked, Mint, TokenAccount, TokenInterface, TransferChecked},
+};
+
+/// Accounts expected in `remaining_accounts` for every hop, in this order:
+/// config, mint_in, mint_out, vault_in, vault_out, user_ata_in, user_ata_out,
+/// token_program_in, token_program_out.
+pub const ACCOUNTS_PER_HOP: usize = 9;
+
+#[derive(Accounts)]
+pub struct RouteSwap<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+}
+
+struct Hop<'info> {
+    config: Account<'info, Config>,
+    mint_in: InterfaceAccount<'info, Mint>,
+    mint_out: InterfaceAccount<'info, Mint>,
+    vault_in: InterfaceAccount<'info, TokenAccount>,
+    vault_out: InterfaceAccount<'info, TokenAccount>,
+    user_ata_in: InterfaceAccount<'info, TokenAccount>,
+    user_ata_out: InterfaceAccount<'info, TokenAccount>,
+    token_program_in: Interface<'info, TokenInterface>,
+    token_program_out: Interface<'info, TokenInterface>,
+    is_x_to_y: bool,
+}
+
+impl<'info> RouteSwap<'info> {
+    pub fn route_swap(
+        &mut self,
+        remaining_accounts: &'info [AccountInfo<'info>],
+        amount_in: u64,
+        min_amount_out: u64,
+        expires_at: Option<i64>,
+    ) -> Result<()> {
+        check_deadline(expires_at)?;
+        require!(amount_in > 0, AMMError::InvalidAmount);
+        require!(
+            !remaining_accounts.is_empty() && remaining_accounts.len() % ACCOUNTS_PER_HOP == 0,
+            AMMError::InvalidRoute
+        );
+
+        let mut amount = amount_in;
+        let mut previous_ata_out: Option<Pubkey> = None;
+        for accounts in remaining_accounts.chunks(ACCOUNTS_PER_HOP) {
+            let mut hop = self.load_hop(accounts)?;
+            // Each hop spends exactly what the previous one delivered.
+            if let Some(previous) = previous_ata_out {
+                require_keys_eq!(previous, hop.user_ata_in.key(), AMMError::InvalidRoute);
+            }
+            previous_ata_out = Some(hop.user_ata_out.key());
+            amount = self.swap_hop(&mut hop, amount)?;
+        }
+
+        // Intermediate hops carry no slippage check, only the end-to-end output.
+        require!(amount >= min_amount_out, AMMError::ExceededMaxSlippage);
+        Ok(())
+    }
+
+    fn load_hop(&self, accounts: &'info [AccountInfo<'info>]) -> Result<Hop<'info>> {
+        let config = Account::<Config>::try_from(&accounts[0])?;
+        let config_seed = config.seed.to_le_bytes();
+        let expected = Pubkey::create_program_address(
+            &[b"config", config_seed.as_ref(), &[config.config_bump]],
+            &crate::ID,
+        )
+        .map_err(|_| AMMError::InvalidRoute)?;
+        require_keys_eq!(expected, config.key(), AMMError::InvalidRoute);
+        require!(!config.locked, AMMError::ConfigLocked);
+        require!(config.flash_loan.is_none(), AMMError::FlashLoanActive);
+
+        let mint_in = InterfaceAccount::<Mint>::try_from(&accounts[1])?;
+        let mint_out = InterfaceAccount::<Mint>::try_from(&accounts[2])?;
+        let is_x_to_y = if config.mint_x == mint_in.key() && config.mint_y == mint_out.key() {
+            true
+        } else if config.mint_y == mint_in.key() && config.mint_x == mint_out.key() {
+            false
+        } else {
+            return err!(AMMError::InvalidRoute);
+        };
+
+        // Each side of a pool may be SPL Token or Token-2022.
+        let token_program_in = Interface::<TokenInterface>::try_from(&accounts[7])?;
+        let token_program_out = Interface::<TokenInterface>::try_from(&accounts[8])?;
+        for (vault, mint, token_program) in [
+            (&accounts[3], &mint_in, &token_program_in),
+            (&accounts[4], &mint_out, &token_program_out),
+        ] {
+            require_keys_eq!(
+                *mint.to_account_info().owner,
+                token_program.key(),
+                AMMError::InvalidRoute
+            );
+            require_keys_eq!(
+                vault.key(),
+                get_associated_token_address_with_program_id(
+                    &config.key(),
+                    &mint.key(),
+                    &token_program.key()
+                ),
+                AMMError::InvalidRoute
+            );
+        }
+        let vault_in = InterfaceAccount::<TokenAccount>::try_from(&accounts[3])?;
+        let vault_out = InterfaceAccount::<TokenAccount>::try_from(&accounts[4])?;
+
+        let user_ata_in = InterfaceAccount::<TokenAccount>::try_from(&accounts[5])?;
+        let user_ata_out = InterfaceAccount::<TokenAccount>::try_from(&accounts[6])?;
+        require!(
+            user_ata_in.mint == mint_in.key() && user_ata_out.mint == mint_out.key(),
+            AMMError::InvalidRoute
+        );
+        require_keys_eq!(user_ata_out.owner, self.user.key(), AMMError::InvalidRoute);
+
+        Ok(Hop {
+            config,
+            mint_in,
+            mint_out,
+            vault_in,
+            vault_out,
+            user_ata_in,
+            user_ata_out,
+            token_program_in,
+            token_program_out,
+            is_x_to_y,
+        })
+    }
+
+    /// Swaps `amount_in` through one pool and returns what the user received.
+    fn swap_hop(&self, hop: &mut Hop<'info>, amount_in: u64) -> Result<u64> {
+        let (vault_x, vault_y) = match hop.is_x_to_y {
+            true => (hop.vault_in.amount, hop.vault_out.amount),
+            false => (hop.vault_out.amount, hop.vault_in.amount),
+        };
+        let (reserve_x, reserve_y) = hop.config.reserves(vault_x, vault_y)?;
+        hop.config.update_oracle()?;
+
+        let cpi_accounts = TransferChecked {
+            from: hop.user_ata_in.to_account_info(),
+            mint: hop.mint_in.to_account_info(),
+            to: hop.vault_in.to_account_info(),
+            authority: self.user.to_account_info(),
+        };
+        let cpi_ctx = CpiContext::new(hop.token_program_in.to_account_info(), cpi_accounts);
+        transfer_checked(cpi_ctx, amount_in, hop.mint_in.decimals)?;
+        let before = hop.vault_in.amount;
+        hop.vault_in.reload()?;
+        let received_in = hop.vault_in.amount - before;
+
+        let (amount_in_after_fee, _) =
+            apply_fee(received_in, hop.config.fee).ok_or(AMMError::InvalidFee)?;
+        let amount_out = swap_amount_out(
+            &hop.config,
+            reserve_x,
+            reserve_y,
+            hop.is_x_to_y,
+            amount_in_after_fee,
+            Clock::get()?.unix_timestamp,
+        )?;
+
+        let cpi_accounts = TransferChecked {
+            from: hop.vault_out.to_account_info(),
+            mint: hop.mint_out.to_account_info(),
+            to: hop.user_ata_out.to_account_info(),
+            authority: hop.config.to_account_info(),
+        };
+        let config_seed = hop.config.seed.to_le_bytes();
+        let seeds: &[&[&[u8]]] = &[&[b"config", config_seed.as_ref(), &[hop.config.config_bump]]];
+        let cpi_ctx = CpiContext::new_with_signer(
+            hop.token_program_out.to_account_info(),
+            cpi_accounts,
+            seeds,
+        );
+        transfer_checked(cpi_ctx, amount_out, hop.mint_out.decimals)?;
+        let before = hop.user_ata_out.amount;
+        hop.user_ata_out.reload()?;
+        let received_out = hop.user_ata_out.amount - before;
+
+        let protocol_fees_before = hop.config.protocol_fees_x + hop.config.protocol_fees_y;
+        let fee = hop.config.accrue_fee(hop.is_x_to_y, received_in)?;
+        let protocol_fee =
+            hop.config.protocol_fees_x + hop.config.protocol_fees_y - protocol_fees_before;
+
+        hop.vault_out.reload()?;
+        let (reserve_x, reserve_y) = match hop.is_x_to_y {
+            true => hop
+                .config
+                .sync_reserves(hop.vault_in.amount, hop.vault_out.amount)?,
+            false => hop
+                .config
+                .sync_reserves(hop.vault_out.amount, hop.vault_in.amount)?,
+        };
+        hop.config.exit(&crate::ID)?;
+
+        emit!(Swapped {
+            config: hop.config.key(),
+            user: self.user.key(),
+            is_x_to_y: hop.is_x_to_y,
+            amount_in: received_in,
+            amount_out,
+            fee,
+            protocol_fee,
+            reserve_x,
+            reserve_y,
+        });
+
+        Ok(received_out)
+    }
+}
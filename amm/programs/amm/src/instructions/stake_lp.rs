@@ -0,0 +1,127 @@
+use crate::error::AMMError;
+use crate::events::{LpStaked, LpUnstaked};
+use crate::state::{Farm, LpStake};
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::{
+    transfer_checked, Mint, TokenAccount, TokenInterface, TransferChecked,
+};
+
+#[derive(Accounts)]
+pub struct StakeLp<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    #[account(
+        mut,
+        has_one = mint_lp,
+        seeds = [b"farm", farm.config.as_ref()],
+        bump = farm.bump
+    )]
+    pub farm: Box<Account<'info, Farm>>,
+    pub mint_lp: Box<InterfaceAccount<'info, Mint>>,
+    #[account(
+        init_if_needed,
+        payer = owner,
+        space = LpStake::DISCRIMINATOR.len() + LpStake::INIT_SPACE,
+        seeds = [b"stake", farm.key().as_ref(), owner.key().as_ref()],
+        bump
+    )]
+    pub stake: Box<Account<'info, LpStake>>,
+    #[account(
+        mut,
+        associated_token::mint = mint_lp,
+        associated_token::authority = owner,
+        associated_token::token_program = token_program,
+    )]
+    pub owner_ata_lp: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        mut,
+        associated_token::mint = mint_lp,
+        associated_token::authority = farm,
+        associated_token::token_program = token_program,
+    )]
+    pub lp_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> StakeLp<'info> {
+    pub fn stake_lp(&mut self, amount: u64, bumps: &StakeLpBumps) -> Result<()> {
+        require!(amount > 0, AMMError::InvalidAmount);
+        if self.stake.owner == Pubkey::default() {
+            self.stake.set_inner(LpStake {
+                farm: self.farm.key(),
+                owner: self.owner.key(),
+                amount: 0,
+                reward_per_share_last_x64: 0,
+                rewards_owed: 0,
+                bump: bumps.stake,
+            });
+        }
+        self.update_rewards()?;
+
+        let cpi_accounts = TransferChecked {
+            from: self.owner_ata_lp.to_account_info(),
+            mint: self.mint_lp.to_account_info(),
+            to: self.lp_vault.to_account_info(),
+            authority: self.owner.to_account_info(),
+        };
+        let cpi_ctx = CpiContext::new(self.token_program.to_account_info(), cpi_accounts);
+        transfer_checked(cpi_ctx, amount, self.mint_lp.decimals)?;
+
+        self.stake.amount = self
+            .stake
+            .amount
+            .checked_add(amount)
+            .ok_or(AMMError::Overflow)?;
+        self.farm.total_staked = self
+            .farm
+            .total_staked
+            .checked_add(amount)
+            .ok_or(AMMError::Overflow)?;
+
+        emit!(LpStaked {
+            farm: self.farm.key(),
+            owner: self.owner.key(),
+            amount,
+            staked: self.stake.amount,
+            total_staked: self.farm.total_staked,
+        });
+        Ok(())
+    }
+    /// Rewards earned so far stay owed on the stake until `harvest`.
+    pub fn unstake_lp(&mut self, amount: u64) -> Result<()> {
+        require!(amount > 0, AMMError::InvalidAmount);
+        require!(amount <= self.stake.amount, AMMError::InsufficientStake);
+        self.update_rewards()?;
+
+        let cpi_accounts = TransferChecked {
+            from: self.lp_vault.to_account_info(),
+            mint: self.mint_lp.to_account_info(),
+            to: self.owner_ata_lp.to_account_info(),
+            authority: self.farm.to_account_info(),
+        };
+        let signer_seeds: &[&[&[u8]]] = &[&[b"farm", self.farm.config.as_ref(), &[self.farm.bump]]];
+        let cpi_ctx = CpiContext::new_with_signer(
+            self.token_program.to_account_info(),
+            cpi_accounts,
+            signer_seeds,
+        );
+        transfer_checked(cpi_ctx, amount, self.mint_lp.decimals)?;
+
+        self.stake.amount -= amount;
+        self.farm.total_staked -= amount;
+
+        emit!(LpUnstaked {
+            farm: self.farm.key(),
+            owner: self.owner.key(),
+            amount,
+            staked: self.stake.amount,
+            total_staked: self.farm.total_staked,
+        });
+        Ok(())
+    }
+    fn update_rewards(&mut self) -> Result<()> {
+        self.farm.accrue(Clock::get()?.unix_timestamp);
+        self.farm.settle(&mut self.stake)
+    }
+}
@@ -1,43 +1,55 @@
+use crate::deadline::check_deadline;
+use crate::error::AMMError;
+use crate::events::Swapped;
+use crate::math::{amount_before_fee, apply_fee};
 use crate::state::Config;
+use crate::transfer_fee::amount_with_transfer_fee;
+use amm_math::quote::{curve_amount_in, curve_amount_out};
 use anchor_lang::prelude::*;
 use anchor_spl::{
     associated_token::AssociatedToken,
-    token::{transfer, Mint, Token, TokenAccount, Transfer},
+    token_interface::{transfer_checked, Mint, TokenAccount, TokenInterface, TransferChecked},
 };
-use constant_product_curve::ConstantProduct;
 
 #[derive(Accounts)]
 pub struct Swap<'info> {
     #[account(mut)]
     pub user: Signer<'info>,
-    pub mint_x: Account<'info, Mint>,
-    pub mint_y: Account<'info, Mint>,
+    #[account(mint::token_program = token_program_x)]
+    pub mint_x: Box<InterfaceAccount<'info, Mint>>,
+    #[account(mint::token_program = token_program_y)]
+    pub mint_y: Box<InterfaceAccount<'info, Mint>>,
     #[account(
         mut,
         associated_token::mint = mint_x,
         associated_token::authority = config,
+        associated_token::token_program = token_program_x,
     )]
-    pub vault_x: Account<'info, TokenAccount>,
+    pub vault_x: Box<InterfaceAccount<'info, TokenAccount>>,
     #[account(
         mut,
         associated_token::mint = mint_y,
         associated_token::authority = config,
+        associated_token::token_program = token_program_y,
     )]
-    pub vault_y: Account<'info, TokenAccount>,
+    pub vault_y: Box<InterfaceAccount<'info, TokenAccount>>,
     #[account(
         mut,
         associated_token::mint = mint_x,
-        associated_token::authority = user
+        associated_token::authority = user,
+        associated_token::token_program = token_program_x,
     )]
-    pub user_ata_x: Account<'info, TokenAccount>,
+    pub user_ata_x: InterfaceAccount<'info, TokenAccount>,
     #[account(
         mut,
         associated_token::mint = mint_y,
-        associated_token::authority = user
+        associated_token::authority = user,
+        associated_token::token_program = token_program_y,
     )]
-    pub user_ata_y: Account<'info, TokenAccount>,
+    pub user_ata_y: InterfaceAccount<'info, TokenAccount>,
 
     #[account(
+        mut,
         has_one = mint_x,
         has_one = mint_y,
         seeds = [b"config", config.seed.to_le_bytes().as_ref()],
@@ -46,7 +58,8 @@ pub struct Swap<'info> {
     pub config: Account<'info, Config>,
 
     pub associated_token_program: Program<'info, AssociatedToken>,
-    pub token_program: Program<'info, Token>,
+    pub token_program_x: Interface<'info, TokenInterface>,
+    pub token_program_y: Interface<'info, TokenInterface>,
     pub system_program: Program<'info, System>,
 }
 
@@ -56,73 +69,215 @@ impl<'info> Swap<'info> {
         is_x_to_y: bool,
         amount_in: u64,
         min_amount_out: u64,
+        expires_at: Option<i64>,
     ) -> Result<()> {
-        let amount_out = match is_x_to_y {
-            true => ConstantProduct::delta_y_from_x_swap_amount(
-                self.vault_x.amount,
-                self.vault_y.amount,
-                amount_in,
-            )
-            .unwrap(),
-            false => ConstantProduct::delta_x_from_y_swap_amount(
-                self.vault_x.amount,
-                self.vault_y.amount,
-                amount_in,
-            )
-            .unwrap(),
-        };
+        check_deadline(expires_at)?;
+        require!(!self.config.locked, AMMError::ConfigLocked);
+        require!(self.config.flash_loan.is_none(), AMMError::FlashLoanActive);
+        require!(amount_in > 0, AMMError::InvalidAmount);
+        let (reserve_x, reserve_y) = self
+            .config
+            .reserves(self.vault_x.amount, self.vault_y.amount)?;
+        self.config.update_oracle()?;
+
+        // Price against what the vault actually received, which is less than
+        // amount_in for transfer-fee mints.
+        let received_in = self.deposit_tokens(is_x_to_y, amount_in)?;
+        let (amount_in_after_fee, _) =
+            apply_fee(received_in, self.config.fee).ok_or(AMMError::InvalidFee)?;
+        let amount_out = swap_amount_out(
+            &self.config,
+            reserve_x,
+            reserve_y,
+            is_x_to_y,
+            amount_in_after_fee,
+            Clock::get()?.unix_timestamp,
+        )?;
+        let received_out = self.withdraw_tokens(!is_x_to_y, amount_out)?;
         require!(
-            amount_out >= min_amount_out,
-            crate::error::AMMError::ExceededMaxSlippage
+            received_out >= min_amount_out,
+            AMMError::ExceededMaxSlippage
         );
 
-        self.deposit_tokens(is_x_to_y, amount_in)?;
-        self.withdraw_tokens(!is_x_to_y, amount_out)?;
+        self.settle(is_x_to_y, received_in, amount_out)
+    }
+    pub fn swap_exact_out(
+        &mut self,
+        is_x_to_y: bool,
+        amount_out: u64,
+        max_amount_in: u64,
+        expires_at: Option<i64>,
+    ) -> Result<()> {
+        check_deadline(expires_at)?;
+        require!(!self.config.locked, AMMError::ConfigLocked);
+        require!(self.config.flash_loan.is_none(), AMMError::FlashLoanActive);
+        require!(amount_out > 0, AMMError::InvalidAmount);
+        let (reserve_x, reserve_y) = self
+            .config
+            .reserves(self.vault_x.amount, self.vault_y.amount)?;
+        self.config.update_oracle()?;
+        let (reserve_in, reserve_out, mint_in, mint_out) = match is_x_to_y {
+            true => (
+                reserve_x,
+                reserve_y,
+                self.mint_x.to_account_info(),
+                self.mint_y.to_account_info(),
+            ),
+            false => (
+                reserve_y,
+                reserve_x,
+                self.mint_y.to_account_info(),
+                self.mint_x.to_account_info(),
+            ),
+        };
+        // The user receives exactly amount_out, so the vault has to send the
+        // output transfer fee on top and be paid the input transfer fee.
+        let vault_out = amount_with_transfer_fee(&mint_out, amount_out)?;
+        let amount_in_after_fee = swap_amount_in(&self.config, reserve_in, reserve_out, vault_out)?;
+        let vault_in =
+            amount_before_fee(amount_in_after_fee, self.config.fee).ok_or(AMMError::InvalidFee)?;
+        let amount_in = amount_with_transfer_fee(&mint_in, vault_in)?;
+        require!(amount_in <= max_amount_in, AMMError::ExceededMaxSlippage);
+
+        let received_in = self.deposit_tokens(is_x_to_y, amount_in)?;
+        require!(received_in >= vault_in, AMMError::TransferFeeMismatch);
+        self.withdraw_tokens(!is_x_to_y, vault_out)?;
+
+        self.settle(is_x_to_y, received_in, vault_out)
+    }
+    fn settle(&mut self, is_x_to_y: bool, amount_in: u64, amount_out: u64) -> Result<()> {
+        let protocol_fees_before = self.config.protocol_fees_x + self.config.protocol_fees_y;
+        let fee = self.config.accrue_fee(is_x_to_y, amount_in)?;
+        let protocol_fee =
+            self.config.protocol_fees_x + self.config.protocol_fees_y - protocol_fees_before;
+
+        // deposit_tokens already reloaded the input vault.
+        match is_x_to_y {
+            true => self.vault_y.reload()?,
+            false => self.vault_x.reload()?,
+        }
+        let (reserve_x, reserve_y) = self
+            .config
+            .sync_reserves(self.vault_x.amount, self.vault_y.amount)?;
+
+        emit!(Swapped {
+            config: self.config.key(),
+            user: self.user.key(),
+            is_x_to_y,
+            amount_in,
+            amount_out,
+            fee,
+            protocol_fee,
+            reserve_x,
+            reserve_y,
+        });
 
         Ok(())
     }
-    pub fn deposit_tokens(&mut self, is_x: bool, amount: u64) -> Result<()> {
-        let (from, to) = match is_x {
+    /// Returns the amount the vault actually received.
+    pub fn deposit_tokens(&mut self, is_x: bool, amount: u64) -> Result<u64> {
+        let (from, to, mint, decimals, token_program) = match is_x {
             true => (
                 self.user_ata_x.to_account_info(),
                 self.vault_x.to_account_info(),
+                self.mint_x.to_account_info(),
+                self.mint_x.decimals,
+                self.token_program_x.to_account_info(),
             ),
             false => (
                 self.user_ata_y.to_account_info(),
                 self.vault_y.to_account_info(),
+                self.mint_y.to_account_info(),
+                self.mint_y.decimals,
+                self.token_program_y.to_account_info(),
             ),
         };
 
-        let cpi_accounts = Transfer {
+        let cpi_accounts = TransferChecked {
             from,
+            mint,
             to,
             authority: self.user.to_account_info(),
         };
-        let cpi_ctx = CpiContext::new(self.token_program.to_account_info(), cpi_accounts);
-        transfer(cpi_ctx, amount)?;
-        Ok(())
+        let cpi_ctx = CpiContext::new(token_program, cpi_accounts);
+        transfer_checked(cpi_ctx, amount, decimals)?;
+
+        let vault = match is_x {
+            true => &mut self.vault_x,
+            false => &mut self.vault_y,
+        };
+        let before = vault.amount;
+        vault.reload()?;
+        Ok(vault.amount - before)
     }
-    pub fn withdraw_tokens(&mut self, is_x: bool, amount: u64) -> Result<()> {
-        let (from, to) = match is_x {
+    /// Returns the amount the user actually received.
+    pub fn withdraw_tokens(&mut self, is_x: bool, amount: u64) -> Result<u64> {
+        let (from, to, mint, decimals, token_program) = match is_x {
             true => (
                 self.vault_x.to_account_info(),
                 self.user_ata_x.to_account_info(),
+                self.mint_x.to_account_info(),
+                self.mint_x.decimals,
+                self.token_program_x.to_account_info(),
             ),
             false => (
                 self.vault_y.to_account_info(),
                 self.user_ata_y.to_account_info(),
+                self.mint_y.to_account_info(),
+                self.mint_y.decimals,
+                self.token_program_y.to_account_info(),
             ),
         };
-        let cpi_accounts = Transfer {
+        let cpi_accounts = TransferChecked {
             from,
+            mint,
             to,
             authority: self.config.to_account_info(),
         };
         let config_seed = self.config.seed.to_le_bytes();
         let seeds: &[&[&[u8]]] = &[&[b"config", config_seed.as_ref(), &[self.config.config_bump]]];
-        let cpi_ctx =
-            CpiContext::new_with_signer(self.token_program.to_account_info(), cpi_accounts, seeds);
-        transfer(cpi_ctx, amount)?;
-        Ok(())
+        let cpi_ctx = CpiContext::new_with_signer(token_program, cpi_accounts, seeds);
+        transfer_checked(cpi_ctx, amount, decimals)?;
+
+        let ata = match is_x {
+            true => &mut self.user_ata_x,
+            false => &mut self.user_ata_y,
+        };
+        let before = ata.amount;
+        ata.reload()?;
+        Ok(ata.amount - before)
     }
 }
+
+/// Curve output for `amount_in_after_fee` against the given LP reserves, with
+/// the curve as it stands at `now`.
+pub fn swap_amount_out(
+    config: &Config,
+    reserve_x: u64,
+    reserve_y: u64,
+    is_x_to_y: bool,
+    amount_in_after_fee: u64,
+    now: i64,
+) -> Result<u64> {
+    let (reserve_in, reserve_out) = match is_x_to_y {
+        true => (reserve_x, reserve_y),
+        false => (reserve_y, reserve_x),
+    };
+    let curve = config.curve(now);
+    let amount_out = curve_amount_out(curve, reserve_in, reserve_out, amount_in_after_fee)
+        .map_err(AMMError::from)?;
+    Ok(amount_out)
+}
+
+/// Post-fee input the curve needs to release exactly `amount_out`.
+pub fn swap_amount_in(
+    config: &Config,
+    reserve_in: u64,
+    reserve_out: u64,
+    amount_out: u64,
+) -> Result<u64> {
+    let curve = config.curve(Clock::get()?.unix_timestamp);
+    let amount_in =
+        curve_amount_in(curve, reserve_in, reserve_out, amount_out).map_err(AMMError::from)?;
+    Ok(amount_in)
+}
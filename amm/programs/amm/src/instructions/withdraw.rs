@@ -1,56 +1,68 @@
+use crate::deadline::check_deadline;
 use crate::error::AMMError;
+use crate::events::LiquidityRemoved;
 use crate::state::Config;
+use amm_math::constant_product;
 use anchor_lang::prelude::*;
 use anchor_spl::{
     associated_token::AssociatedToken,
-    token::{burn, transfer, Burn, Mint, Token, TokenAccount, Transfer},
+    token_interface::{
+        burn, transfer_checked, Burn, Mint, TokenAccount, TokenInterface, TransferChecked,
+    },
 };
-use constant_product_curve::ConstantProduct;
 
 #[derive(Accounts)]
 pub struct Withdraw<'info> {
     #[account(mut)]
     pub withdrawer: Signer<'info>,
-    pub mint_x: Box<Account<'info, Mint>>,
-    pub mint_y: Box<Account<'info, Mint>>,
+    #[account(mint::token_program = token_program_x)]
+    pub mint_x: Box<InterfaceAccount<'info, Mint>>,
+    #[account(mint::token_program = token_program_y)]
+    pub mint_y: Box<InterfaceAccount<'info, Mint>>,
     #[account(
         mut,
         seeds = [b"lp", config.key().as_ref()],
         bump = config.lp_bump
     )]
-    pub mint_lp: Box<Account<'info, Mint>>,
+    pub mint_lp: Box<InterfaceAccount<'info, Mint>>,
     #[account(
         mut,
         associated_token::mint = mint_x,
         associated_token::authority = config,
+        associated_token::token_program = token_program_x,
     )]
-    pub vault_x: Box<Account<'info, TokenAccount>>,
+    pub vault_x: Box<InterfaceAccount<'info, TokenAccount>>,
     #[account(
         mut,
         associated_token::mint = mint_y,
         associated_token::authority = config,
+        associated_token::token_program = token_program_y,
     )]
-    pub vault_y: Box<Account<'info, TokenAccount>>,
+    pub vault_y: Box<InterfaceAccount<'info, TokenAccount>>,
     #[account(
         mut,
         associated_token::mint = mint_x,
-        associated_token::authority = withdrawer
+        associated_token::authority = withdrawer,
+        associated_token::token_program = token_program_x,
     )]
-    pub withdrawer_ata_x: Account<'info, TokenAccount>,
+    pub withdrawer_ata_x: InterfaceAccount<'info, TokenAccount>,
     #[account(
         mut,
         associated_token::mint = mint_y,
-        associated_token::authority = withdrawer
+        associated_token::authority = withdrawer,
+        associated_token::token_program = token_program_y,
     )]
-    pub withdrawer_ata_y: Account<'info, TokenAccount>,
+    pub withdrawer_ata_y: InterfaceAccount<'info, TokenAccount>,
 
     #[account(
         mut,
         associated_token::mint = mint_lp,
-        associated_token::authority = withdrawer
+        associated_token::authority = withdrawer,
+        associated_token::token_program = token_program,
     )]
-    pub withdrawer_ata_lp: Account<'info, TokenAccount>,
+    pub withdrawer_ata_lp: InterfaceAccount<'info, TokenAccount>,
     #[account(
+        mut,
         has_one = mint_x,
         has_one = mint_y,
         seeds = [b"config", config.seed.to_le_bytes().as_ref()],
@@ -59,50 +71,88 @@ pub struct Withdraw<'info> {
     pub config: Account<'info, Config>,
 
     pub associated_token_program: Program<'info, AssociatedToken>,
-    pub token_program: Program<'info, Token>,
+    /// Program of the LP mint.
+    pub token_program: Interface<'info, TokenInterface>,
+    pub token_program_x: Interface<'info, TokenInterface>,
+    pub token_program_y: Interface<'info, TokenInterface>,
     pub system_program: Program<'info, System>,
 }
 
 impl<'info> Withdraw<'info> {
-    pub fn withdraw(&mut self, amount: u64, min_x: u64, min_y: u64) -> Result<()> {
+    pub fn withdraw(
+        &mut self,
+        amount: u64,
+        min_x: u64,
+        min_y: u64,
+        expires_at: Option<i64>,
+    ) -> Result<()> {
+        check_deadline(expires_at)?;
+        require!(!self.config.locked, AMMError::ConfigLocked);
+        require!(self.config.flash_loan.is_none(), AMMError::FlashLoanActive);
+        require!(amount > 0, AMMError::InvalidAmount);
+        require!(self.mint_lp.supply > 0, AMMError::EmptyPool);
         require!(
-            self.mint_lp.supply > 0 && self.mint_lp.supply >= amount,
+            self.mint_lp.supply >= amount,
             AMMError::InsufficientLiquidity
         );
-        let xy_amount = match ConstantProduct::xy_withdraw_amounts_from_l(
-            self.vault_x.amount,
-            self.vault_y.amount,
-            self.mint_lp.supply,
-            amount,
-            6,
-        ) {
-            Ok(a) => a,
-            Err(_) => return err!(AMMError::InsufficientLiquidity),
-        };
+        let (reserve_x, reserve_y) = self
+            .config
+            .reserves(self.vault_x.amount, self.vault_y.amount)?;
+        self.config.update_oracle()?;
+        let (amount_x, amount_y) =
+            constant_product::withdraw_amounts(reserve_x, reserve_y, self.mint_lp.supply, amount)
+                .ok_or(AMMError::InsufficientLiquidity)?;
+        require!(amount_x > 0 || amount_y > 0, AMMError::ZeroOutput);
 
+        self.burn_lp(amount)?;
+        let received_x = self.withdraw_tokens(true, amount_x)?;
+        let received_y = self.withdraw_tokens(false, amount_y)?;
+
+        // Slippage is checked on what the withdrawer received, net of transfer fees.
         require!(
-            xy_amount.x >= min_x && xy_amount.y >= min_y,
+            received_x >= min_x && received_y >= min_y,
             AMMError::ExceededMaxSlippage
         );
 
-        self.burn_lp(amount)?;
-        self.withdraw_tokens(true, xy_amount.x)?;
-        self.withdraw_tokens(false, xy_amount.y)?;
+        self.vault_x.reload()?;
+        self.vault_y.reload()?;
+        self.mint_lp.reload()?;
+        let (reserve_x, reserve_y) = self
+            .config
+            .sync_reserves(self.vault_x.amount, self.vault_y.amount)?;
+        emit!(LiquidityRemoved {
+            config: self.config.key(),
+            user: self.withdrawer.key(),
+            amount_x,
+            amount_y,
+            lp_burned: amount,
+            reserve_x,
+            reserve_y,
+            lp_supply: self.mint_lp.supply,
+        });
         Ok(())
     }
-    pub fn withdraw_tokens(&mut self, is_x: bool, amount: u64) -> Result<()> {
-        let (from, to) = match is_x {
+    /// Returns the amount the withdrawer actually received.
+    pub fn withdraw_tokens(&mut self, is_x: bool, amount: u64) -> Result<u64> {
+        let (from, to, mint, decimals, token_program) = match is_x {
             true => (
                 self.vault_x.to_account_info(),
                 self.withdrawer_ata_x.to_account_info(),
+                self.mint_x.to_account_info(),
+                self.mint_x.decimals,
+                self.token_program_x.to_account_info(),
             ),
             false => (
                 self.vault_y.to_account_info(),
                 self.withdrawer_ata_y.to_account_info(),
+                self.mint_y.to_account_info(),
+                self.mint_y.decimals,
+                self.token_program_y.to_account_info(),
             ),
         };
-        let cpi_accounts = Transfer {
+        let cpi_accounts = TransferChecked {
             from,
+            mint,
             to,
             authority: self.config.to_account_info(),
         };
@@ -110,13 +160,16 @@ impl<'info> Withdraw<'info> {
         let signer_seeds: &[&[&[u8]]] =
             &[&[b"config", config_seed.as_ref(), &[self.config.config_bump]]];
 
-        let cpi_ctx = CpiContext::new_with_signer(
-            self.token_program.to_account_info(),
-            cpi_accounts,
-            signer_seeds,
-        );
-        transfer(cpi_ctx, amount)?;
-        Ok(())
+        let cpi_ctx = CpiContext::new_with_signer(token_program, cpi_accounts, signer_seeds);
+        transfer_checked(cpi_ctx, amount, decimals)?;
+
+        let ata = match is_x {
+            true => &mut self.withdrawer_ata_x,
+            false => &mut self.withdrawer_ata_y,
+        };
+        let before = ata.amount;
+        ata.reload()?;
+        Ok(ata.amount - before)
     }
     pub fn burn_lp(&mut self, amount: u64) -> Result<()> {
         let cpi_accounts = Burn {
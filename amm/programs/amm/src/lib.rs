@@ -1,7 +1,13 @@
+pub mod cl_math;
 pub mod constants;
+pub mod deadline;
 pub mod error;
+pub mod events;
 pub mod instructions;
 pub mod state;
+pub mod transfer_fee;
+
+pub use amm_math::{math, stable_swap};
 
 use anchor_lang::prelude::*;
 
@@ -13,22 +19,194 @@ declare_id!("4f4DhXC59uftcSi5wZzVQVBEDnU6AV6qb8NQmgbuwSDZ");
 #[program]
 pub mod amm {
     use super::*;
-    pub fn initialize_config(ctx: Context<InitializeConfig>, seed: u64, fee: u16) -> Result<()> {
-        ctx.accounts.init_config(seed, fee, &ctx.bumps)
+    pub fn initialize_config(
+        ctx: Context<InitializeConfig>,
+        seed: u64,
+        fee: u16,
+        curve_type: CurveType,
+        amp: u64,
+    ) -> Result<()> {
+        ctx.accounts
+            .init_config(seed, fee, curve_type, amp, &ctx.bumps)
     }
-    pub fn deposit(ctx: Context<Deposit>, amount: u64, max_x: u64, max_y: u64) -> Result<()> {
-        ctx.accounts.deposit_to_pool(amount, max_x, max_y)
+    pub fn deposit(
+        ctx: Context<Deposit>,
+        amount: u64,
+        max_x: u64,
+        max_y: u64,
+        expires_at: Option<i64>,
+    ) -> Result<()> {
+        ctx.accounts
+            .deposit_to_pool(amount, max_x, max_y, expires_at)
     }
-    pub fn withdraw(ctx: Context<Withdraw>, amount: u64, min_x: u64, min_y: u64) -> Result<()> {
-        ctx.accounts.withdraw(amount, min_x, min_y)
+    pub fn deposit_single_sided(
+        ctx: Context<Deposit>,
+        is_x: bool,
+        amount_in: u64,
+        min_lp_out: u64,
+        expires_at: Option<i64>,
+    ) -> Result<()> {
+        ctx.accounts
+            .deposit_single_sided(is_x, amount_in, min_lp_out, expires_at)
+    }
+    pub fn withdraw(
+        ctx: Context<Withdraw>,
+        amount: u64,
+        min_x: u64,
+        min_y: u64,
+        expires_at: Option<i64>,
+    ) -> Result<()> {
+        ctx.accounts.withdraw(amount, min_x, min_y, expires_at)
     }
     pub fn swap(
         ctx: Context<Swap>,
         is_x_to_y: bool,
         amount_in: u64,
         min_amount_out: u64,
+        expires_at: Option<i64>,
+    ) -> Result<()> {
+        ctx.accounts
+            .swap_tokens(is_x_to_y, amount_in, min_amount_out, expires_at)
+    }
+    pub fn swap_exact_out(
+        ctx: Context<Swap>,
+        is_x_to_y: bool,
+        amount_out: u64,
+        max_amount_in: u64,
+        expires_at: Option<i64>,
     ) -> Result<()> {
         ctx.accounts
-            .swap_tokens(is_x_to_y, amount_in, min_amount_out)
+            .swap_exact_out(is_x_to_y, amount_out, max_amount_in, expires_at)
+    }
+    pub fn route_swap<'info>(
+        ctx: Context<'_, '_, 'info, 'info, RouteSwap<'info>>,
+        amount_in: u64,
+        min_amount_out: u64,
+        expires_at: Option<i64>,
+    ) -> Result<()> {
+        ctx.accounts.route_swap(
+            ctx.remaining_accounts,
+            amount_in,
+            min_amount_out,
+            expires_at,
+        )
+    }
+    pub fn flash_borrow(ctx: Context<FlashLoan>, is_x: bool, amount: u64) -> Result<()> {
+        ctx.accounts.flash_borrow(is_x, amount)
+    }
+    pub fn flash_repay(ctx: Context<FlashLoan>, is_x: bool, amount: u64) -> Result<()> {
+        ctx.accounts.flash_repay(is_x, amount)
+    }
+    pub fn observe(ctx: Context<Observe>) -> Result<Observation> {
+        ctx.accounts.observe()
+    }
+    pub fn lock(ctx: Context<UpdateConfig>) -> Result<()> {
+        ctx.accounts.lock()
+    }
+    pub fn unlock(ctx: Context<UpdateConfig>) -> Result<()> {
+        ctx.accounts.unlock()
+    }
+    pub fn update_fee(ctx: Context<UpdateConfig>, fee: u16) -> Result<()> {
+        ctx.accounts.update_fee(fee)
+    }
+    pub fn update_protocol_fee(ctx: Context<UpdateConfig>, protocol_fee: u16) -> Result<()> {
+        ctx.accounts.update_protocol_fee(protocol_fee)
+    }
+    pub fn collect_protocol_fees(ctx: Context<CollectProtocolFees>) -> Result<()> {
+        ctx.accounts.collect_protocol_fees()
+    }
+    pub fn set_treasury(ctx: Context<UpdateConfig>, treasury: Pubkey) -> Result<()> {
+        ctx.accounts.set_treasury(treasury)
+    }
+    pub fn ramp_amp(ctx: Context<UpdateConfig>, target_amp: u64, ramp_end_ts: i64) -> Result<()> {
+        ctx.accounts.ramp_amp(target_amp, ramp_end_ts)
+    }
+    pub fn stop_ramp_amp(ctx: Context<UpdateConfig>) -> Result<()> {
+        ctx.accounts.stop_ramp_amp()
+    }
+    pub fn transfer_authority(ctx: Context<UpdateConfig>, new_authority: Pubkey) -> Result<()> {
+        ctx.accounts.transfer_authority(new_authority)
+    }
+    pub fn accept_authority(ctx: Context<AcceptAuthority>) -> Result<()> {
+        ctx.accounts.accept_authority()
+    }
+    pub fn renounce_authority(ctx: Context<RenounceAuthority>) -> Result<()> {
+        ctx.accounts.renounce_authority()
+    }
+    pub fn initialize_cl_pool(
+        ctx: Context<InitializeClPool>,
+        seed: u64,
+        fee: u16,
+        tick_spacing: u16,
+        sqrt_price_x64: u128,
+    ) -> Result<()> {
+        ctx.accounts
+            .init_cl_pool(seed, fee, tick_spacing, sqrt_price_x64, &ctx.bumps)
+    }
+    pub fn open_position(
+        ctx: Context<OpenPosition>,
+        tick_lower: i32,
+        tick_upper: i32,
+    ) -> Result<()> {
+        ctx.accounts
+            .open_position(tick_lower, tick_upper, &ctx.bumps)
+    }
+    pub fn increase_liquidity(
+        ctx: Context<ModifyPosition>,
+        liquidity: u128,
+        max_x: u64,
+        max_y: u64,
+    ) -> Result<()> {
+        ctx.accounts.increase_liquidity(liquidity, max_x, max_y)
+    }
+    pub fn decrease_liquidity(
+        ctx: Context<ModifyPosition>,
+        liquidity: u128,
+        min_x: u64,
+        min_y: u64,
+    ) -> Result<()> {
+        ctx.accounts.decrease_liquidity(liquidity, min_x, min_y)
+    }
+    pub fn collect_fees(ctx: Context<ModifyPosition>) -> Result<()> {
+        ctx.accounts.collect_fees()
+    }
+    pub fn close_position(ctx: Context<ClosePosition>) -> Result<()> {
+        ctx.accounts.close_position()
+    }
+    pub fn cl_swap<'info>(
+        ctx: Context<'_, '_, 'info, 'info, ClSwap<'info>>,
+        is_x_to_y: bool,
+        amount_in: u64,
+        min_amount_out: u64,
+        sqrt_price_limit_x64: u128,
+    ) -> Result<()> {
+        ctx.accounts.cl_swap(
+            ctx.remaining_accounts,
+            is_x_to_y,
+            amount_in,
+            min_amount_out,
+            sqrt_price_limit_x64,
+        )
+    }
+    pub fn initialize_farm(ctx: Context<InitializeFarm>, emission_per_second: u64) -> Result<()> {
+        ctx.accounts.init_farm(emission_per_second, &ctx.bumps)
+    }
+    pub fn fund_farm(ctx: Context<FundFarm>, amount: u64) -> Result<()> {
+        ctx.accounts.fund_farm(amount)
+    }
+    pub fn withdraw_unemitted(ctx: Context<WithdrawUnemitted>, amount: u64) -> Result<()> {
+        ctx.accounts.withdraw_unemitted(amount)
+    }
+    pub fn set_emission(ctx: Context<SetEmission>, emission_per_second: u64) -> Result<()> {
+        ctx.accounts.set_emission(emission_per_second)
+    }
+    pub fn stake_lp(ctx: Context<StakeLp>, amount: u64) -> Result<()> {
+        ctx.accounts.stake_lp(amount, &ctx.bumps)
+    }
+    pub fn unstake_lp(ctx: Context<StakeLp>, amount: u64) -> Result<()> {
+        ctx.accounts.unstake_lp(amount)
+    }
+    pub fn harvest(ctx: Context<Harvest>) -> Result<()> {
+        ctx.accounts.harvest()
     }
 }
@@ -1,14 +1,835 @@
+use crate::cl_math::{add_liquidity_delta, fee_growth, fees_owed};
+use crate::constants::{MIN_POSITION_LIQUIDITY, POOLS_PER_PAGE, TICKS_PER_BITMAP};
+use crate::error::AMMError;
+use crate::math::{apply_fee, price_q64, protocol_fee_share};
+use amm_math::quote::{Curve, Pool};
 use anchor_lang::prelude::*;
 
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq, InitSpace)]
+pub enum CurveType {
+    ConstantProduct,
+    StableSwap,
+}
+
 #[account]
 #[derive(InitSpace)]
 pub struct Config {
     pub seed: u64,
     pub authority: Option<Pubkey>,
+    pub pending_authority: Option<Pubkey>,
     pub mint_x: Pubkey,
     pub mint_y: Pubkey,
+    pub curve_type: CurveType,
+    pub amp_initial: u64,
+    pub amp_target: u64,
+    pub amp_ramp_start_ts: i64,
+    pub amp_ramp_end_ts: i64,
     pub fee: u16,
+    pub protocol_fee: u16,
+    pub protocol_fees_x: u64,
+    pub protocol_fees_y: u64,
+    /// Owner of the token accounts protocol fees are collected to.
+    pub treasury: Pubkey,
     pub locked: bool,
+    pub price_x_cumulative: u128,
+    pub price_y_cumulative: u128,
+    pub last_update_ts: i64,
+    /// Reserves as of the end of the last pool action. The price accumulators
+    /// integrate these rather than live vault balances, which donations and
+    /// flash loans can move without trading against the curve.
+    pub reserve_x_last: u64,
+    pub reserve_y_last: u64,
+    /// Set between `flash_borrow` and `flash_repay`; the pool refuses every
+    /// other reserve-dependent action meanwhile.
+    pub flash_loan: Option<FlashLoanState>,
     pub config_bump: u8,
     pub lp_bump: u8,
 }
+
+/// Canonical pool for a mint pair, at `[b"registry", mint_x, mint_y]` with
+/// `mint_x < mint_y`.
+#[account]
+#[derive(InitSpace)]
+pub struct PoolRegistry {
+    pub config: Pubkey,
+    pub mint_x: Pubkey,
+    pub mint_y: Pubkey,
+    pub bump: u8,
+}
+
+/// Singleton counting registered pools; pool `n` is listed on page
+/// `n / POOLS_PER_PAGE`.
+#[account]
+#[derive(InitSpace)]
+pub struct PoolDirectory {
+    pub pool_count: u64,
+    pub bump: u8,
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct PoolListPage {
+    pub page: u64,
+    #[max_len(POOLS_PER_PAGE)]
+    pub pools: Vec<Pubkey>,
+    pub bump: u8,
+}
+
+/// LP farm of a pool, at `[b"farm", config]`. Funded rewards are emitted per
+/// second and split over the staked LP through `reward_per_share_x64`.
+#[account]
+#[derive(InitSpace)]
+pub struct Farm {
+    pub config: Pubkey,
+    pub mint_lp: Pubkey,
+    pub reward_mint: Pubkey,
+    pub emission_per_second: u64,
+    /// Funded rewards that have not been emitted yet.
+    pub rewards_remaining: u64,
+    /// Rewards emitted per staked LP token, in Q64.64. Only differences are
+    /// meaningful, so it is allowed to wrap.
+    pub reward_per_share_x64: u128,
+    pub total_staked: u64,
+    pub last_update_ts: i64,
+    pub bump: u8,
+}
+
+/// LP staked by `owner` in a farm, at `[b"stake", farm, owner]`.
+#[account]
+#[derive(InitSpace)]
+pub struct LpStake {
+    pub farm: Pubkey,
+    pub owner: Pubkey,
+    pub amount: u64,
+    pub reward_per_share_last_x64: u128,
+    pub rewards_owed: u64,
+    pub bump: u8,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq, InitSpace)]
+pub struct FlashLoanState {
+    pub is_x: bool,
+    pub amount: u64,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug)]
+pub struct Observation {
+    pub price_x_cumulative: u128,
+    pub price_y_cumulative: u128,
+    pub timestamp: i64,
+}
+
+impl Config {
+    /// Vault balances minus protocol fees that are owed to the treasury, i.e.
+    /// the liquidity that actually belongs to LPs and backs the curve.
+    pub fn reserves(&self, vault_x: u64, vault_y: u64) -> Result<(u64, u64)> {
+        let x = vault_x
+            .checked_sub(self.protocol_fees_x)
+            .ok_or(AMMError::InsufficientLiquidity)?;
+        let y = vault_y
+            .checked_sub(self.protocol_fees_y)
+            .ok_or(AMMError::InsufficientLiquidity)?;
+        Ok((x, y))
+    }
+
+    /// StableSwap amplification at `now`, moving linearly from `amp_initial`
+    /// to `amp_target` over the ramp window.
+    pub fn amp(&self, now: i64) -> u64 {
+        if now >= self.amp_ramp_end_ts || self.amp_ramp_end_ts <= self.amp_ramp_start_ts {
+            return self.amp_target;
+        }
+        let elapsed = now.saturating_sub(self.amp_ramp_start_ts).max(0) as u128;
+        let duration = (self.amp_ramp_end_ts - self.amp_ramp_start_ts) as u128;
+        let (initial, target) = (self.amp_initial as u128, self.amp_target as u128);
+        let amp = match target >= initial {
+            true => initial + (target - initial) * elapsed / duration,
+            false => initial - (initial - target) * elapsed / duration,
+        };
+        amp as u64
+    }
+
+    /// Curve that prices trades at `now`.
+    pub fn curve(&self, now: i64) -> Curve {
+        match self.curve_type {
+            CurveType::ConstantProduct => Curve::ConstantProduct,
+            CurveType::StableSwap => Curve::StableSwap { amp: self.amp(now) },
+        }
+    }
+
+    /// The pool as `amm_math::quote` sees it, from the vault balances and LP
+    /// supply; lets clients quote straight from the fetched accounts.
+    pub fn pool(&self, vault_x: u64, vault_y: u64, lp_supply: u64, now: i64) -> Result<Pool> {
+        let (reserve_x, reserve_y) = self.reserves(vault_x, vault_y)?;
+        Ok(Pool {
+            reserve_x,
+            reserve_y,
+            lp_supply,
+            fee: self.fee,
+            protocol_fee: self.protocol_fee,
+            curve: self.curve(now),
+        })
+    }
+
+    /// Charges the swap fee on `amount_in`, which stays in the vault: the LP
+    /// share grows the reserves, the protocol share is set aside until collected.
+    pub fn accrue_fee(&mut self, is_x: bool, amount_in: u64) -> Result<u64> {
+        let (_, fee) = apply_fee(amount_in, self.fee).ok_or(AMMError::InvalidFee)?;
+        let protocol_fee =
+            protocol_fee_share(fee, self.protocol_fee).ok_or(AMMError::InvalidFee)?;
+        let protocol_fees = match is_x {
+            true => &mut self.protocol_fees_x,
+            false => &mut self.protocol_fees_y,
+        };
+        *protocol_fees = protocol_fees
+            .checked_add(protocol_fee)
+            .ok_or(AMMError::Overflow)?;
+        Ok(fee)
+    }
+
+    /// Cumulative Q64.64 prices as they would read at `now` given the stored
+    /// reserves. Accumulators wrap; consumers only use differences.
+    pub fn observe(&self, now: i64) -> Observation {
+        let elapsed = now.saturating_sub(self.last_update_ts).max(0) as u128;
+        let (reserve_x, reserve_y) = (self.reserve_x_last, self.reserve_y_last);
+        let (mut price_x_cumulative, mut price_y_cumulative) =
+            (self.price_x_cumulative, self.price_y_cumulative);
+        if elapsed > 0 {
+            if let (Some(price_x), Some(price_y)) = (
+                price_q64(reserve_y, reserve_x),
+                price_q64(reserve_x, reserve_y),
+            ) {
+                price_x_cumulative = price_x_cumulative.wrapping_add(price_x.wrapping_mul(elapsed));
+                price_y_cumulative = price_y_cumulative.wrapping_add(price_y.wrapping_mul(elapsed));
+            }
+        }
+        Observation {
+            price_x_cumulative,
+            price_y_cumulative,
+            timestamp: now,
+        }
+    }
+
+    /// Must be called before the pool is mutated.
+    pub fn update_oracle(&mut self) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        if now <= self.last_update_ts {
+            return Ok(());
+        }
+        let observation = self.observe(now);
+        self.price_x_cumulative = observation.price_x_cumulative;
+        self.price_y_cumulative = observation.price_y_cumulative;
+        self.last_update_ts = now;
+        Ok(())
+    }
+
+    /// Records the reserves the oracle prices from; must be called with the
+    /// vault balances at the end of every action that moves them.
+    pub fn sync_reserves(&mut self, vault_x: u64, vault_y: u64) -> Result<(u64, u64)> {
+        let (reserve_x, reserve_y) = self.reserves(vault_x, vault_y)?;
+        self.reserve_x_last = reserve_x;
+        self.reserve_y_last = reserve_y;
+        Ok((reserve_x, reserve_y))
+    }
+}
+
+/// Concentrated-liquidity pool: liquidity is provided over tick ranges and
+/// only the liquidity whose range contains the current price is active.
+#[account]
+#[derive(InitSpace)]
+pub struct ClPool {
+    pub seed: u64,
+    pub mint_x: Pubkey,
+    pub mint_y: Pubkey,
+    pub fee: u16,
+    pub tick_spacing: u16,
+    pub sqrt_price_x64: u128,
+    pub tick_current: i32,
+    pub liquidity: u128,
+    pub fee_growth_global_x: u128,
+    pub fee_growth_global_y: u128,
+    pub bump: u8,
+}
+
+/// Marks which ticks of a pool have liquidity referencing them, one bit per
+/// multiple of the tick spacing. Each account covers `TICKS_PER_BITMAP` of
+/// them, so a pool can hold any number of initialized ticks.
+#[account]
+#[derive(InitSpace)]
+pub struct TickBitmap {
+    pub pool: Pubkey,
+    pub word: i32,
+    pub bits: [u64; TICKS_PER_BITMAP as usize / 64],
+    pub bump: u8,
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct Tick {
+    pub pool: Pubkey,
+    pub index: i32,
+    pub liquidity_gross: u128,
+    /// Liquidity added when the price crosses this tick upwards.
+    pub liquidity_net: i128,
+    /// Fee growth on the other side of this tick from the current price.
+    pub fee_growth_outside_x: u128,
+    pub fee_growth_outside_y: u128,
+    /// Paid the tick's rent and gets it back when the tick is closed.
+    pub funder: Pubkey,
+    /// Open positions bounded by this tick; it is closed once none are left.
+    pub position_count: u32,
+    pub bump: u8,
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct Position {
+    pub pool: Pubkey,
+    pub owner: Pubkey,
+    pub tick_lower: i32,
+    pub tick_upper: i32,
+    pub liquidity: u128,
+    pub fee_growth_inside_x_last: u128,
+    pub fee_growth_inside_y_last: u128,
+    pub tokens_owed_x: u64,
+    pub tokens_owed_y: u64,
+    pub bump: u8,
+}
+
+impl ClPool {
+    /// Fee growth per unit of liquidity accrued while the price was inside
+    /// `[lower, upper)`. Growth values wrap; only differences are meaningful.
+    pub fn fee_growth_inside(&self, lower: &Tick, upper: &Tick) -> (u128, u128) {
+        let (global_x, global_y) = (self.fee_growth_global_x, self.fee_growth_global_y);
+        let (below_x, below_y) = match self.tick_current >= lower.index {
+            true => (lower.fee_growth_outside_x, lower.fee_growth_outside_y),
+            false => (
+                global_x.wrapping_sub(lower.fee_growth_outside_x),
+                global_y.wrapping_sub(lower.fee_growth_outside_y),
+            ),
+        };
+        let (above_x, above_y) = match self.tick_current < upper.index {
+            true => (upper.fee_growth_outside_x, upper.fee_growth_outside_y),
+            false => (
+                global_x.wrapping_sub(upper.fee_growth_outside_x),
+                global_y.wrapping_sub(upper.fee_growth_outside_y),
+            ),
+        };
+        (
+            global_x.wrapping_sub(below_x).wrapping_sub(above_x),
+            global_y.wrapping_sub(below_y).wrapping_sub(above_y),
+        )
+    }
+
+    /// Adds `liquidity_delta` to a position and its boundary ticks, settling
+    /// the fees the position earned so far into `tokens_owed_*`. Returns
+    /// whether the lower and upper ticks were flipped between initialized and
+    /// uninitialized, so the caller can update their `TickBitmap`s.
+    pub fn update_position(
+        &mut self,
+        position: &mut Position,
+        lower: &mut Tick,
+        upper: &mut Tick,
+        liquidity_delta: i128,
+    ) -> Result<(bool, bool)> {
+        let liquidity = add_liquidity_delta(position.liquidity, liquidity_delta)
+            .ok_or(AMMError::InsufficientLiquidity)?;
+        require!(
+            liquidity == 0 || liquidity >= MIN_POSITION_LIQUIDITY,
+            AMMError::LiquidityBelowMinimum
+        );
+        let flipped = match liquidity_delta {
+            0 => (false, false),
+            _ => (
+                self.update_tick(lower, liquidity_delta, false)?,
+                self.update_tick(upper, liquidity_delta, true)?,
+            ),
+        };
+
+        let (inside_x, inside_y) = self.fee_growth_inside(lower, upper);
+        let owed_x = fees_owed(
+            position.liquidity,
+            inside_x.wrapping_sub(position.fee_growth_inside_x_last),
+        )
+        .ok_or(AMMError::Overflow)?;
+        let owed_y = fees_owed(
+            position.liquidity,
+            inside_y.wrapping_sub(position.fee_growth_inside_y_last),
+        )
+        .ok_or(AMMError::Overflow)?;
+        position.tokens_owed_x = position
+            .tokens_owed_x
+            .checked_add(owed_x)
+            .ok_or(AMMError::Overflow)?;
+        position.tokens_owed_y = position
+            .tokens_owed_y
+            .checked_add(owed_y)
+            .ok_or(AMMError::Overflow)?;
+        position.fee_growth_inside_x_last = inside_x;
+        position.fee_growth_inside_y_last = inside_y;
+        position.liquidity = liquidity;
+
+        if (lower.index..upper.index).contains(&self.tick_current) {
+            self.liquidity =
+                add_liquidity_delta(self.liquidity, liquidity_delta).ok_or(AMMError::Overflow)?;
+        }
+        Ok(flipped)
+    }
+
+    /// Returns whether the tick was flipped between initialized and
+    /// uninitialized.
+    fn update_tick(&self, tick: &mut Tick, liquidity_delta: i128, is_upper: bool) -> Result<bool> {
+        let gross_before = tick.liquidity_gross;
+        tick.liquidity_gross = add_liquidity_delta(gross_before, liquidity_delta)
+            .ok_or(AMMError::InsufficientLiquidity)?;
+        tick.liquidity_net = match is_upper {
+            true => tick.liquidity_net.checked_sub(liquidity_delta),
+            false => tick.liquidity_net.checked_add(liquidity_delta),
+        }
+        .ok_or(AMMError::Overflow)?;
+
+        if gross_before == 0 && tick.liquidity_gross != 0 {
+            // By convention all growth so far happened below the tick.
+            let (outside_x, outside_y) = match tick.index <= self.tick_current {
+                true => (self.fee_growth_global_x, self.fee_growth_global_y),
+                false => (0, 0),
+            };
+            tick.fee_growth_outside_x = outside_x;
+            tick.fee_growth_outside_y = outside_y;
+        }
+        Ok((gross_before == 0) != (tick.liquidity_gross == 0))
+    }
+
+    /// Moves the price across `tick`, flipping its outside fee growth, and
+    /// returns the liquidity change for crossing upwards.
+    pub fn cross_tick(&self, tick: &mut Tick) -> i128 {
+        tick.fee_growth_outside_x = self
+            .fee_growth_global_x
+            .wrapping_sub(tick.fee_growth_outside_x);
+        tick.fee_growth_outside_y = self
+            .fee_growth_global_y
+            .wrapping_sub(tick.fee_growth_outside_y);
+        tick.liquidity_net
+    }
+}
+
+impl TickBitmap {
+    /// Bitmap word holding the spaced tick at or below `tick`.
+    pub fn word_of(tick: i32, tick_spacing: u16) -> i32 {
+        tick.div_euclid(tick_spacing as i32)
+            .div_euclid(TICKS_PER_BITMAP)
+    }
+
+    /// Word a swap from `tick_current` searches next: the one holding the
+    /// current tick moving down, or the tick after it moving up.
+    pub fn word_to_search(tick_current: i32, tick_spacing: u16, downwards: bool) -> i32 {
+        let compressed = tick_current.div_euclid(tick_spacing as i32);
+        match downwards {
+            true => compressed.div_euclid(TICKS_PER_BITMAP),
+            false => (compressed + 1).div_euclid(TICKS_PER_BITMAP),
+        }
+    }
+
+    /// Flips `tick`, a multiple of the tick spacing inside this word.
+    pub fn flip(&mut self, tick: i32, tick_spacing: u16) -> Result<()> {
+        require!(
+            Self::word_of(tick, tick_spacing) == self.word,
+            AMMError::InvalidTickAccount
+        );
+        let bit = tick
+            .div_euclid(tick_spacing as i32)
+            .rem_euclid(TICKS_PER_BITMAP) as usize;
+        self.bits[bit / 64] ^= 1 << (bit % 64);
+        Ok(())
+    }
+
+    /// Next tick in this word the price reaches from `tick_current` moving
+    /// down (at or below it) or up (above it). Without an initialized one
+    /// left, returns the word's last tick in that direction and `false`.
+    pub fn next_tick(&self, tick_current: i32, tick_spacing: u16, downwards: bool) -> (i32, bool) {
+        let spacing = tick_spacing as i32;
+        let first = self.word * TICKS_PER_BITMAP;
+        let compressed = tick_current.div_euclid(spacing);
+        let is_set = |tick: &i32| {
+            let bit = (tick - first) as usize;
+            self.bits[bit / 64] & (1 << (bit % 64)) != 0
+        };
+        let ticks = first..first + TICKS_PER_BITMAP;
+        let found = match downwards {
+            true => ticks.rev().filter(|&tick| tick <= compressed).find(is_set),
+            false => ticks.filter(|&tick| tick > compressed).find(is_set),
+        };
+        match (found, downwards) {
+            (Some(tick), _) => (tick * spacing, true),
+            (None, true) => (first * spacing, false),
+            (None, false) => ((first + TICKS_PER_BITMAP - 1) * spacing, false),
+        }
+    }
+}
+
+impl Farm {
+    /// Emits rewards for the time since the last update. Nothing is emitted
+    /// while no LP is staked, and never more than has been funded.
+    pub fn accrue(&mut self, now: i64) {
+        let elapsed = now.saturating_sub(self.last_update_ts).max(0) as u64;
+        self.last_update_ts = self.last_update_ts.max(now);
+        if self.total_staked == 0 {
+            return;
+        }
+        let emitted = elapsed
+            .saturating_mul(self.emission_per_second)
+            .min(self.rewards_remaining);
+        let growth = fee_growth(emitted, self.total_staked as u128);
+        // Only charge what the floored growth hands out, so the remainder
+        // stays unemitted instead of stuck in the vault.
+        let distributed = fees_owed(self.total_staked as u128, growth).unwrap_or(emitted);
+        self.rewards_remaining -= distributed;
+        self.reward_per_share_x64 = self.reward_per_share_x64.wrapping_add(growth);
+    }
+
+    /// Credits `stake` with what it earned since it was last settled. Must run
+    /// after `accrue` and before the staked amount changes.
+    pub fn settle(&self, stake: &mut LpStake) -> Result<()> {
+        let growth = self
+            .reward_per_share_x64
+            .wrapping_sub(stake.reward_per_share_last_x64);
+        let earned = fees_owed(stake.amount as u128, growth).ok_or(AMMError::Overflow)?;
+        stake.rewards_owed = stake
+            .rewards_owed
+            .checked_add(earned)
+            .ok_or(AMMError::Overflow)?;
+        stake.reward_per_share_last_x64 = self.reward_per_share_x64;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants::MAX_AMP;
+    use crate::instructions::swap_amount_out;
+    use amm_math::quote::quote_swap;
+    use proptest::prelude::*;
+
+    fn pool() -> ClPool {
+        ClPool {
+            seed: 0,
+            mint_x: Pubkey::default(),
+            mint_y: Pubkey::default(),
+            fee: 30,
+            tick_spacing: 60,
+            sqrt_price_x64: 1 << 64,
+            tick_current: 0,
+            liquidity: 0,
+            fee_growth_global_x: 0,
+            fee_growth_global_y: 0,
+            bump: 0,
+        }
+    }
+
+    fn tick(index: i32) -> Tick {
+        Tick {
+            pool: Pubkey::default(),
+            index,
+            liquidity_gross: 0,
+            liquidity_net: 0,
+            fee_growth_outside_x: 0,
+            fee_growth_outside_y: 0,
+            funder: Pubkey::default(),
+            position_count: 1,
+            bump: 0,
+        }
+    }
+
+    fn bitmap(word: i32) -> TickBitmap {
+        TickBitmap {
+            pool: Pubkey::default(),
+            word,
+            bits: [0; 4],
+            bump: 0,
+        }
+    }
+
+    fn position(tick_lower: i32, tick_upper: i32) -> Position {
+        Position {
+            pool: Pubkey::default(),
+            owner: Pubkey::default(),
+            tick_lower,
+            tick_upper,
+            liquidity: 0,
+            fee_growth_inside_x_last: 0,
+            fee_growth_inside_y_last: 0,
+            tokens_owed_x: 0,
+            tokens_owed_y: 0,
+            bump: 0,
+        }
+    }
+
+    #[test]
+    fn position_only_earns_fees_while_in_range() {
+        let mut pool = pool();
+        let (mut lower, mut upper) = (tick(-60), tick(60));
+        let mut position = position(-60, 60);
+        let flipped = pool
+            .update_position(&mut position, &mut lower, &mut upper, 1_000_000)
+            .unwrap();
+        assert_eq!(pool.liquidity, 1_000_000);
+        assert_eq!(flipped, (true, true));
+
+        // Fees while in range go to the position.
+        pool.fee_growth_global_x += fee_growth(1_000, pool.liquidity);
+        pool.update_position(&mut position, &mut lower, &mut upper, 0)
+            .unwrap();
+        assert_eq!(position.tokens_owed_x, 999);
+
+        // Price moves above the range; fees earned there are not the position's.
+        let net = pool.cross_tick(&mut upper);
+        pool.liquidity = add_liquidity_delta(pool.liquidity, net).unwrap();
+        pool.tick_current = 60;
+        assert_eq!(pool.liquidity, 0);
+        pool.fee_growth_global_y += fee_growth(5_000, 1_000);
+        pool.update_position(&mut position, &mut lower, &mut upper, 0)
+            .unwrap();
+        assert_eq!((position.tokens_owed_x, position.tokens_owed_y), (999, 0));
+    }
+
+    #[test]
+    fn ticks_are_tracked_while_referenced() {
+        let mut pool = pool();
+        let (mut lower, mut upper) = (tick(-120), tick(-60));
+        let mut position = position(-120, -60);
+        let mut bitmap = bitmap(-1);
+        let flipped = pool
+            .update_position(&mut position, &mut lower, &mut upper, 5_000_000)
+            .unwrap();
+        // Out-of-range liquidity doesn't become active.
+        assert_eq!(pool.liquidity, 0);
+        assert_eq!(flipped, (true, true));
+        bitmap.flip(-120, 60).unwrap();
+        bitmap.flip(-60, 60).unwrap();
+
+        // Both ticks sit in the word below the current tick's, so a swap down
+        // first walks to the edge of the current word.
+        assert_eq!(TickBitmap::word_to_search(0, 60, true), 0);
+        assert_eq!(TickBitmap::word_to_search(-1, 60, true), -1);
+        assert_eq!(TickBitmap::word_to_search(-60, 60, false), 0);
+        assert_eq!(bitmap.next_tick(-1, 60, true), (-60, true));
+        assert_eq!(bitmap.next_tick(-61, 60, true), (-120, true));
+        assert_eq!(bitmap.next_tick(-121, 60, true), (-256 * 60, false));
+        assert_eq!(bitmap.next_tick(-121, 60, false), (-120, true));
+
+        let flipped = pool
+            .update_position(&mut position, &mut lower, &mut upper, -5_000_000)
+            .unwrap();
+        assert_eq!(flipped, (true, true));
+        bitmap.flip(-120, 60).unwrap();
+        bitmap.flip(-60, 60).unwrap();
+        assert_eq!(bitmap.bits, [0; 4]);
+        assert!(pool
+            .update_position(&mut position, &mut lower, &mut upper, -1)
+            .is_err());
+    }
+
+    #[test]
+    fn positions_cannot_hold_dust_liquidity() {
+        let mut pool = pool();
+        let (mut lower, mut upper) = (tick(-60), tick(60));
+        let mut position = position(-60, 60);
+        let min = MIN_POSITION_LIQUIDITY as i128;
+        assert!(pool
+            .update_position(&mut position, &mut lower, &mut upper, min - 1)
+            .is_err());
+        pool.update_position(&mut position, &mut lower, &mut upper, min)
+            .unwrap();
+        // Only a full withdrawal may take the position below the minimum.
+        assert!(pool
+            .update_position(&mut position, &mut lower, &mut upper, -1)
+            .is_err());
+        let flipped = pool
+            .update_position(&mut position, &mut lower, &mut upper, -min)
+            .unwrap();
+        assert_eq!(flipped, (true, true));
+    }
+
+    #[test]
+    fn every_spaced_tick_of_a_word_can_be_initialized() {
+        let mut bitmap = bitmap(1);
+        for index in 256..512 {
+            bitmap.flip(index * 60, 60).unwrap();
+        }
+        assert_eq!(bitmap.bits, [u64::MAX; 4]);
+        assert_eq!(bitmap.next_tick(256 * 60 - 1, 60, false), (256 * 60, true));
+        assert_eq!(bitmap.next_tick(256 * 60, 60, false), (257 * 60, true));
+        assert_eq!(bitmap.next_tick(512 * 60, 60, true), (511 * 60, true));
+        // Ticks outside the word are tracked by the neighbouring accounts.
+        assert!(bitmap.flip(0, 60).is_err());
+        assert!(bitmap.flip(512 * 60, 60).is_err());
+    }
+
+    fn farm(emission_per_second: u64, rewards_remaining: u64) -> Farm {
+        Farm {
+            config: Pubkey::default(),
+            mint_lp: Pubkey::default(),
+            reward_mint: Pubkey::default(),
+            emission_per_second,
+            rewards_remaining,
+            reward_per_share_x64: 0,
+            total_staked: 0,
+            last_update_ts: 0,
+            bump: 0,
+        }
+    }
+
+    fn stake(amount: u64) -> LpStake {
+        LpStake {
+            farm: Pubkey::default(),
+            owner: Pubkey::default(),
+            amount,
+            reward_per_share_last_x64: 0,
+            rewards_owed: 0,
+            bump: 0,
+        }
+    }
+
+    #[test]
+    fn farm_splits_emissions_by_stake() {
+        let mut farm = farm(100, 10_000);
+        let (mut a, mut b) = (stake(0), stake(0));
+
+        // Nothing is emitted while the farm is empty.
+        farm.accrue(10);
+        assert_eq!(
+            (farm.rewards_remaining, farm.reward_per_share_x64),
+            (10_000, 0)
+        );
+
+        farm.settle(&mut a).unwrap();
+        a.amount = 1_000;
+        farm.total_staked = 1_000;
+        farm.accrue(20);
+        farm.settle(&mut b).unwrap();
+        b.amount = 3_000;
+        farm.total_staked = 4_000;
+        farm.accrue(30);
+
+        farm.settle(&mut a).unwrap();
+        farm.settle(&mut b).unwrap();
+        assert_eq!(farm.rewards_remaining, 8_000);
+        assert_eq!((a.rewards_owed, b.rewards_owed), (1_250, 750));
+        // Settling twice doesn't pay twice.
+        farm.settle(&mut a).unwrap();
+        assert_eq!(a.rewards_owed, 1_250);
+    }
+
+    #[test]
+    fn farm_emits_at_most_what_was_funded() {
+        let mut farm = farm(100, 250);
+        let mut a = stake(1_000);
+        farm.total_staked = 1_000;
+        farm.accrue(10);
+        farm.settle(&mut a).unwrap();
+        assert_eq!((farm.rewards_remaining, a.rewards_owed), (0, 250));
+        farm.accrue(20);
+        farm.settle(&mut a).unwrap();
+        assert_eq!(a.rewards_owed, 250);
+    }
+
+    #[test]
+    fn farm_keeps_rounding_dust_unemitted() {
+        let mut farm = farm(100, 1_000);
+        let mut a = stake(3);
+        farm.total_staked = 3;
+        farm.accrue(1);
+        farm.settle(&mut a).unwrap();
+        // 100 / 3 per token floors, so only 99 is handed out.
+        assert_eq!((farm.rewards_remaining, a.rewards_owed), (901, 99));
+    }
+
+    fn config(curve_type: CurveType, fee: u16, protocol_fee: u16) -> Config {
+        Config {
+            seed: 0,
+            authority: None,
+            pending_authority: None,
+            mint_x: Pubkey::default(),
+            mint_y: Pubkey::default(),
+            curve_type,
+            amp_initial: 0,
+            amp_target: 0,
+            amp_ramp_start_ts: 0,
+            amp_ramp_end_ts: 0,
+            fee,
+            protocol_fee,
+            protocol_fees_x: 0,
+            protocol_fees_y: 0,
+            treasury: Pubkey::default(),
+            locked: false,
+            price_x_cumulative: 0,
+            price_y_cumulative: 0,
+            last_update_ts: 0,
+            reserve_x_last: 0,
+            reserve_y_last: 0,
+            flash_loan: None,
+            config_bump: 0,
+            lp_bump: 0,
+        }
+    }
+
+    #[test]
+    fn oracle_prices_from_synced_reserves_only() {
+        let mut config = config(CurveType::ConstantProduct, 0, 0);
+        config.protocol_fees_x = 100;
+        assert_eq!(config.sync_reserves(1_100, 4_000).unwrap(), (1_000, 4_000));
+        // Priced from the synced reserves, net of protocol fees.
+        let observation = config.observe(10);
+        assert_eq!(observation.price_x_cumulative, (4u128 << 64) * 10);
+        assert_eq!(observation.price_y_cumulative, (1u128 << 62) * 10);
+        assert_eq!(config.observe(0).price_x_cumulative, 0);
+    }
+
+    proptest! {
+        /// `quote_swap` on `Config::pool` agrees with the amounts `swap` moves
+        /// and the fees it accrues, including mid-ramp StableSwap pools.
+        #[test]
+        fn quote_matches_swap_settlement(
+            vault_x in 1u64..1_000_000_000_000,
+            vault_y in 1u64..1_000_000_000_000,
+            owed_x in 0u64..1_000,
+            amount_in in 1u64..1_000_000_000_000,
+            fee in 0u16..1_000,
+            protocol_fee in 0u16..=10_000,
+            stable: bool,
+            amps in (1u64..=MAX_AMP, 1u64..=MAX_AMP),
+            now in 0i64..2_000,
+            is_x_to_y: bool,
+        ) {
+            prop_assume!(owed_x <= vault_x);
+            let curve_type = match stable {
+                true => CurveType::StableSwap,
+                false => CurveType::ConstantProduct,
+            };
+            let mut config = config(curve_type, fee, protocol_fee);
+            config.protocol_fees_x = owed_x;
+            (config.amp_initial, config.amp_target) = amps;
+            config.amp_ramp_end_ts = 1_000;
+            let quote = quote_swap(&config.pool(vault_x, vault_y, 0, now).unwrap(), is_x_to_y, amount_in)
+                .map_err(|error| Error::from(AMMError::from(error)));
+
+            // The calls `swap_tokens` and `settle` make with the received input.
+            let (reserve_x, reserve_y) = config.reserves(vault_x, vault_y).unwrap();
+            let (amount_in_after_fee, _) = apply_fee(amount_in, config.fee).unwrap();
+            let amount_out =
+                swap_amount_out(&config, reserve_x, reserve_y, is_x_to_y, amount_in_after_fee, now);
+            let owed_before = config.protocol_fees_x + config.protocol_fees_y;
+            let fee = config.accrue_fee(is_x_to_y, amount_in).unwrap();
+            let protocol_fee = config.protocol_fees_x + config.protocol_fees_y - owed_before;
+
+            match (quote, amount_out) {
+                (Ok(quote), Ok(amount_out)) => {
+                    prop_assert_eq!(quote.amount_out, amount_out);
+                    prop_assert_eq!(quote.fee, fee);
+                    prop_assert_eq!(quote.protocol_fee, protocol_fee);
+                }
+                (quote, amount_out) => prop_assert_eq!(quote.map(|q| q.amount_out), amount_out),
+            }
+        }
+    }
+}
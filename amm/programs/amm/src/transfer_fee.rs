@@ -0,0 +1,25 @@
+use crate::error::AMMError;
+use anchor_lang::prelude::*;
+use anchor_spl::token_2022::spl_token_2022::extension::transfer_fee::TransferFeeConfig;
+use anchor_spl::token_interface::{get_mint_extension_data, spl_token_2022};
+
+fn transfer_fee_config(mint: &AccountInfo) -> Option<TransferFeeConfig> {
+    if *mint.owner != spl_token_2022::ID {
+        return None;
+    }
+    get_mint_extension_data::<TransferFeeConfig>(mint).ok()
+}
+
+/// Amount that has to be sent so the recipient ends up with `post_fee_amount`.
+pub fn amount_with_transfer_fee(mint: &AccountInfo, post_fee_amount: u64) -> Result<u64> {
+    let Some(config) = transfer_fee_config(mint) else {
+        return Ok(post_fee_amount);
+    };
+    let epoch = Clock::get()?.epoch;
+    let fee = config
+        .calculate_inverse_epoch_fee(epoch, post_fee_amount)
+        .ok_or(AMMError::TransferFeeMismatch)?;
+    Ok(post_fee_amount
+        .checked_add(fee)
+        .ok_or(AMMError::TransferFeeMismatch)?)
+}
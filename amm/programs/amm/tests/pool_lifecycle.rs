@@ -0,0 +1,413 @@
+//! Runs the compiled program in LiteSVM, so `anchor build` has to run first.
+
+use amm::error::AMMError;
+use amm::state::{Config, CurveType};
+use amm_math::quote::quote_swap;
+use anchor_lang::prelude::{Clock, Pubkey};
+use anchor_lang::solana_program::instruction::{error::InstructionError, Instruction};
+use anchor_lang::{system_program, AccountDeserialize, InstructionData, ToAccountMetas};
+use anchor_spl::associated_token::{self, get_associated_token_address};
+use anchor_spl::token::{self, Mint, TokenAccount};
+use litesvm::LiteSVM;
+use litesvm_token::{CreateAssociatedTokenAccount, CreateMint, MintTo};
+use solana_keypair::Keypair;
+use solana_native_token::LAMPORTS_PER_SOL;
+use solana_signer::Signer;
+use solana_transaction::Transaction;
+use solana_transaction_error::TransactionError;
+
+const PROGRAM_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../../target/deploy/amm.so");
+const SEED: u64 = 12_346_789;
+const FEE: u16 = 20;
+
+struct Pool {
+    svm: LiteSVM,
+    user: Keypair,
+    mint_x: Pubkey,
+    mint_y: Pubkey,
+    mint_lp: Pubkey,
+    config: Pubkey,
+    vault_x: Pubkey,
+    vault_y: Pubkey,
+    user_x: Pubkey,
+    user_y: Pubkey,
+    user_lp: Pubkey,
+}
+
+impl Pool {
+    /// Creates two sorted mints, funds the user with 1_000_000 of each and
+    /// initializes a constant-product pool over them.
+    fn new() -> Self {
+        let mut svm = LiteSVM::new();
+        svm.add_program_from_file(amm::ID, PROGRAM_PATH)
+            .expect("amm.so not found, run `anchor build` first");
+        let user = Keypair::new();
+        svm.airdrop(&user.pubkey(), 10 * LAMPORTS_PER_SOL).unwrap();
+
+        let mut mints = [
+            CreateMint::new(&mut svm, &user).decimals(6).send().unwrap(),
+            CreateMint::new(&mut svm, &user).decimals(6).send().unwrap(),
+        ];
+        mints.sort();
+        let [mint_x, mint_y] = mints;
+        let mut user_atas = [Pubkey::default(); 2];
+        for (ata, mint) in user_atas.iter_mut().zip(mints) {
+            *ata = CreateAssociatedTokenAccount::new(&mut svm, &user, &mint)
+                .send()
+                .unwrap();
+            MintTo::new(&mut svm, &user, &mint, ata, 1_000_000)
+                .send()
+                .unwrap();
+        }
+
+        let config = Pubkey::find_program_address(&[b"config", &SEED.to_le_bytes()], &amm::ID).0;
+        let mint_lp = Pubkey::find_program_address(&[b"lp", config.as_ref()], &amm::ID).0;
+        let mut pool = Pool {
+            user_lp: get_associated_token_address(&user.pubkey(), &mint_lp),
+            vault_x: get_associated_token_address(&config, &mint_x),
+            vault_y: get_associated_token_address(&config, &mint_y),
+            svm,
+            user,
+            mint_x,
+            mint_y,
+            mint_lp,
+            config,
+            user_x: user_atas[0],
+            user_y: user_atas[1],
+        };
+        pool.initialize().unwrap();
+        pool
+    }
+
+    fn send(
+        &mut self,
+        accounts: impl ToAccountMetas,
+        data: impl InstructionData,
+    ) -> Result<(), TransactionError> {
+        let ix = Instruction {
+            program_id: amm::ID,
+            accounts: accounts.to_account_metas(None),
+            data: data.data(),
+        };
+        let tx = Transaction::new_signed_with_payer(
+            &[ix],
+            Some(&self.user.pubkey()),
+            &[&self.user],
+            self.svm.latest_blockhash(),
+        );
+        let result = self
+            .svm
+            .send_transaction(tx)
+            .map(|_| ())
+            .map_err(|failed| failed.err);
+        // Identical instructions sent twice would otherwise share a signature.
+        self.svm.expire_blockhash();
+        result
+    }
+
+    fn initialize(&mut self) -> Result<(), TransactionError> {
+        let pda = |seeds: &[&[u8]]| Pubkey::find_program_address(seeds, &amm::ID).0;
+        let accounts = amm::accounts::InitializeConfig {
+            mint_lp: self.mint_lp,
+            mint_x: self.mint_x,
+            mint_y: self.mint_y,
+            vault_x: self.vault_x,
+            vault_y: self.vault_y,
+            config: self.config,
+            registry: pda(&[b"registry", self.mint_x.as_ref(), self.mint_y.as_ref()]),
+            pool_directory: pda(&[b"pool_directory"]),
+            pool_list_page: pda(&[b"pool_list", &0u64.to_le_bytes()]),
+            user: self.user.pubkey(),
+            token_program: token::ID,
+            token_program_x: token::ID,
+            token_program_y: token::ID,
+            associated_token_program: associated_token::ID,
+            system_program: system_program::ID,
+        };
+        let data = amm::instruction::InitializeConfig {
+            seed: SEED,
+            fee: FEE,
+            curve_type: CurveType::ConstantProduct,
+            amp: 0,
+        };
+        self.send(accounts, data)
+    }
+
+    fn deposit(&mut self, amount: u64, max_x: u64, max_y: u64) -> Result<(), TransactionError> {
+        let accounts = amm::accounts::Deposit {
+            mint_x: self.mint_x,
+            mint_y: self.mint_y,
+            mint_lp: self.mint_lp,
+            vault_x: self.vault_x,
+            vault_y: self.vault_y,
+            config: self.config,
+            depositer_ata_x: self.user_x,
+            depositer_ata_y: self.user_y,
+            depositer_ata_lp: self.user_lp,
+            locked_liquidity_owner: system_program::ID,
+            locked_lp: get_associated_token_address(&system_program::ID, &self.mint_lp),
+            depositer: self.user.pubkey(),
+            system_program: system_program::ID,
+            token_program: token::ID,
+            token_program_x: token::ID,
+            token_program_y: token::ID,
+            associated_token_program: associated_token::ID,
+        };
+        let data = amm::instruction::Deposit {
+            amount,
+            max_x,
+            max_y,
+            expires_at: None,
+        };
+        self.send(accounts, data)
+    }
+
+    fn swap(
+        &mut self,
+        is_x_to_y: bool,
+        amount_in: u64,
+        min_amount_out: u64,
+    ) -> Result<(), TransactionError> {
+        let accounts = amm::accounts::Swap {
+            user: self.user.pubkey(),
+            mint_x: self.mint_x,
+            mint_y: self.mint_y,
+            vault_x: self.vault_x,
+            vault_y: self.vault_y,
+            user_ata_x: self.user_x,
+            user_ata_y: self.user_y,
+            config: self.config,
+            associated_token_program: associated_token::ID,
+            token_program_x: token::ID,
+            token_program_y: token::ID,
+            system_program: system_program::ID,
+        };
+        let data = amm::instruction::Swap {
+            is_x_to_y,
+            amount_in,
+            min_amount_out,
+            expires_at: None,
+        };
+        self.send(accounts, data)
+    }
+
+    fn withdraw(&mut self, amount: u64, min_x: u64, min_y: u64) -> Result<(), TransactionError> {
+        let accounts = amm::accounts::Withdraw {
+            withdrawer: self.user.pubkey(),
+            mint_x: self.mint_x,
+            mint_y: self.mint_y,
+            mint_lp: self.mint_lp,
+            vault_x: self.vault_x,
+            vault_y: self.vault_y,
+            withdrawer_ata_x: self.user_x,
+            withdrawer_ata_y: self.user_y,
+            withdrawer_ata_lp: self.user_lp,
+            config: self.config,
+            associated_token_program: associated_token::ID,
+            token_program: token::ID,
+            token_program_x: token::ID,
+            token_program_y: token::ID,
+            system_program: system_program::ID,
+        };
+        let data = amm::instruction::Withdraw {
+            amount,
+            min_x,
+            min_y,
+            expires_at: None,
+        };
+        self.send(accounts, data)
+    }
+
+    fn set_locked(&mut self, locked: bool) -> Result<(), TransactionError> {
+        let accounts = amm::accounts::UpdateConfig {
+            authority: self.user.pubkey(),
+            config: self.config,
+        };
+        match locked {
+            true => self.send(accounts, amm::instruction::Lock {}),
+            false => self.send(accounts, amm::instruction::Unlock {}),
+        }
+    }
+
+    fn load<T: AccountDeserialize>(&self, address: &Pubkey) -> T {
+        let account = self.svm.get_account(address).expect("account missing");
+        T::try_deserialize(&mut account.data.as_slice()).unwrap()
+    }
+
+    fn balance(&self, address: &Pubkey) -> u64 {
+        self.load::<TokenAccount>(address).amount
+    }
+
+    fn reserves(&self) -> (u64, u64) {
+        (self.balance(&self.vault_x), self.balance(&self.vault_y))
+    }
+}
+
+fn assert_amm_error(result: Result<(), TransactionError>, error: AMMError) {
+    assert_eq!(
+        result,
+        Err(TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(error.into())
+        ))
+    );
+}
+
+#[test]
+fn initialize_sets_up_an_unlocked_pool() {
+    let pool = Pool::new();
+    let config: Config = pool.load(&pool.config);
+    assert_eq!(config.seed, SEED);
+    assert_eq!(config.fee, FEE);
+    assert_eq!(config.mint_x, pool.mint_x);
+    assert_eq!(config.mint_y, pool.mint_y);
+    assert_eq!(config.authority, Some(pool.user.pubkey()));
+    assert!(!config.locked);
+    assert_eq!(pool.reserves(), (0, 0));
+}
+
+#[test]
+fn pool_lifecycle() {
+    let mut pool = Pool::new();
+
+    // sqrt(30_000 * 20_000) = 24_494, of which MINIMUM_LIQUIDITY is locked.
+    pool.deposit(20_000, 30_000, 20_000).unwrap();
+    assert_eq!(pool.reserves(), (30_000, 20_000));
+    assert_eq!(pool.balance(&pool.user_lp), 23_494);
+    assert_eq!(pool.load::<Mint>(&pool.mint_lp).supply, 24_494);
+
+    let k = |(x, y): (u64, u64)| x as u128 * y as u128;
+    let (user_y, k_before) = (pool.balance(&pool.user_y), k(pool.reserves()));
+    pool.swap(true, 1_000, 1).unwrap();
+    let received_y = pool.balance(&pool.user_y) - user_y;
+    assert!(received_y > 0 && received_y < 20_000 * 1_000 / 30_000);
+    assert_eq!(pool.reserves(), (31_000, 20_000 - received_y));
+    assert!(k(pool.reserves()) >= k_before);
+
+    let (user_x, k_before) = (pool.balance(&pool.user_x), k(pool.reserves()));
+    pool.swap(false, received_y, 1).unwrap();
+    let received_x = pool.balance(&pool.user_x) - user_x;
+    // The round trip pays the fee twice, so less than 1_000 comes back.
+    assert!(received_x > 0 && received_x < 1_000);
+    assert!(k(pool.reserves()) >= k_before);
+
+    let (reserve_x, reserve_y) = pool.reserves();
+    let (user_x, user_y) = (pool.balance(&pool.user_x), pool.balance(&pool.user_y));
+    pool.withdraw(23_494, 1, 1).unwrap();
+    assert_eq!(pool.balance(&pool.user_lp), 0);
+    assert_eq!(pool.load::<Mint>(&pool.mint_lp).supply, 1_000);
+    let withdrawn_x = pool.balance(&pool.user_x) - user_x;
+    let withdrawn_y = pool.balance(&pool.user_y) - user_y;
+    // The locked liquidity keeps its share of the reserves in the pool.
+    assert!(withdrawn_x < reserve_x && withdrawn_y < reserve_y);
+    assert_eq!(
+        pool.reserves(),
+        (reserve_x - withdrawn_x, reserve_y - withdrawn_y)
+    );
+}
+
+#[test]
+fn quote_matches_what_swap_pays_out() {
+    let mut pool = Pool::new();
+    pool.deposit(20_000, 30_000, 20_000).unwrap();
+
+    for is_x_to_y in [true, false] {
+        let config = pool.load::<Config>(&pool.config);
+        let (vault_x, vault_y) = pool.reserves();
+        let lp_supply = pool.load::<Mint>(&pool.mint_lp).supply;
+        let now = pool.svm.get_sysvar::<Clock>().unix_timestamp;
+        let quote = quote_swap(
+            &config.pool(vault_x, vault_y, lp_supply, now).unwrap(),
+            is_x_to_y,
+            1_234,
+        )
+        .unwrap();
+
+        let user_out = match is_x_to_y {
+            true => pool.user_y,
+            false => pool.user_x,
+        };
+        let before = pool.balance(&user_out);
+        pool.swap(is_x_to_y, 1_234, quote.amount_out).unwrap();
+        assert_eq!(pool.balance(&user_out) - before, quote.amount_out);
+
+        let after = pool.load::<Config>(&pool.config);
+        let accrued = match is_x_to_y {
+            true => after.protocol_fees_x - config.protocol_fees_x,
+            false => after.protocol_fees_y - config.protocol_fees_y,
+        };
+        assert_eq!(accrued, quote.protocol_fee);
+    }
+}
+
+#[test]
+fn slippage_limits_reject_without_moving_funds() {
+    let mut pool = Pool::new();
+    pool.deposit(20_000, 30_000, 20_000).unwrap();
+    let reserves = pool.reserves();
+
+    assert_amm_error(pool.swap(true, 1_000, 1_000), AMMError::ExceededMaxSlippage);
+    assert_amm_error(
+        pool.swap(false, 1_000, 10_000),
+        AMMError::ExceededMaxSlippage,
+    );
+    // 10_000 LP needs more than 10_000 of x at a 3:2 ratio.
+    assert_amm_error(
+        pool.deposit(10_000, 10_000, 20_000),
+        AMMError::ExceededMaxSlippage,
+    );
+    assert_amm_error(
+        pool.withdraw(10_000, u64::MAX, 0),
+        AMMError::ExceededMaxSlippage,
+    );
+    assert_eq!(pool.reserves(), reserves);
+    assert_eq!(pool.balance(&pool.user_lp), 23_494);
+}
+
+#[test]
+fn locked_pool_rejects_trading_until_unlocked() {
+    let mut pool = Pool::new();
+    pool.deposit(20_000, 30_000, 20_000).unwrap();
+
+    pool.set_locked(true).unwrap();
+    assert!(pool.load::<Config>(&pool.config).locked);
+    assert_amm_error(pool.swap(true, 1_000, 1), AMMError::ConfigLocked);
+    assert_amm_error(pool.deposit(1_000, 10_000, 10_000), AMMError::ConfigLocked);
+    assert_amm_error(pool.withdraw(1_000, 0, 0), AMMError::ConfigLocked);
+
+    pool.set_locked(false).unwrap();
+    pool.swap(true, 1_000, 1).unwrap();
+    pool.deposit(1_000, 10_000, 10_000).unwrap();
+    pool.withdraw(1_000, 0, 0).unwrap();
+}
+
+#[test]
+fn only_the_authority_can_lock() {
+    let mut pool = Pool::new();
+    let stranger = Keypair::new();
+    pool.svm
+        .airdrop(&stranger.pubkey(), LAMPORTS_PER_SOL)
+        .unwrap();
+    let ix = Instruction {
+        program_id: amm::ID,
+        accounts: amm::accounts::UpdateConfig {
+            authority: stranger.pubkey(),
+            config: pool.config,
+        }
+        .to_account_metas(None),
+        data: amm::instruction::Lock {}.data(),
+    };
+    let tx = Transaction::new_signed_with_payer(
+        &[ix],
+        Some(&stranger.pubkey()),
+        &[&stranger],
+        pool.svm.latest_blockhash(),
+    );
+    let result = pool
+        .svm
+        .send_transaction(tx)
+        .map(|_| ())
+        .map_err(|failed| failed.err);
+    assert_amm_error(result, AMMError::Unauthorized);
+    assert!(!pool.load::<Config>(&pool.config).locked);
+}
@@ -1,6 +1,6 @@
 import * as anchor from "@coral-xyz/anchor";
 import { Program } from "@coral-xyz/anchor";
-import { PublicKey, SystemProgram, LAMPORTS_PER_SOL } from "@solana/web3.js";
+import { Keypair, PublicKey, SystemProgram, LAMPORTS_PER_SOL } from "@solana/web3.js";
 import { Amm } from "../target/types/amm";
 import { assert } from "chai";
 import {
@@ -10,6 +10,7 @@ import {
   getMint,
   getOrCreateAssociatedTokenAccount,
   mintTo,
+  TOKEN_2022_PROGRAM_ID,
 } from "@solana/spl-token";
 
 describe("amm", () => {
@@ -32,23 +33,19 @@ describe("amm", () => {
     [Buffer.from("lp"), config_pda.toBuffer()],
     program.programId
   )[0];
+  // Minimum liquidity is locked in an LP account nobody can sign for.
+  const locked_lp = getAssociatedTokenAddressSync(
+    mint_lp,
+    SystemProgram.programId,
+    true
+  );
 
 
   before(async () => {
     await airdrop(provider, user.publicKey, 2 * LAMPORTS_PER_SOL);
-    mint_x = await createMint(
-      provider.connection,
-      user.payer,
-      user.publicKey,
-      null,
-      6
-    );
-    mint_y = await createMint(
-      provider.connection,
-      user.payer,
-      user.publicKey,
-      null,
-      6
+    [mint_x, mint_y] = sortMints(
+      await createMint(provider.connection, user.payer, user.publicKey, null, 6),
+      await createMint(provider.connection, user.payer, user.publicKey, null, 6)
     );
     vault_ata_x = getAssociatedTokenAddressSync(
       mint_x,
@@ -97,7 +94,7 @@ describe("amm", () => {
   });
 
   it("config initialized!", async () => {
-    await program.methods.initializeConfig(seed, fee)
+    await program.methods.initializeConfig(seed, fee, { constantProduct: {} }, new anchor.BN(0))
       .accountsStrict({
         mintLp: mint_lp,
         mintX: mint_x,
@@ -105,8 +102,11 @@ describe("amm", () => {
         vaultX: vault_ata_x,
         vaultY: vault_ata_y,
         config: config_pda,
+        ...(await registryAccounts(program, mint_x, mint_y)),
         user: user.publicKey,
         tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+        tokenProgramX: anchor.utils.token.TOKEN_PROGRAM_ID,
+        tokenProgramY: anchor.utils.token.TOKEN_PROGRAM_ID,
         associatedTokenProgram: anchor.utils.token.ASSOCIATED_PROGRAM_ID,
         systemProgram: SystemProgram.programId,
       }).signers([user.payer]).rpc();
@@ -117,12 +117,58 @@ describe("amm", () => {
     assert.equal(configAccount.mintX.toBase58(), mint_x.toBase58());
     assert.equal(configAccount.mintY.toBase58(), mint_y.toBase58());
     assert.equal(configAccount.locked, false);
+    const { registry } = await registryAccounts(program, mint_x, mint_y);
+    const registryAccount = await program.account.poolRegistry.fetch(registry);
+    assert.equal(registryAccount.config.toBase58(), config_pda.toBase58());
+  });
+
+  it("registry rejects a second pool for the same pair and unsorted mints", async () => {
+    const duplicate_seed = new anchor.BN(777);
+    const duplicate_config = PublicKey.findProgramAddressSync(
+      [Buffer.from("config"), duplicate_seed.toArrayLike(Buffer, "le", 8)],
+      program.programId
+    )[0];
+    const duplicate_mint_lp = PublicKey.findProgramAddressSync(
+      [Buffer.from("lp"), duplicate_config.toBuffer()],
+      program.programId
+    )[0];
+    const initialize = async (mint_a: PublicKey, mint_b: PublicKey) =>
+      program.methods.initializeConfig(duplicate_seed, fee, { constantProduct: {} }, new anchor.BN(0))
+        .accountsStrict({
+          mintLp: duplicate_mint_lp,
+          mintX: mint_a,
+          mintY: mint_b,
+          vaultX: getAssociatedTokenAddressSync(mint_a, duplicate_config, true),
+          vaultY: getAssociatedTokenAddressSync(mint_b, duplicate_config, true),
+          config: duplicate_config,
+          ...(await registryAccounts(program, mint_a, mint_b)),
+          user: user.publicKey,
+          tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+          tokenProgramX: anchor.utils.token.TOKEN_PROGRAM_ID,
+          tokenProgramY: anchor.utils.token.TOKEN_PROGRAM_ID,
+          associatedTokenProgram: anchor.utils.token.ASSOCIATED_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
+        }).signers([user.payer]).rpc({ commitment: "confirmed" });
+
+    try {
+      await initialize(mint_x, mint_y);
+      assert.fail("a second pool for the same pair should be rejected");
+    } catch (err) {
+      assert.include(err.logs.join("\n"), "already in use");
+    }
+    try {
+      await initialize(mint_y, mint_x);
+      assert.fail("unsorted mints should be rejected");
+    } catch (err) {
+      assert.equal(err.error.errorCode.code, "MintsNotSorted");
+    }
   });
 
   it("add liquidity to pool", async () => {
-    let deposit_amount = 1_000_000;
-    let max_x = 30, max_y = 20;
-    await program.methods.deposit(new anchor.BN(deposit_amount), new anchor.BN(max_x), new anchor.BN(max_y))
+    // The first deposit mints sqrt(x * y) LP; deposit_amount is the minimum accepted.
+    let deposit_amount = 20_000;
+    let max_x = 30_000, max_y = 20_000;
+    await program.methods.deposit(new anchor.BN(deposit_amount), new anchor.BN(max_x), new anchor.BN(max_y), null)
       .accountsStrict({
         config: config_pda,
         mintLp: mint_lp,
@@ -133,8 +179,12 @@ describe("amm", () => {
         depositerAtaX: user_ata_x,
         depositerAtaY: user_ata_y,
         depositerAtaLp: user_ata_lp,
+        lockedLiquidityOwner: SystemProgram.programId,
+        lockedLp: locked_lp,
         depositer: user.publicKey,
         tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+        tokenProgramX: anchor.utils.token.TOKEN_PROGRAM_ID,
+        tokenProgramY: anchor.utils.token.TOKEN_PROGRAM_ID,
         associatedTokenProgram: anchor.utils.token.ASSOCIATED_PROGRAM_ID,
         systemProgram: SystemProgram.programId,
       }).signers([user.payer]).rpc({ commitment: "confirmed" });
@@ -142,17 +192,19 @@ describe("amm", () => {
     const user_ata_lp_account = await getAccount(provider.connection, user_ata_lp);
     const vault_x_account = await getAccount(provider.connection, vault_ata_x, 'confirmed');
     const vault_y_account = await getAccount(provider.connection, vault_ata_y, 'confirmed');
+    const locked_lp_account = await getAccount(provider.connection, locked_lp, 'confirmed');
     const mint_lp_account = await getMint(provider.connection, mint_lp, 'confirmed');
-    assert.equal(user_ata_lp_account.amount > 0, true);
-    assert.equal(vault_x_account.amount == BigInt(30), true);
-    assert.equal(vault_y_account.amount == BigInt(20), true);
-    assert.equal(mint_lp_account.supply == user_ata_lp_account.amount, true);
+    assert.equal(user_ata_lp_account.amount == BigInt(24_494 - 1_000), true);
+    assert.equal(locked_lp_account.amount == BigInt(1_000), true);
+    assert.equal(vault_x_account.amount == BigInt(30_000), true);
+    assert.equal(vault_y_account.amount == BigInt(20_000), true);
+    assert.equal(mint_lp_account.supply == user_ata_lp_account.amount + locked_lp_account.amount, true);
   })
 
   it("withdraw liquidity from pool", async () => {
-    let withdraw_amount = 500_000;
+    let withdraw_amount = 10_000;
     let min_x = 10, min_y = 5;
-    await program.methods.withdraw(new anchor.BN(withdraw_amount), new anchor.BN(min_x), new anchor.BN(min_y))
+    await program.methods.withdraw(new anchor.BN(withdraw_amount), new anchor.BN(min_x), new anchor.BN(min_y), null)
       .accountsStrict({
         config: config_pda,
         mintLp: mint_lp,
@@ -165,6 +217,8 @@ describe("amm", () => {
         withdrawerAtaLp: user_ata_lp,
         withdrawer: user.publicKey,
         tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+        tokenProgramX: anchor.utils.token.TOKEN_PROGRAM_ID,
+        tokenProgramY: anchor.utils.token.TOKEN_PROGRAM_ID,
         associatedTokenProgram: anchor.utils.token.ASSOCIATED_PROGRAM_ID,
         systemProgram: SystemProgram.programId,
       }).signers([user.payer]).rpc({ commitment: "confirmed" });
@@ -173,15 +227,15 @@ describe("amm", () => {
     const vault_x_account = await getAccount(provider.connection, vault_ata_x, 'confirmed');
     const vault_y_account = await getAccount(provider.connection, vault_ata_y, 'confirmed');
     const mint_lp_account = await getMint(provider.connection, mint_lp, 'confirmed');
-    assert.equal(user_ata_lp_account.amount.toString() == BigInt(500_000).toString(), true);
-    assert.equal(mint_lp_account.supply.toString() == user_ata_lp_account.amount.toString(), true);
+    assert.equal(user_ata_lp_account.amount == BigInt(24_494 - 1_000 - 10_000), true);
+    assert.equal(mint_lp_account.supply == user_ata_lp_account.amount + BigInt(1_000), true);
   })
   it("swap x for y", async () => {
     let swap_amount = 10;
     let min_y = 3;
     const user_ata_oldx_account = await getAccount(provider.connection, user_ata_x, 'confirmed');
     const user_ata_oldy_account = await getAccount(provider.connection, user_ata_y, 'confirmed');
-    await program.methods.swap(true, new anchor.BN(swap_amount), new anchor.BN(min_y))
+    await program.methods.swap(true, new anchor.BN(swap_amount), new anchor.BN(min_y), null)
       .accountsStrict({
         user: user.publicKey,
         mintX: mint_x,
@@ -191,7 +245,8 @@ describe("amm", () => {
         userAtaX: user_ata_x,
         userAtaY: user_ata_y,
         config: config_pda,
-        tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+        tokenProgramX: anchor.utils.token.TOKEN_PROGRAM_ID,
+        tokenProgramY: anchor.utils.token.TOKEN_PROGRAM_ID,
         associatedTokenProgram: anchor.utils.token.ASSOCIATED_PROGRAM_ID,
         systemProgram: SystemProgram.programId,
       }).signers([user.payer]).rpc({ commitment: "confirmed" });
@@ -209,7 +264,7 @@ describe("amm", () => {
     const user_ata_oldx_account = await getAccount(provider.connection, user_ata_x, 'confirmed');
     const user_ata_oldy_account = await getAccount(provider.connection, user_ata_y, 'confirmed');
 
-    await program.methods.swap(false, new anchor.BN(swap_amount), new anchor.BN(min_x))
+    await program.methods.swap(false, new anchor.BN(swap_amount), new anchor.BN(min_x), null)
       .accountsStrict({
         user: user.publicKey,
         mintX: mint_x,
@@ -219,7 +274,8 @@ describe("amm", () => {
         userAtaX: user_ata_x,
         userAtaY: user_ata_y,
         config: config_pda,
-        tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+        tokenProgramX: anchor.utils.token.TOKEN_PROGRAM_ID,
+        tokenProgramY: anchor.utils.token.TOKEN_PROGRAM_ID,
         associatedTokenProgram: anchor.utils.token.ASSOCIATED_PROGRAM_ID,
         systemProgram: SystemProgram.programId,
       }).signers([user.payer]).rpc({ commitment: "confirmed" });
@@ -229,10 +285,940 @@ describe("amm", () => {
     assert.equal(user_ata_oldx_account.amount < user_ata_x_account.amount, true);
     assert.equal(user_ata_y_account.amount < user_ata_oldy_account.amount, true);
   });
+  it("swap exact out x for y", async () => {
+    let amount_out = 2;
+    let max_x = 1_000;
+    const user_ata_oldx_account = await getAccount(provider.connection, user_ata_x, 'confirmed');
+    const user_ata_oldy_account = await getAccount(provider.connection, user_ata_y, 'confirmed');
+
+    await program.methods.swapExactOut(true, new anchor.BN(amount_out), new anchor.BN(max_x), null)
+      .accountsStrict({
+        user: user.publicKey,
+        mintX: mint_x,
+        mintY: mint_y,
+        vaultX: vault_ata_x,
+        vaultY: vault_ata_y,
+        userAtaX: user_ata_x,
+        userAtaY: user_ata_y,
+        config: config_pda,
+        tokenProgramX: anchor.utils.token.TOKEN_PROGRAM_ID,
+        tokenProgramY: anchor.utils.token.TOKEN_PROGRAM_ID,
+        associatedTokenProgram: anchor.utils.token.ASSOCIATED_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      }).signers([user.payer]).rpc({ commitment: "confirmed" });
+
+    const user_ata_x_account = await getAccount(provider.connection, user_ata_x, 'confirmed');
+    const user_ata_y_account = await getAccount(provider.connection, user_ata_y, 'confirmed');
+    assert.equal(user_ata_y_account.amount - user_ata_oldy_account.amount == BigInt(amount_out), true);
+    assert.equal(user_ata_oldx_account.amount - user_ata_x_account.amount <= BigInt(max_x), true);
+  });
+  it("route swap x -> y -> x with one end-to-end slippage check", async () => {
+    const hop = (mint_in: PublicKey, mint_out: PublicKey, vault_in: PublicKey, vault_out: PublicKey, ata_in: PublicKey, ata_out: PublicKey) => [
+      { pubkey: config_pda, isSigner: false, isWritable: true },
+      { pubkey: mint_in, isSigner: false, isWritable: false },
+      { pubkey: mint_out, isSigner: false, isWritable: false },
+      { pubkey: vault_in, isSigner: false, isWritable: true },
+      { pubkey: vault_out, isSigner: false, isWritable: true },
+      { pubkey: ata_in, isSigner: false, isWritable: true },
+      { pubkey: ata_out, isSigner: false, isWritable: true },
+      { pubkey: anchor.utils.token.TOKEN_PROGRAM_ID, isSigner: false, isWritable: false },
+      { pubkey: anchor.utils.token.TOKEN_PROGRAM_ID, isSigner: false, isWritable: false },
+    ];
+    const user_ata_oldx_account = await getAccount(provider.connection, user_ata_x, 'confirmed');
+
+    await program.methods.routeSwap(new anchor.BN(1_000), new anchor.BN(1), null)
+      .accountsStrict({
+        user: user.publicKey,
+      })
+      .remainingAccounts([
+        ...hop(mint_x, mint_y, vault_ata_x, vault_ata_y, user_ata_x, user_ata_y),
+        ...hop(mint_y, mint_x, vault_ata_y, vault_ata_x, user_ata_y, user_ata_x),
+      ])
+      .signers([user.payer]).rpc({ commitment: "confirmed" });
+
+    // Round-tripping through the same pool pays the fee twice.
+    const user_ata_x_account = await getAccount(provider.connection, user_ata_x, 'confirmed');
+    assert.equal(user_ata_x_account.amount < user_ata_oldx_account.amount, true);
+  });
+  it("single-sided deposit mints lp from y only", async () => {
+    const user_ata_oldx_account = await getAccount(provider.connection, user_ata_x, 'confirmed');
+    const user_ata_oldlp_account = await getAccount(provider.connection, user_ata_lp, 'confirmed');
+    await program.methods.depositSingleSided(false, new anchor.BN(10_000), new anchor.BN(1), null)
+      .accountsStrict({
+        config: config_pda,
+        mintLp: mint_lp,
+        mintX: mint_x,
+        mintY: mint_y,
+        vaultX: vault_ata_x,
+        vaultY: vault_ata_y,
+        depositerAtaX: user_ata_x,
+        depositerAtaY: user_ata_y,
+        depositerAtaLp: user_ata_lp,
+        lockedLiquidityOwner: SystemProgram.programId,
+        lockedLp: locked_lp,
+        depositer: user.publicKey,
+        tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+        tokenProgramX: anchor.utils.token.TOKEN_PROGRAM_ID,
+        tokenProgramY: anchor.utils.token.TOKEN_PROGRAM_ID,
+        associatedTokenProgram: anchor.utils.token.ASSOCIATED_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      }).signers([user.payer]).rpc({ commitment: "confirmed" });
+
+    const user_ata_x_account = await getAccount(provider.connection, user_ata_x, 'confirmed');
+    const user_ata_lp_account = await getAccount(provider.connection, user_ata_lp, 'confirmed');
+    assert.equal(user_ata_lp_account.amount > user_ata_oldlp_account.amount, true);
+    assert.equal(user_ata_x_account.amount == user_ata_oldx_account.amount, true);
+  });
+  it("observe returns accumulated prices", async () => {
+    const configAccount = await program.account.config.fetch(config_pda);
+    const observation = await program.methods.observe()
+      .accountsStrict({ config: config_pda })
+      .view();
+    // The last swap or deposit recorded the reserves the oracle prices from.
+    const vault_x_account = await getAccount(provider.connection, vault_ata_x, 'confirmed');
+    assert.equal(configAccount.reserveXLast.toString(), (vault_x_account.amount - BigInt(configAccount.protocolFeesX.toString())).toString());
+    assert.equal(observation.timestamp.gte(configAccount.lastUpdateTs), true);
+    assert.equal(observation.priceXCumulative.gte(configAccount.priceXCumulative), true);
+    assert.equal(observation.priceYCumulative.gte(configAccount.priceYCumulative), true);
+  });
+  it("locked pool rejects swaps until unlocked", async () => {
+    await program.methods.lock()
+      .accountsStrict({ authority: user.publicKey, config: config_pda })
+      .signers([user.payer]).rpc({ commitment: "confirmed" });
+    assert.equal((await program.account.config.fetch(config_pda)).locked, true);
+
+    try {
+      await program.methods.swap(true, new anchor.BN(10), new anchor.BN(0), null)
+        .accountsStrict({
+          user: user.publicKey,
+          mintX: mint_x,
+          mintY: mint_y,
+          vaultX: vault_ata_x,
+          vaultY: vault_ata_y,
+          userAtaX: user_ata_x,
+          userAtaY: user_ata_y,
+          config: config_pda,
+          tokenProgramX: anchor.utils.token.TOKEN_PROGRAM_ID,
+          tokenProgramY: anchor.utils.token.TOKEN_PROGRAM_ID,
+          associatedTokenProgram: anchor.utils.token.ASSOCIATED_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
+        }).signers([user.payer]).rpc({ commitment: "confirmed" });
+      assert.fail("swap should fail while the pool is locked");
+    } catch (err) {
+      assert.equal(err.error.errorCode.code, "ConfigLocked");
+    }
+
+    await program.methods.unlock()
+      .accountsStrict({ authority: user.publicKey, config: config_pda })
+      .signers([user.payer]).rpc({ commitment: "confirmed" });
+    assert.equal((await program.account.config.fetch(config_pda)).locked, false);
+  });
+  it("update fee", async () => {
+    await program.methods.updateFee(30)
+      .accountsStrict({ authority: user.publicKey, config: config_pda })
+      .signers([user.payer]).rpc({ commitment: "confirmed" });
+    assert.equal((await program.account.config.fetch(config_pda)).fee, 30);
+  });
+  it("amplification ramp is rejected on constant-product pools", async () => {
+    try {
+      await program.methods.rampAmp(new anchor.BN(100), new anchor.BN(Math.floor(Date.now() / 1000) + 2 * 86_400))
+        .accountsStrict({ authority: user.publicKey, config: config_pda })
+        .signers([user.payer]).rpc({ commitment: "confirmed" });
+      assert.fail("ramp_amp should fail on a constant-product pool");
+    } catch (err) {
+      assert.equal(err.error.errorCode.code, "InvalidCurve");
+    }
+  });
+  it("transfer authority takes effect only once accepted", async () => {
+    const new_authority = Keypair.generate();
+    await program.methods.transferAuthority(new_authority.publicKey)
+      .accountsStrict({ authority: user.publicKey, config: config_pda })
+      .signers([user.payer]).rpc({ commitment: "confirmed" });
+    let configAccount = await program.account.config.fetch(config_pda);
+    assert.equal(configAccount.authority?.toBase58(), user.publicKey.toBase58());
+    assert.equal(configAccount.pendingAuthority?.toBase58(), new_authority.publicKey.toBase58());
+
+    await program.methods.acceptAuthority()
+      .accountsStrict({ newAuthority: new_authority.publicKey, config: config_pda })
+      .signers([new_authority]).rpc({ commitment: "confirmed" });
+    configAccount = await program.account.config.fetch(config_pda);
+    assert.equal(configAccount.authority?.toBase58(), new_authority.publicKey.toBase58());
+    assert.equal(configAccount.pendingAuthority, null);
+
+    await program.methods.transferAuthority(user.publicKey)
+      .accountsStrict({ authority: new_authority.publicKey, config: config_pda })
+      .signers([new_authority]).rpc({ commitment: "confirmed" });
+    await program.methods.acceptAuthority()
+      .accountsStrict({ newAuthority: user.publicKey, config: config_pda })
+      .signers([user.payer]).rpc({ commitment: "confirmed" });
+    configAccount = await program.account.config.fetch(config_pda);
+    assert.equal(configAccount.authority?.toBase58(), user.publicKey.toBase58());
+  });
+  it("protocol fees accrue on swaps and are collected to the treasury", async () => {
+    const treasury = Keypair.generate();
+    const treasury_ata_x = getAssociatedTokenAddressSync(mint_x, treasury.publicKey);
+    const treasury_ata_y = getAssociatedTokenAddressSync(mint_y, treasury.publicKey);
+    await program.methods.updateProtocolFee(5_000)
+      .accountsStrict({ authority: user.publicKey, config: config_pda })
+      .signers([user.payer]).rpc({ commitment: "confirmed" });
+    await program.methods.setTreasury(treasury.publicKey)
+      .accountsStrict({ authority: user.publicKey, config: config_pda })
+      .signers([user.payer]).rpc({ commitment: "confirmed" });
+
+    await program.methods.swap(true, new anchor.BN(100_000), new anchor.BN(0), null)
+      .accountsStrict({
+        user: user.publicKey,
+        mintX: mint_x,
+        mintY: mint_y,
+        vaultX: vault_ata_x,
+        vaultY: vault_ata_y,
+        userAtaX: user_ata_x,
+        userAtaY: user_ata_y,
+        config: config_pda,
+        tokenProgramX: anchor.utils.token.TOKEN_PROGRAM_ID,
+        tokenProgramY: anchor.utils.token.TOKEN_PROGRAM_ID,
+        associatedTokenProgram: anchor.utils.token.ASSOCIATED_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      }).signers([user.payer]).rpc({ commitment: "confirmed" });
+    let configAccount = await program.account.config.fetch(config_pda);
+    const accrued_x = BigInt(configAccount.protocolFeesX.toString());
+    assert.equal(accrued_x > BigInt(0), true);
+
+    await program.methods.collectProtocolFees()
+      .accountsStrict({
+        authority: user.publicKey,
+        treasury: treasury.publicKey,
+        mintX: mint_x,
+        mintY: mint_y,
+        vaultX: vault_ata_x,
+        vaultY: vault_ata_y,
+        treasuryAtaX: treasury_ata_x,
+        treasuryAtaY: treasury_ata_y,
+        config: config_pda,
+        tokenProgramX: anchor.utils.token.TOKEN_PROGRAM_ID,
+        tokenProgramY: anchor.utils.token.TOKEN_PROGRAM_ID,
+        associatedTokenProgram: anchor.utils.token.ASSOCIATED_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      }).signers([user.payer]).rpc({ commitment: "confirmed" });
+    configAccount = await program.account.config.fetch(config_pda);
+    const treasury_x_account = await getAccount(provider.connection, treasury_ata_x, 'confirmed');
+    assert.equal(treasury_x_account.amount == accrued_x, true);
+    assert.equal(configAccount.protocolFeesX.toNumber(), 0);
+    assert.equal(configAccount.protocolFeesY.toNumber(), 0);
+
+    try {
+      await program.methods.collectProtocolFees()
+        .accountsStrict({
+          authority: treasury.publicKey,
+          treasury: treasury.publicKey,
+          mintX: mint_x,
+          mintY: mint_y,
+          vaultX: vault_ata_x,
+          vaultY: vault_ata_y,
+          treasuryAtaX: treasury_ata_x,
+          treasuryAtaY: treasury_ata_y,
+          config: config_pda,
+          tokenProgramX: anchor.utils.token.TOKEN_PROGRAM_ID,
+          tokenProgramY: anchor.utils.token.TOKEN_PROGRAM_ID,
+          associatedTokenProgram: anchor.utils.token.ASSOCIATED_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
+        }).signers([treasury]).rpc({ commitment: "confirmed" });
+      assert.fail("only the pool authority may collect protocol fees");
+    } catch (err) {
+      assert.equal(err.error.errorCode.code, "Unauthorized");
+    }
+  });
+  it("token-2022 pool accepts deposits", async () => {
+    const seed_2022 = new anchor.BN(987654321);
+    const config_2022 = PublicKey.findProgramAddressSync(
+      [Buffer.from("config"), seed_2022.toArrayLike(Buffer, "le", 8)],
+      program.programId
+    )[0];
+    const mint_lp_2022 = PublicKey.findProgramAddressSync(
+      [Buffer.from("lp"), config_2022.toBuffer()],
+      program.programId
+    )[0];
+    const [mint_x_2022, mint_y_2022] = sortMints(
+      await createMint(provider.connection, user.payer, user.publicKey, null, 6, undefined, undefined, TOKEN_2022_PROGRAM_ID),
+      await createMint(provider.connection, user.payer, user.publicKey, null, 6, undefined, undefined, TOKEN_2022_PROGRAM_ID)
+    );
+    const vault_x_2022 = getAssociatedTokenAddressSync(mint_x_2022, config_2022, true, TOKEN_2022_PROGRAM_ID);
+    const vault_y_2022 = getAssociatedTokenAddressSync(mint_y_2022, config_2022, true, TOKEN_2022_PROGRAM_ID);
+    const user_x_2022 = (await getOrCreateAssociatedTokenAccount(provider.connection, user.payer, mint_x_2022, user.publicKey, false, undefined, undefined, TOKEN_2022_PROGRAM_ID)).address;
+    const user_y_2022 = (await getOrCreateAssociatedTokenAccount(provider.connection, user.payer, mint_y_2022, user.publicKey, false, undefined, undefined, TOKEN_2022_PROGRAM_ID)).address;
+    const user_lp_2022 = getAssociatedTokenAddressSync(mint_lp_2022, user.publicKey, false, TOKEN_2022_PROGRAM_ID);
+    const locked_lp_2022 = getAssociatedTokenAddressSync(mint_lp_2022, SystemProgram.programId, true, TOKEN_2022_PROGRAM_ID);
+    await mintTo(provider.connection, user.payer, mint_x_2022, user_x_2022, user.payer, 1_000_000, [], undefined, TOKEN_2022_PROGRAM_ID);
+    await mintTo(provider.connection, user.payer, mint_y_2022, user_y_2022, user.payer, 1_000_000, [], undefined, TOKEN_2022_PROGRAM_ID);
+
+    await program.methods.initializeConfig(seed_2022, fee, { constantProduct: {} }, new anchor.BN(0))
+      .accountsStrict({
+        mintLp: mint_lp_2022,
+        mintX: mint_x_2022,
+        mintY: mint_y_2022,
+        vaultX: vault_x_2022,
+        vaultY: vault_y_2022,
+        config: config_2022,
+        ...(await registryAccounts(program, mint_x_2022, mint_y_2022)),
+        user: user.publicKey,
+        tokenProgram: TOKEN_2022_PROGRAM_ID,
+        tokenProgramX: TOKEN_2022_PROGRAM_ID,
+        tokenProgramY: TOKEN_2022_PROGRAM_ID,
+        associatedTokenProgram: anchor.utils.token.ASSOCIATED_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      }).signers([user.payer]).rpc({ commitment: "confirmed" });
+
+    await program.methods.deposit(new anchor.BN(1), new anchor.BN(30_000), new anchor.BN(20_000), null)
+      .accountsStrict({
+        config: config_2022,
+        mintLp: mint_lp_2022,
+        mintX: mint_x_2022,
+        mintY: mint_y_2022,
+        vaultX: vault_x_2022,
+        vaultY: vault_y_2022,
+        depositerAtaX: user_x_2022,
+        depositerAtaY: user_y_2022,
+        depositerAtaLp: user_lp_2022,
+        lockedLiquidityOwner: SystemProgram.programId,
+        lockedLp: locked_lp_2022,
+        depositer: user.publicKey,
+        tokenProgram: TOKEN_2022_PROGRAM_ID,
+        tokenProgramX: TOKEN_2022_PROGRAM_ID,
+        tokenProgramY: TOKEN_2022_PROGRAM_ID,
+        associatedTokenProgram: anchor.utils.token.ASSOCIATED_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      }).signers([user.payer]).rpc({ commitment: "confirmed" });
+
+    const vault_x_account = await getAccount(provider.connection, vault_x_2022, 'confirmed', TOKEN_2022_PROGRAM_ID);
+    const user_lp_account = await getAccount(provider.connection, user_lp_2022, 'confirmed', TOKEN_2022_PROGRAM_ID);
+    assert.equal(vault_x_account.amount == BigInt(30_000), true);
+    assert.equal(user_lp_account.amount > BigInt(0), true);
+  });
+  it("pool mixing spl-token and token-2022 mints accepts deposits", async () => {
+    const seed_mixed = new anchor.BN(192837465);
+    const config_mixed = PublicKey.findProgramAddressSync(
+      [Buffer.from("config"), seed_mixed.toArrayLike(Buffer, "le", 8)],
+      program.programId
+    )[0];
+    const mint_lp_mixed = PublicKey.findProgramAddressSync(
+      [Buffer.from("lp"), config_mixed.toBuffer()],
+      program.programId
+    )[0];
+    const mint_legacy = await createMint(provider.connection, user.payer, user.publicKey, null, 6);
+    const mint_2022 = await createMint(provider.connection, user.payer, user.publicKey, null, 6, undefined, undefined, TOKEN_2022_PROGRAM_ID);
+    const [mint_x_mixed, mint_y_mixed] = sortMints(mint_legacy, mint_2022);
+    const programOf = (mint: PublicKey) => mint.equals(mint_2022) ? TOKEN_2022_PROGRAM_ID : anchor.utils.token.TOKEN_PROGRAM_ID;
+    const [program_x, program_y] = [programOf(mint_x_mixed), programOf(mint_y_mixed)];
+    const vault_x_mixed = getAssociatedTokenAddressSync(mint_x_mixed, config_mixed, true, program_x);
+    const vault_y_mixed = getAssociatedTokenAddressSync(mint_y_mixed, config_mixed, true, program_y);
+    const user_x_mixed = (await getOrCreateAssociatedTokenAccount(provider.connection, user.payer, mint_x_mixed, user.publicKey, false, undefined, undefined, program_x)).address;
+    const user_y_mixed = (await getOrCreateAssociatedTokenAccount(provider.connection, user.payer, mint_y_mixed, user.publicKey, false, undefined, undefined, program_y)).address;
+    const user_lp_mixed = getAssociatedTokenAddressSync(mint_lp_mixed, user.publicKey);
+    const locked_lp_mixed = getAssociatedTokenAddressSync(mint_lp_mixed, SystemProgram.programId, true);
+    await mintTo(provider.connection, user.payer, mint_x_mixed, user_x_mixed, user.payer, 1_000_000, [], undefined, program_x);
+    await mintTo(provider.connection, user.payer, mint_y_mixed, user_y_mixed, user.payer, 1_000_000, [], undefined, program_y);
+
+    await program.methods.initializeConfig(seed_mixed, fee, { constantProduct: {} }, new anchor.BN(0))
+      .accountsStrict({
+        mintLp: mint_lp_mixed,
+        mintX: mint_x_mixed,
+        mintY: mint_y_mixed,
+        vaultX: vault_x_mixed,
+        vaultY: vault_y_mixed,
+        config: config_mixed,
+        ...(await registryAccounts(program, mint_x_mixed, mint_y_mixed)),
+        user: user.publicKey,
+        tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+        tokenProgramX: program_x,
+        tokenProgramY: program_y,
+        associatedTokenProgram: anchor.utils.token.ASSOCIATED_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      }).signers([user.payer]).rpc({ commitment: "confirmed" });
+
+    await program.methods.deposit(new anchor.BN(1), new anchor.BN(30_000), new anchor.BN(20_000), null)
+      .accountsStrict({
+        config: config_mixed,
+        mintLp: mint_lp_mixed,
+        mintX: mint_x_mixed,
+        mintY: mint_y_mixed,
+        vaultX: vault_x_mixed,
+        vaultY: vault_y_mixed,
+        depositerAtaX: user_x_mixed,
+        depositerAtaY: user_y_mixed,
+        depositerAtaLp: user_lp_mixed,
+        lockedLiquidityOwner: SystemProgram.programId,
+        lockedLp: locked_lp_mixed,
+        depositer: user.publicKey,
+        tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+        tokenProgramX: program_x,
+        tokenProgramY: program_y,
+        associatedTokenProgram: anchor.utils.token.ASSOCIATED_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      }).signers([user.payer]).rpc({ commitment: "confirmed" });
+
+    const vault_x_account = await getAccount(provider.connection, vault_x_mixed, 'confirmed', program_x);
+    const vault_y_account = await getAccount(provider.connection, vault_y_mixed, 'confirmed', program_y);
+    assert.equal(vault_x_account.amount == BigInt(30_000), true);
+    assert.equal(vault_y_account.amount == BigInt(20_000), true);
+  });
+  it("swap emits Swapped with post-swap reserves", async () => {
+    const signature = await program.methods.swap(true, new anchor.BN(100), new anchor.BN(1), null)
+      .accountsStrict({
+        user: user.publicKey,
+        mintX: mint_x,
+        mintY: mint_y,
+        vaultX: vault_ata_x,
+        vaultY: vault_ata_y,
+        userAtaX: user_ata_x,
+        userAtaY: user_ata_y,
+        config: config_pda,
+        tokenProgramX: anchor.utils.token.TOKEN_PROGRAM_ID,
+        tokenProgramY: anchor.utils.token.TOKEN_PROGRAM_ID,
+        associatedTokenProgram: anchor.utils.token.ASSOCIATED_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      }).signers([user.payer]).rpc({ commitment: "confirmed" });
+
+    const tx = await provider.connection.getTransaction(signature, { commitment: "confirmed", maxSupportedTransactionVersion: 0 });
+    const parser = new anchor.EventParser(program.programId, new anchor.BorshCoder(program.idl));
+    const events = [...parser.parseLogs(tx.meta.logMessages)];
+    assert.equal(events.length, 1);
+    assert.equal(events[0].name, "swapped");
+
+    const swapped = events[0].data;
+    const configAccount = await program.account.config.fetch(config_pda);
+    const vault_x_account = await getAccount(provider.connection, vault_ata_x, 'confirmed');
+    const vault_y_account = await getAccount(provider.connection, vault_ata_y, 'confirmed');
+    assert.equal(swapped.isXToY, true);
+    assert.equal(swapped.amountIn.toNumber(), 100);
+    assert.equal(swapped.fee.toNumber() > 0, true);
+    assert.equal(swapped.reserveX.toString(), (vault_x_account.amount - BigInt(configAccount.protocolFeesX.toString())).toString());
+    assert.equal(swapped.reserveY.toString(), (vault_y_account.amount - BigInt(configAccount.protocolFeesY.toString())).toString());
+  });
+  it("swap rejects a zero amount_in and an output that rounds to zero", async () => {
+    const accounts = {
+      user: user.publicKey,
+      mintX: mint_x,
+      mintY: mint_y,
+      vaultX: vault_ata_x,
+      vaultY: vault_ata_y,
+      userAtaX: user_ata_x,
+      userAtaY: user_ata_y,
+      config: config_pda,
+      tokenProgramX: anchor.utils.token.TOKEN_PROGRAM_ID,
+      tokenProgramY: anchor.utils.token.TOKEN_PROGRAM_ID,
+      associatedTokenProgram: anchor.utils.token.ASSOCIATED_PROGRAM_ID,
+      systemProgram: SystemProgram.programId,
+    };
+    try {
+      await program.methods.swap(true, new anchor.BN(0), new anchor.BN(0), null)
+        .accountsStrict(accounts).signers([user.payer]).rpc({ commitment: "confirmed" });
+      assert.fail("zero amount_in should be rejected");
+    } catch (err) {
+      assert.equal(err.error.errorCode.code, "InvalidAmount");
+    }
+    // 1 unit in is eaten entirely by the fee.
+    try {
+      await program.methods.swap(true, new anchor.BN(1), new anchor.BN(0), null)
+        .accountsStrict(accounts).signers([user.payer]).rpc({ commitment: "confirmed" });
+      assert.fail("swap producing no output should be rejected");
+    } catch (err) {
+      assert.equal(err.error.errorCode.code, "ZeroOutput");
+    }
+  });
+  it("deposit fails with Overflow when the pool's share of the reserves exceeds u64", async () => {
+    const u64_max = new anchor.BN("18446744073709551615");
+    try {
+      await program.methods.deposit(u64_max, u64_max, u64_max, null)
+        .accountsStrict({
+          config: config_pda,
+          mintLp: mint_lp,
+          mintX: mint_x,
+          mintY: mint_y,
+          vaultX: vault_ata_x,
+          vaultY: vault_ata_y,
+          depositerAtaX: user_ata_x,
+          depositerAtaY: user_ata_y,
+          depositerAtaLp: user_ata_lp,
+          lockedLiquidityOwner: SystemProgram.programId,
+          lockedLp: locked_lp,
+          depositer: user.publicKey,
+          tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+          tokenProgramX: anchor.utils.token.TOKEN_PROGRAM_ID,
+          tokenProgramY: anchor.utils.token.TOKEN_PROGRAM_ID,
+          associatedTokenProgram: anchor.utils.token.ASSOCIATED_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
+        }).signers([user.payer]).rpc({ commitment: "confirmed" });
+      assert.fail("a deposit needing more than u64::MAX of a reserve should be rejected");
+    } catch (err) {
+      assert.equal(err.error.errorCode.code, "Overflow");
+    }
+  });
+  it("swaps past their expires_at deadline are rejected", async () => {
+    const expired = new anchor.BN(Math.floor(Date.now() / 1000) - 60);
+    const accounts = {
+      user: user.publicKey,
+      mintX: mint_x,
+      mintY: mint_y,
+      vaultX: vault_ata_x,
+      vaultY: vault_ata_y,
+      userAtaX: user_ata_x,
+      userAtaY: user_ata_y,
+      config: config_pda,
+      tokenProgramX: anchor.utils.token.TOKEN_PROGRAM_ID,
+      tokenProgramY: anchor.utils.token.TOKEN_PROGRAM_ID,
+      associatedTokenProgram: anchor.utils.token.ASSOCIATED_PROGRAM_ID,
+      systemProgram: SystemProgram.programId,
+    };
+    try {
+      await program.methods.swap(true, new anchor.BN(100), new anchor.BN(0), expired)
+        .accountsStrict(accounts).signers([user.payer]).rpc({ commitment: "confirmed" });
+      assert.fail("expired swap should be rejected");
+    } catch (err) {
+      assert.equal(err.error.errorCode.code, "Expired");
+    }
+    try {
+      await program.methods.swapExactOut(true, new anchor.BN(100), new anchor.BN(1_000), expired)
+        .accountsStrict(accounts).signers([user.payer]).rpc({ commitment: "confirmed" });
+      assert.fail("expired exact-out swap should be rejected");
+    } catch (err) {
+      assert.equal(err.error.errorCode.code, "Expired");
+    }
+  });
+  it("swap and single-sided deposit fail with EmptyPool before any liquidity", async () => {
+    const empty_seed = new anchor.BN(555);
+    const empty_config = PublicKey.findProgramAddressSync(
+      [Buffer.from("config"), empty_seed.toArrayLike(Buffer, "le", 8)],
+      program.programId
+    )[0];
+    const empty_mint_lp = PublicKey.findProgramAddressSync(
+      [Buffer.from("lp"), empty_config.toBuffer()],
+      program.programId
+    )[0];
+    const [empty_mint_x, empty_mint_y] = sortMints(
+      await createMint(provider.connection, user.payer, user.publicKey, null, 6),
+      await createMint(provider.connection, user.payer, user.publicKey, null, 6)
+    );
+    const empty_vault_x = getAssociatedTokenAddressSync(empty_mint_x, empty_config, true);
+    const empty_vault_y = getAssociatedTokenAddressSync(empty_mint_y, empty_config, true);
+    const empty_user_x = (await getOrCreateAssociatedTokenAccount(provider.connection, user.payer, empty_mint_x, user.publicKey)).address;
+    const empty_user_y = (await getOrCreateAssociatedTokenAccount(provider.connection, user.payer, empty_mint_y, user.publicKey)).address;
+    await mintTo(provider.connection, user.payer, empty_mint_x, empty_user_x, user.payer, 1_000_000);
+
+    await program.methods.initializeConfig(empty_seed, fee, { constantProduct: {} }, new anchor.BN(0))
+      .accountsStrict({
+        mintLp: empty_mint_lp,
+        mintX: empty_mint_x,
+        mintY: empty_mint_y,
+        vaultX: empty_vault_x,
+        vaultY: empty_vault_y,
+        config: empty_config,
+        ...(await registryAccounts(program, empty_mint_x, empty_mint_y)),
+        user: user.publicKey,
+        tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+        tokenProgramX: anchor.utils.token.TOKEN_PROGRAM_ID,
+        tokenProgramY: anchor.utils.token.TOKEN_PROGRAM_ID,
+        associatedTokenProgram: anchor.utils.token.ASSOCIATED_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      }).signers([user.payer]).rpc({ commitment: "confirmed" });
+
+    try {
+      await program.methods.swap(true, new anchor.BN(1_000), new anchor.BN(0), null)
+        .accountsStrict({
+          user: user.publicKey,
+          mintX: empty_mint_x,
+          mintY: empty_mint_y,
+          vaultX: empty_vault_x,
+          vaultY: empty_vault_y,
+          userAtaX: empty_user_x,
+          userAtaY: empty_user_y,
+          config: empty_config,
+          tokenProgramX: anchor.utils.token.TOKEN_PROGRAM_ID,
+          tokenProgramY: anchor.utils.token.TOKEN_PROGRAM_ID,
+          associatedTokenProgram: anchor.utils.token.ASSOCIATED_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
+        }).signers([user.payer]).rpc({ commitment: "confirmed" });
+      assert.fail("swap against an empty pool should be rejected");
+    } catch (err) {
+      assert.equal(err.error.errorCode.code, "EmptyPool");
+    }
+    try {
+      await program.methods.depositSingleSided(true, new anchor.BN(1_000), new anchor.BN(1), null)
+        .accountsStrict({
+          config: empty_config,
+          mintLp: empty_mint_lp,
+          mintX: empty_mint_x,
+          mintY: empty_mint_y,
+          vaultX: empty_vault_x,
+          vaultY: empty_vault_y,
+          depositerAtaX: empty_user_x,
+          depositerAtaY: empty_user_y,
+          depositerAtaLp: getAssociatedTokenAddressSync(empty_mint_lp, user.publicKey),
+          lockedLiquidityOwner: SystemProgram.programId,
+          lockedLp: getAssociatedTokenAddressSync(empty_mint_lp, SystemProgram.programId, true),
+          depositer: user.publicKey,
+          tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+          tokenProgramX: anchor.utils.token.TOKEN_PROGRAM_ID,
+          tokenProgramY: anchor.utils.token.TOKEN_PROGRAM_ID,
+          associatedTokenProgram: anchor.utils.token.ASSOCIATED_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
+        }).signers([user.payer]).rpc({ commitment: "confirmed" });
+      assert.fail("single-sided deposit into an empty pool should be rejected");
+    } catch (err) {
+      assert.equal(err.error.errorCode.code, "EmptyPool");
+    }
+  });
+  it("concentrated-liquidity position earns swap fees inside its range", async () => {
+    const cl_seed = new anchor.BN(4242);
+    const tickBuffer = (tick: number) => {
+      const buffer = Buffer.alloc(4);
+      buffer.writeInt32LE(tick);
+      return buffer;
+    };
+    const pool = PublicKey.findProgramAddressSync(
+      [Buffer.from("cl_pool"), cl_seed.toArrayLike(Buffer, "le", 8)],
+      program.programId
+    )[0];
+    const [tick_lower, tick_upper] = [-600, 600];
+    const position = PublicKey.findProgramAddressSync(
+      [Buffer.from("position"), pool.toBuffer(), user.publicKey.toBuffer(), tickBuffer(tick_lower), tickBuffer(tick_upper)],
+      program.programId
+    )[0];
+    const lower_tick = PublicKey.findProgramAddressSync(
+      [Buffer.from("tick"), pool.toBuffer(), tickBuffer(tick_lower)],
+      program.programId
+    )[0];
+    const upper_tick = PublicKey.findProgramAddressSync(
+      [Buffer.from("tick"), pool.toBuffer(), tickBuffer(tick_upper)],
+      program.programId
+    )[0];
+    // With a spacing of 60 the lower tick is in bitmap word -1 and the upper one in word 0.
+    const tickBitmap = (word: number) => PublicKey.findProgramAddressSync(
+      [Buffer.from("tick_bitmap"), pool.toBuffer(), tickBuffer(word)],
+      program.programId
+    )[0];
+    const [lower_bitmap, upper_bitmap] = [tickBitmap(-1), tickBitmap(0)];
+    const cl_vault_x = getAssociatedTokenAddressSync(mint_x, pool, true);
+    const cl_vault_y = getAssociatedTokenAddressSync(mint_y, pool, true);
+
+    // Price 1.0: sqrt price 2^64 in Q64.64.
+    await program.methods.initializeClPool(cl_seed, 30, 60, new anchor.BN(1).shln(64))
+      .accountsStrict({
+        mintX: mint_x,
+        mintY: mint_y,
+        vaultX: cl_vault_x,
+        vaultY: cl_vault_y,
+        pool,
+        user: user.publicKey,
+        tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+        associatedTokenProgram: anchor.utils.token.ASSOCIATED_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      }).signers([user.payer]).rpc({ commitment: "confirmed" });
+
+    await program.methods.openPosition(tick_lower, tick_upper)
+      .accountsStrict({
+        owner: user.publicKey,
+        pool,
+        position,
+        lowerTick: lower_tick,
+        upperTick: upper_tick,
+        lowerBitmap: lower_bitmap,
+        upperBitmap: upper_bitmap,
+        systemProgram: SystemProgram.programId,
+      }).signers([user.payer]).rpc({ commitment: "confirmed" });
+
+    const modifyAccounts = {
+      owner: user.publicKey,
+      pool,
+      position,
+      lowerTick: lower_tick,
+      upperTick: upper_tick,
+      lowerBitmap: lower_bitmap,
+      upperBitmap: upper_bitmap,
+      mintX: mint_x,
+      mintY: mint_y,
+      vaultX: cl_vault_x,
+      vaultY: cl_vault_y,
+      ownerAtaX: user_ata_x,
+      ownerAtaY: user_ata_y,
+      tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+    };
+    const liquidity = new anchor.BN(1_000_000);
+    await program.methods.increaseLiquidity(liquidity, new anchor.BN(100_000), new anchor.BN(100_000))
+      .accountsStrict(modifyAccounts).signers([user.payer]).rpc({ commitment: "confirmed" });
+    let poolAccount = await program.account.clPool.fetch(pool);
+    assert.equal(poolAccount.liquidity.toString(), liquidity.toString());
+    // -600 is spaced tick -10, bit 246 of word -1; 600 is spaced tick 10, bit 10 of word 0.
+    let lowerBitmapAccount = await program.account.tickBitmap.fetch(lower_bitmap);
+    let upperBitmapAccount = await program.account.tickBitmap.fetch(upper_bitmap);
+    assert.equal(lowerBitmapAccount.bits[3].toString(), new anchor.BN(1).shln(54).toString());
+    assert.equal(upperBitmapAccount.bits[0].toNumber(), 1 << 10);
+
+    // Small enough to stay inside the range, so no tick accounts are crossed.
+    // The price starts on tick 0, the bottom of word 0, so the swap walks
+    // from word 0 into word -1.
+    await program.methods.clSwap(true, new anchor.BN(1_000), new anchor.BN(1), new anchor.BN("4295048018"))
+      .accountsStrict({
+        user: user.publicKey,
+        mintX: mint_x,
+        mintY: mint_y,
+        pool,
+        vaultX: cl_vault_x,
+        vaultY: cl_vault_y,
+        userAtaX: user_ata_x,
+        userAtaY: user_ata_y,
+        tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+      })
+      .remainingAccounts([
+        { pubkey: upper_bitmap, isSigner: false, isWritable: false },
+        { pubkey: lower_bitmap, isSigner: false, isWritable: false },
+      ])
+      .signers([user.payer]).rpc({ commitment: "confirmed" });
+    poolAccount = await program.account.clPool.fetch(pool);
+    assert.equal(poolAccount.tickCurrent < 0 && poolAccount.tickCurrent >= tick_lower, true);
+    assert.equal(poolAccount.feeGrowthGlobalX.gtn(0), true);
+
+    const user_ata_oldx_account = await getAccount(provider.connection, user_ata_x, 'confirmed');
+    await program.methods.collectFees()
+      .accountsStrict(modifyAccounts).signers([user.payer]).rpc({ commitment: "confirmed" });
+    const user_ata_x_account = await getAccount(provider.connection, user_ata_x, 'confirmed');
+    // The only position earns the whole 0.3% fee (3 of the 1_000 in), less rounding.
+    const collected = user_ata_x_account.amount - user_ata_oldx_account.amount;
+    assert.equal(collected > BigInt(0) && collected <= BigInt(3), true);
+
+    await program.methods.decreaseLiquidity(liquidity, new anchor.BN(0), new anchor.BN(0))
+      .accountsStrict(modifyAccounts).signers([user.payer]).rpc({ commitment: "confirmed" });
+    poolAccount = await program.account.clPool.fetch(pool);
+    const positionAccount = await program.account.position.fetch(position);
+    assert.equal(poolAccount.liquidity.toNumber(), 0);
+    lowerBitmapAccount = await program.account.tickBitmap.fetch(lower_bitmap);
+    upperBitmapAccount = await program.account.tickBitmap.fetch(upper_bitmap);
+    assert.equal([...lowerBitmapAccount.bits, ...upperBitmapAccount.bits].every((bits) => bits.isZero()), true);
+    assert.equal(positionAccount.liquidity.toNumber(), 0);
+
+    // The emptied position and the ticks only it used give their rent back.
+    await program.methods.closePosition()
+      .accountsStrict({
+        owner: user.publicKey,
+        pool,
+        position,
+        lowerTick: lower_tick,
+        upperTick: upper_tick,
+        lowerTickFunder: user.publicKey,
+        upperTickFunder: user.publicKey,
+      }).signers([user.payer]).rpc({ commitment: "confirmed" });
+    assert.equal(await provider.connection.getAccountInfo(position, 'confirmed'), null);
+    assert.equal(await provider.connection.getAccountInfo(lower_tick, 'confirmed'), null);
+    assert.equal(await provider.connection.getAccountInfo(upper_tick, 'confirmed'), null);
+  });
+  it("flash loan is repaid with a fee in the same transaction", async () => {
+    const accounts = {
+      borrower: user.publicKey,
+      config: config_pda,
+      mintX: mint_x,
+      mintY: mint_y,
+      vaultX: vault_ata_x,
+      vaultY: vault_ata_y,
+      borrowerAtaX: user_ata_x,
+      borrowerAtaY: user_ata_y,
+      instructionSysvar: anchor.web3.SYSVAR_INSTRUCTIONS_PUBKEY,
+      tokenProgramX: anchor.utils.token.TOKEN_PROGRAM_ID,
+      tokenProgramY: anchor.utils.token.TOKEN_PROGRAM_ID,
+    };
+    const amount = new anchor.BN(1_000);
+    const vault_x_before = await getAccount(provider.connection, vault_ata_x, 'confirmed');
+
+    const borrow = await program.methods.flashBorrow(true, amount).accountsStrict(accounts).instruction();
+    const repay = await program.methods.flashRepay(true, amount).accountsStrict(accounts).instruction();
+    await provider.sendAndConfirm(new anchor.web3.Transaction().add(borrow, repay), [], { commitment: "confirmed" });
+
+    const vault_x_after = await getAccount(provider.connection, vault_ata_x, 'confirmed');
+    const configAccount = await program.account.config.fetch(config_pda);
+    assert.equal(vault_x_after.amount > vault_x_before.amount, true);
+    assert.equal(configAccount.flashLoan, null);
+
+    try {
+      await program.methods.flashBorrow(true, amount).accountsStrict(accounts)
+        .signers([user.payer]).rpc({ commitment: "confirmed" });
+      assert.fail("borrow without a repay should be rejected");
+    } catch (err) {
+      assert.equal(err.error.errorCode.code, "FlashLoanNotRepaid");
+    }
+  });
+  it("observe is rejected while a flash loan is outstanding", async () => {
+    const accounts = {
+      borrower: user.publicKey,
+      config: config_pda,
+      mintX: mint_x,
+      mintY: mint_y,
+      vaultX: vault_ata_x,
+      vaultY: vault_ata_y,
+      borrowerAtaX: user_ata_x,
+      borrowerAtaY: user_ata_y,
+      instructionSysvar: anchor.web3.SYSVAR_INSTRUCTIONS_PUBKEY,
+      tokenProgramX: anchor.utils.token.TOKEN_PROGRAM_ID,
+      tokenProgramY: anchor.utils.token.TOKEN_PROGRAM_ID,
+    };
+    const amount = new anchor.BN(1_000);
+    const borrow = await program.methods.flashBorrow(true, amount).accountsStrict(accounts).instruction();
+    const observe = await program.methods.observe().accountsStrict({ config: config_pda }).instruction();
+    const repay = await program.methods.flashRepay(true, amount).accountsStrict(accounts).instruction();
+    try {
+      await provider.sendAndConfirm(new anchor.web3.Transaction().add(borrow, observe, repay), [], { commitment: "confirmed" });
+      assert.fail("observe during a flash loan should be rejected");
+    } catch (err) {
+      assert.include(err.logs.join("\n"), "FlashLoanActive");
+    }
+  });
+  it("staked lp earns farm rewards that can be harvested", async () => {
+    const reward_mint = await createMint(provider.connection, user.payer, user.publicKey, null, 6);
+    const user_ata_reward = (await getOrCreateAssociatedTokenAccount(
+      provider.connection,
+      user.payer,
+      reward_mint,
+      user.publicKey
+    )).address;
+    await mintTo(provider.connection, user.payer, reward_mint, user_ata_reward, user.payer, 1_000_000);
+    const farm = PublicKey.findProgramAddressSync(
+      [Buffer.from("farm"), config_pda.toBuffer()],
+      program.programId
+    )[0];
+    const stake = PublicKey.findProgramAddressSync(
+      [Buffer.from("stake"), farm.toBuffer(), user.publicKey.toBuffer()],
+      program.programId
+    )[0];
+    const lp_vault = getAssociatedTokenAddressSync(mint_lp, farm, true);
+    const reward_vault = getAssociatedTokenAddressSync(reward_mint, farm, true);
+
+    await program.methods.initializeFarm(new anchor.BN(100))
+      .accountsStrict({
+        authority: user.publicKey,
+        config: config_pda,
+        mintLp: mint_lp,
+        rewardMint: reward_mint,
+        farm,
+        lpVault: lp_vault,
+        rewardVault: reward_vault,
+        lpTokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+        rewardTokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+        associatedTokenProgram: anchor.utils.token.ASSOCIATED_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      }).signers([user.payer]).rpc({ commitment: "confirmed" });
+    await program.methods.fundFarm(new anchor.BN(100_000))
+      .accountsStrict({
+        authority: user.publicKey,
+        config: config_pda,
+        farm,
+        rewardMint: reward_mint,
+        rewardVault: reward_vault,
+        authorityAtaReward: user_ata_reward,
+        tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+      }).signers([user.payer]).rpc({ commitment: "confirmed" });
+
+    const stakeAccounts = {
+      owner: user.publicKey,
+      farm,
+      mintLp: mint_lp,
+      stake,
+      ownerAtaLp: user_ata_lp,
+      lpVault: lp_vault,
+      tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+      systemProgram: SystemProgram.programId,
+    };
+    const staked = new anchor.BN(1_000);
+    await program.methods.stakeLp(staked)
+      .accountsStrict(stakeAccounts).signers([user.payer]).rpc({ commitment: "confirmed" });
+    let farmAccount = await program.account.farm.fetch(farm);
+    assert.equal(farmAccount.totalStaked.toNumber(), staked.toNumber());
+
+    await new Promise((resolve) => setTimeout(resolve, 2_000));
+    const reward_before = await getAccount(provider.connection, user_ata_reward, 'confirmed');
+    await program.methods.harvest()
+      .accountsStrict({
+        owner: user.publicKey,
+        farm,
+        stake,
+        rewardMint: reward_mint,
+        rewardVault: reward_vault,
+        ownerAtaReward: user_ata_reward,
+        tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+        associatedTokenProgram: anchor.utils.token.ASSOCIATED_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      }).signers([user.payer]).rpc({ commitment: "confirmed" });
+    const reward_after = await getAccount(provider.connection, user_ata_reward, 'confirmed');
+    assert.equal(reward_after.amount > reward_before.amount, true);
+
+    try {
+      await program.methods.unstakeLp(staked.addn(1))
+        .accountsStrict(stakeAccounts).signers([user.payer]).rpc({ commitment: "confirmed" });
+      assert.fail("unstaking more than staked should be rejected");
+    } catch (err) {
+      assert.equal(err.error.errorCode.code, "InsufficientStake");
+    }
+    await program.methods.unstakeLp(staked)
+      .accountsStrict(stakeAccounts).signers([user.payer]).rpc({ commitment: "confirmed" });
+    farmAccount = await program.account.farm.fetch(farm);
+    assert.equal(farmAccount.totalStaked.toNumber(), 0);
+
+    // Unemitted rewards would be stranded without an authority.
+    try {
+      await program.methods.renounceAuthority()
+        .accountsStrict({ authority: user.publicKey, config: config_pda, farm })
+        .signers([user.payer]).rpc({ commitment: "confirmed" });
+      assert.fail("renouncing with unemitted farm rewards should be rejected");
+    } catch (err) {
+      assert.equal(err.error.errorCode.code, "FarmNotDrained");
+    }
+    await program.methods.withdrawUnemitted(farmAccount.rewardsRemaining)
+      .accountsStrict({
+        authority: user.publicKey,
+        config: config_pda,
+        farm,
+        rewardMint: reward_mint,
+        rewardVault: reward_vault,
+        authorityAtaReward: user_ata_reward,
+        tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+      }).signers([user.payer]).rpc({ commitment: "confirmed" });
+    farmAccount = await program.account.farm.fetch(farm);
+    assert.equal(farmAccount.rewardsRemaining.toNumber(), 0);
+  });
+  it("renounce authority", async () => {
+    const farm = PublicKey.findProgramAddressSync(
+      [Buffer.from("farm"), config_pda.toBuffer()],
+      program.programId
+    )[0];
+    await program.methods.renounceAuthority()
+      .accountsStrict({ authority: user.publicKey, config: config_pda, farm })
+      .signers([user.payer]).rpc({ commitment: "confirmed" });
+    const configAccount = await program.account.config.fetch(config_pda);
+    assert.equal(configAccount.authority, null);
+  });
 });
 
 
 async function airdrop(provider: anchor.AnchorProvider, publicKey: anchor.web3.PublicKey, amount: number) {
   const airdropSignature = await provider.connection.requestAirdrop(publicKey, amount);
   await provider.connection.confirmTransaction(airdropSignature, "confirmed");
-}
\ No newline at end of file
+}
+
+function sortMints(a: PublicKey, b: PublicKey): [PublicKey, PublicKey] {
+  return Buffer.compare(a.toBuffer(), b.toBuffer()) < 0 ? [a, b] : [b, a];
+}
+
+// Registry, directory and the list page the next pool will be appended to.
+async function registryAccounts(program: Program<Amm>, mint_x: PublicKey, mint_y: PublicKey) {
+  const registry = PublicKey.findProgramAddressSync(
+    [Buffer.from("registry"), mint_x.toBuffer(), mint_y.toBuffer()],
+    program.programId
+  )[0];
+  const poolDirectory = PublicKey.findProgramAddressSync(
+    [Buffer.from("pool_directory")],
+    program.programId
+  )[0];
+  const directory = await program.account.poolDirectory.fetchNullable(poolDirectory);
+  const page = directory ? directory.poolCount.divn(32) : new anchor.BN(0);
+  const poolListPage = PublicKey.findProgramAddressSync(
+    [Buffer.from("pool_list"), page.toArrayLike(Buffer, "le", 8)],
+    program.programId
+  )[0];
+  return { registry, poolDirectory, poolListPage };
+}
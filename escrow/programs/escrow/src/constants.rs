@@ -2,3 +2,11 @@ use anchor_lang::prelude::*;
 
 #[constant]
 pub const SEED: &str = "anchor";
+
+/// Most mints on either side of a basket offer.
+#[constant]
+pub const MAX_BASKET_LEGS: usize = 4;
+
+/// Remaining accounts each basket leg takes, the leg's token program last.
+#[constant]
+pub const ACCOUNTS_PER_LEG: usize = 4;
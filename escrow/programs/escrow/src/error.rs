@@ -4,4 +4,26 @@ use anchor_lang::prelude::*;
 pub enum EscrowError {
     #[msg("Invalid Amount")]
     InvalidAmount,
+    #[msg("Amount exceeds what is left of the offer")]
+    ExceedsRemaining,
+    #[msg("Offer is not fully filled")]
+    NotFilled,
+    #[msg("Offer has expired")]
+    Expired,
+    #[msg("Offer has not expired yet")]
+    NotExpired,
+    #[msg("Expiry must be in the future")]
+    InvalidExpiry,
+    #[msg("Offer is reserved for another taker")]
+    TakerNotAllowed,
+    #[msg("Paid fills are still waiting to be withdrawn")]
+    PendingWithdrawal,
+    #[msg("Basket needs 1 to MAX_BASKET_LEGS legs with distinct mints")]
+    InvalidBasket,
+    #[msg("Remaining accounts don't match the basket legs")]
+    InvalidBasketAccount,
+    #[msg("Offer changed since the expected version")]
+    VersionMismatch,
+    #[msg("Arithmetic overflow")]
+    Overflow,
 }
@@ -0,0 +1,10 @@
+use anchor_lang::prelude::*;
+
+#[event]
+pub struct OfferUpdated {
+    pub escrow: Pubkey,
+    pub version: u64,
+    pub deposit_remaining: u64,
+    pub receive_remaining: u64,
+    pub expires_at: Option<i64>,
+}
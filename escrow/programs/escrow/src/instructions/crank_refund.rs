@@ -0,0 +1,84 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::{close_account, transfer_checked, CloseAccount, Mint, TokenAccount, TokenInterface, TransferChecked};
+
+use crate::error::EscrowError;
+use crate::state::Escrow;
+
+#[derive(Accounts)]
+pub struct CrankRefund<'info> {
+    #[account(mut)]
+    pub cranker: Signer<'info>,
+    #[account(mut)]
+    pub maker: SystemAccount<'info>,
+    #[account(
+        mint::token_program = token_program
+    )]
+    pub mint_a: InterfaceAccount<'info, Mint>,
+    /// Must already exist: creating it would cost the cranker more rent than
+    /// the vault's rent pays back.
+    #[account(
+        mut,
+        associated_token::mint = mint_a,
+        associated_token::authority = maker,
+        associated_token::token_program = token_program
+    )]
+    pub maker_ata_a: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        has_one = mint_a,
+        has_one = maker,
+        seeds = [b"escrow", maker.key().as_ref(), escrow.seed.to_le_bytes().as_ref()],
+        bump = escrow.bump
+    )]
+    pub escrow: Account<'info, Escrow>,
+    #[account(
+        mut,
+        associated_token::mint = mint_a,
+        associated_token::authority = escrow,
+        associated_token::token_program = token_program
+    )]
+    pub vault: InterfaceAccount<'info, TokenAccount>,
+
+    pub token_program: Interface<'info, TokenInterface>
+}
+
+impl<'info> CrankRefund<'info> {
+    /// Anyone can return an expired offer to its maker, as `do_refund` would.
+    /// Once it closes, the vault's rent goes to the cranker as the reward and
+    /// the escrow's rent goes back to the maker.
+    pub fn crank_refund(&mut self) -> Result<()> {
+        require!(self.escrow.is_expired(Clock::get()?.unix_timestamp), EscrowError::NotExpired);
+        let refund = self.escrow.refundable(self.vault.amount);
+        self.escrow.deposit_remaining = 0;
+
+        let escrow_seed = self.escrow.seed.to_le_bytes();
+        let maker_key = self.maker.key();
+        let signer_seed: &[&[&[u8]]] = &[&[
+            b"escrow",
+            maker_key.as_ref(),
+            escrow_seed.as_ref(),
+            &[self.escrow.bump]
+        ]];
+
+        let transfer_accounts = TransferChecked {
+            mint: self.mint_a.to_account_info(),
+            from: self.vault.to_account_info(),
+            to: self.maker_ata_a.to_account_info(),
+            authority: self.escrow.to_account_info()
+        };
+        let cpi_ctx = CpiContext::new_with_signer(self.token_program.to_account_info(), transfer_accounts, signer_seed);
+        transfer_checked(cpi_ctx, refund, self.mint_a.decimals)?;
+        if self.escrow.pending_withdrawal > 0 {
+            return Ok(());
+        }
+
+        let close_accounts = CloseAccount {
+            account: self.vault.to_account_info(),
+            destination: self.cranker.to_account_info(),
+            authority: self.escrow.to_account_info()
+        };
+        let close_cpi_ctx = CpiContext::new_with_signer(self.token_program.to_account_info(), close_accounts, signer_seed);
+        close_account(close_cpi_ctx)?;
+        self.escrow.close(self.maker.to_account_info())
+    }
+}
@@ -0,0 +1,82 @@
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    associated_token::get_associated_token_address_with_program_id,
+    token_interface::{close_account, transfer_checked, CloseAccount, Mint, TokenAccount, TransferChecked},
+};
+
+use crate::constants::ACCOUNTS_PER_LEG;
+use crate::error::EscrowError;
+use crate::state::BasketEscrow;
+
+#[derive(Accounts)]
+pub struct CrankRefundBasket<'info> {
+    #[account(mut)]
+    pub cranker: Signer<'info>,
+    #[account(mut)]
+    pub maker: SystemAccount<'info>,
+    #[account(
+        mut,
+        close = maker,
+        has_one = maker,
+        seeds = [b"basket", maker.key().as_ref(), basket.seed.to_le_bytes().as_ref()],
+        bump = basket.bump
+    )]
+    pub basket: Account<'info, BasketEscrow>
+}
+
+impl<'info> CrankRefundBasket<'info> {
+    /// Anyone can return an expired basket to its maker. `remaining_accounts`
+    /// holds `[mint, vault, maker_ata, token_program]` for each offered leg;
+    /// the maker's ATAs must already exist. The vaults' rent goes to the
+    /// cranker as the reward; the basket's rent goes back to the maker.
+    pub fn crank_refund_basket(&mut self, remaining_accounts: &'info [AccountInfo<'info>]) -> Result<()> {
+        require!(self.basket.is_expired(Clock::get()?.unix_timestamp), EscrowError::NotExpired);
+        let offer = self.basket.offer.clone();
+        require!(remaining_accounts.len() == offer.len() * ACCOUNTS_PER_LEG, EscrowError::InvalidBasketAccount);
+
+        let seed = self.basket.seed.to_le_bytes();
+        let maker_key = self.maker.key();
+        let signer_seeds: &[&[&[u8]]] = &[&[
+            b"basket",
+            maker_key.as_ref(),
+            seed.as_ref(),
+            &[self.basket.bump],
+        ]];
+        for (leg, accounts) in offer.iter().zip(remaining_accounts.chunks_exact(ACCOUNTS_PER_LEG)) {
+            let [mint, vault, maker_ata, token_program] = accounts else {
+                return err!(EscrowError::InvalidBasketAccount);
+            };
+            let token_program = leg.token_program(mint, token_program)?;
+            require_keys_eq!(
+                vault.key(),
+                get_associated_token_address_with_program_id(&self.basket.key(), &leg.mint, &token_program.key()),
+                EscrowError::InvalidBasketAccount
+            );
+            require_keys_eq!(
+                maker_ata.key(),
+                get_associated_token_address_with_program_id(&maker_key, &leg.mint, &token_program.key()),
+                EscrowError::InvalidBasketAccount
+            );
+            let vault_amount = InterfaceAccount::<TokenAccount>::try_from(vault)?.amount;
+            let decimals = InterfaceAccount::<Mint>::try_from(mint)?.decimals;
+
+            let transfer_accounts = TransferChecked {
+                from: vault.clone(),
+                mint: mint.clone(),
+                to: maker_ata.clone(),
+                authority: self.basket.to_account_info()
+            };
+            let cpi_ctx = CpiContext::new_with_signer(token_program.to_account_info(), transfer_accounts, signer_seeds);
+            transfer_checked(cpi_ctx, vault_amount, decimals)?;
+
+            let close_accounts = CloseAccount {
+                account: vault.clone(),
+                destination: self.cranker.to_account_info(),
+                authority: self.basket.to_account_info()
+            };
+            let close_cpi_ctx = CpiContext::new_with_signer(token_program.to_account_info(), close_accounts, signer_seeds);
+            close_account(close_cpi_ctx)?;
+        }
+        Ok(())
+    }
+}
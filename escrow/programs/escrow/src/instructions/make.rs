@@ -2,6 +2,7 @@ use anchor_lang::prelude::*;
 use anchor_spl::{associated_token::AssociatedToken,
     token_interface::{transfer_checked, Mint, TokenAccount, TokenInterface, TransferChecked}};
 
+use crate::error::EscrowError;
 use crate::state::Escrow;
 
 #[derive(Accounts)]
@@ -43,17 +44,37 @@ pub struct Make<'info> {
 }
 
 impl<'info> Make<'info> {
-    pub fn init_escrow(&mut self, seed: u64, receive: u64, bumps: &MakeBumps) -> Result<()> {
+    pub fn init_escrow(
+        &mut self,
+        seed: u64,
+        deposit: u64,
+        receive: u64,
+        expires_at: Option<i64>,
+        allowed_taker: Option<Pubkey>,
+        bumps: &MakeBumps,
+    ) -> Result<()> {
+        require!(deposit > 0 && receive > 0, EscrowError::InvalidAmount);
+        if let Some(expires_at) = expires_at {
+            require!(expires_at > Clock::get()?.unix_timestamp, EscrowError::InvalidExpiry);
+        }
         self.escrow.set_inner( Escrow {
             seed,
             maker: self.maker.key(),
             mint_a: self.mint_a.key(),
             mint_b: self.mint_b.key(),
             receive,
+            deposit_remaining: deposit,
+            receive_remaining: receive,
+            pending_withdrawal: 0,
+            expires_at,
+            allowed_taker,
+            version: 0,
             bump: bumps.escrow
         });
         Ok(())
     }
+    /// Books what the vault actually received, which is less than `amount`
+    /// for transfer-fee mints, so a refund can always empty the vault.
     pub fn deposit(&mut self, amount: u64) -> Result<()> {
         let transfer_accounts = TransferChecked {
             from: self.maker_ata_a.to_account_info(),
@@ -63,6 +84,11 @@ impl<'info> Make<'info> {
         };
         let cpi_ctx = CpiContext::new(self.token_program.to_account_info(), transfer_accounts);
         transfer_checked(cpi_ctx, amount, self.mint_a.decimals)?;
+        let before = self.vault.amount;
+        self.vault.reload()?;
+        let received = self.vault.amount - before;
+        require!(received > 0, EscrowError::InvalidAmount);
+        self.escrow.deposit_remaining = received;
         Ok(())
     }
 }
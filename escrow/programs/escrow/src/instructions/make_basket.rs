@@ -0,0 +1,95 @@
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    associated_token::{create_idempotent, AssociatedToken, Create},
+    token_interface::{transfer_checked, Mint, TransferChecked},
+};
+
+use crate::constants::ACCOUNTS_PER_LEG;
+use crate::error::EscrowError;
+use crate::state::{BasketEscrow, BasketLeg};
+
+/// Terms of a new basket offer; see `BasketEscrow` for their meaning.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct MakeBasketParams {
+    pub seed: u64,
+    pub offer: Vec<BasketLeg>,
+    pub request: Vec<BasketLeg>,
+    pub expires_at: Option<i64>,
+    pub allowed_taker: Option<Pubkey>
+}
+
+#[derive(Accounts)]
+#[instruction(params: MakeBasketParams)]
+pub struct MakeBasket<'info> {
+    #[account(mut)]
+    pub maker: Signer<'info>,
+    #[account(
+        init,
+        payer = maker,
+        seeds = [b"basket", maker.key().as_ref(), params.seed.to_le_bytes().as_ref()],
+        bump,
+        space = BasketEscrow::INIT_SPACE + BasketEscrow::DISCRIMINATOR.len()
+    )]
+    pub basket: Account<'info, BasketEscrow>,
+
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>
+}
+
+impl<'info> MakeBasket<'info> {
+    /// `remaining_accounts` holds `[mint, maker_ata, vault, token_program]` for
+    /// each offered leg, in order. The vaults are created here as the basket's
+    /// ATAs under each leg's token program.
+    pub fn make_basket(
+        &mut self,
+        remaining_accounts: &'info [AccountInfo<'info>],
+        params: MakeBasketParams,
+        bumps: &MakeBasketBumps,
+    ) -> Result<()> {
+        let MakeBasketParams { seed, offer, request, expires_at, allowed_taker } = params;
+        BasketEscrow::check_legs(&offer)?;
+        BasketEscrow::check_legs(&request)?;
+        if let Some(expires_at) = expires_at {
+            require!(expires_at > Clock::get()?.unix_timestamp, EscrowError::InvalidExpiry);
+        }
+        require!(remaining_accounts.len() == offer.len() * ACCOUNTS_PER_LEG, EscrowError::InvalidBasketAccount);
+
+        for (leg, accounts) in offer.iter().zip(remaining_accounts.chunks_exact(ACCOUNTS_PER_LEG)) {
+            let [mint, maker_ata, vault, token_program] = accounts else {
+                return err!(EscrowError::InvalidBasketAccount);
+            };
+            let token_program = leg.token_program(mint, token_program)?;
+            let decimals = InterfaceAccount::<Mint>::try_from(mint)?.decimals;
+
+            let create_accounts = Create {
+                payer: self.maker.to_account_info(),
+                associated_token: vault.clone(),
+                authority: self.basket.to_account_info(),
+                mint: mint.clone(),
+                system_program: self.system_program.to_account_info(),
+                token_program: token_program.to_account_info()
+            };
+            create_idempotent(CpiContext::new(self.associated_token_program.to_account_info(), create_accounts))?;
+
+            let transfer_accounts = TransferChecked {
+                from: maker_ata.clone(),
+                mint: mint.clone(),
+                to: vault.clone(),
+                authority: self.maker.to_account_info()
+            };
+            let cpi_ctx = CpiContext::new(token_program.to_account_info(), transfer_accounts);
+            transfer_checked(cpi_ctx, leg.amount, decimals)?;
+        }
+
+        self.basket.set_inner(BasketEscrow {
+            seed,
+            maker: self.maker.key(),
+            offer,
+            request,
+            expires_at,
+            allowed_taker,
+            bump: bumps.basket
+        });
+        Ok(())
+    }
+}
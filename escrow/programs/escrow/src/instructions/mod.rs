@@ -1,11 +1,27 @@
+pub mod crank_refund;
+pub mod crank_refund_basket;
 pub mod make;
+pub mod make_basket;
 pub mod refund;
+pub mod refund_basket;
+pub mod take;
+pub mod take_basket;
 pub mod take_deposit;
 pub mod take_withdraw;
 pub mod take_close;
+pub mod top_up;
+pub mod update_offer;
 
+pub use crank_refund::*;
+pub use crank_refund_basket::*;
 pub use make::*;
+pub use make_basket::*;
 pub use refund::*;
+pub use refund_basket::*;
+pub use take::*;
+pub use take_basket::*;
 pub use take_deposit::*;
 pub use take_withdraw::*;
-pub use take_close::*;
\ No newline at end of file
+pub use take_close::*;
+pub use top_up::*;
+pub use update_offer::*;
\ No newline at end of file
@@ -23,7 +23,6 @@ pub struct Refund<'info> {
     pub maker_ata_a: InterfaceAccount<'info, TokenAccount>,
     #[account(
         mut,
-        close = maker,
         has_one = mint_a,
         has_one = maker,
         seeds = [b"escrow", maker.key().as_ref(), &escrow.seed.to_le_bytes().as_ref()],
@@ -44,13 +43,19 @@ pub struct Refund<'info> {
 }
 
 impl<'info> Refund <'info> {
+    /// Returns what no receipt has paid for. The vault and the escrow close
+    /// once no receipt is left to redeem; until then the offer stays open,
+    /// with nothing left to take.
     pub fn refund_and_close_vault(&mut self)-> Result<()> {
+        let refund = self.escrow.refundable(self.vault.amount);
+        self.escrow.deposit_remaining = 0;
+
         let escrow_seed = self.escrow.seed.to_le_bytes();
         let maker_key = self.maker.key();
         let signer_seed: &[&[&[u8]]] = &[&[
             b"escrow",
             maker_key.as_ref(), 
-            &escrow_seed.as_ref(), 
+            escrow_seed.as_ref(), 
             &[self.escrow.bump]
         ]];
 
@@ -61,7 +66,10 @@ impl<'info> Refund <'info> {
             authority: self.escrow.to_account_info()
         };
         let cpi_ctx = CpiContext::new_with_signer(self.token_program.to_account_info(), transfer_accounts, signer_seed);
-        transfer_checked(cpi_ctx, self.vault.amount, self.mint_a.decimals)?;
+        transfer_checked(cpi_ctx, refund, self.mint_a.decimals)?;
+        if self.escrow.pending_withdrawal > 0 {
+            return Ok(());
+        }
 
         let close_accounts = CloseAccount {
             account: self.vault.to_account_info(),
@@ -71,7 +79,8 @@ impl<'info> Refund <'info> {
 
         let close_cpi_ctx = CpiContext::new_with_signer(self.token_program.to_account_info(), close_accounts, signer_seed);
     
-        close_account(close_cpi_ctx)
+        close_account(close_cpi_ctx)?;
+        self.escrow.close(self.maker.to_account_info())
     }
 }
 
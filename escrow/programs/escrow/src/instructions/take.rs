@@ -1,16 +1,18 @@
 use anchor_lang::prelude::*;
 use anchor_spl::{
     associated_token::AssociatedToken,
-    token::{
-        self,
+    token_interface::{
+        close_account,
+        transfer_checked,
         CloseAccount,
         Mint,
-        Token,
         TokenAccount,
+        TokenInterface,
         TransferChecked,
     },
 };
 
+use crate::error::EscrowError;
 use crate::state::Escrow;
 
 #[derive(Accounts)]
@@ -21,35 +23,39 @@ pub struct Take<'info> {
     #[account(mut)]
     pub maker: SystemAccount<'info>,
 
-    pub mint_a: Account<'info, Mint>,
-    pub mint_b: Account<'info, Mint>,
+    pub mint_a: InterfaceAccount<'info, Mint>,
+    pub mint_b: InterfaceAccount<'info, Mint>,
 
     #[account(
         mut,
         associated_token::mint = mint_a,
-        associated_token::authority = taker
+        associated_token::authority = taker,
+        associated_token::token_program = token_program
     )]
-    pub taker_ata_a: Account<'info, TokenAccount>,
+    pub taker_ata_a: InterfaceAccount<'info, TokenAccount>,
 
     #[account(
         mut,
         associated_token::mint = mint_b,
-        associated_token::authority = taker
+        associated_token::authority = taker,
+        associated_token::token_program = token_program
     )]
-    pub taker_ata_b: Account<'info, TokenAccount>,
+    pub taker_ata_b: InterfaceAccount<'info, TokenAccount>,
 
     #[account(
         mut,
         associated_token::mint = mint_b,
-        associated_token::authority = maker
+        associated_token::authority = maker,
+        associated_token::token_program = token_program
     )]
-    pub maker_ata_b: Account<'info, TokenAccount>,
+    pub maker_ata_b: InterfaceAccount<'info, TokenAccount>,
 
     #[account(
         mut,
         has_one = mint_a,
         has_one = mint_b,
         has_one = maker,
+        constraint = escrow.can_take(&taker.key()) @ EscrowError::TakerNotAllowed,
         seeds = [b"escrow", escrow.maker.as_ref(), escrow.seed.to_le_bytes().as_ref()],
         bump = escrow.bump
     )]
@@ -58,17 +64,35 @@ pub struct Take<'info> {
     #[account(
         mut,
         associated_token::mint = mint_a,
-        associated_token::authority = escrow
+        associated_token::authority = escrow,
+        associated_token::token_program = token_program
     )]
-    pub vault: Account<'info, TokenAccount>,
+    pub vault: InterfaceAccount<'info, TokenAccount>,
 
-    pub token_program: Program<'info, Token>,
+    pub token_program: Interface<'info, TokenInterface>,
     pub system_program: Program<'info, System>,
     pub associated_token_program: Program<'info, AssociatedToken>,
 }
 
 impl<'info> Take<'info> {
-    pub fn deposit(&mut self) -> Result<()> {
+    /// Pays for and receives `amount` of the vault in one instruction. The
+    /// fill that empties the offer also closes the vault and the escrow.
+    pub fn take(&mut self, amount: u64, expected_version: u64) -> Result<()> {
+        self.escrow.check_version(expected_version)?;
+        self.escrow.check_not_expired()?;
+        let owed = self.escrow.fill(amount)?;
+        self.deposit(owed)?;
+        self.withdraw(amount)?;
+
+        self.vault.reload()?;
+        if self.escrow.deposit_remaining == 0 && self.vault.amount == 0 {
+            self.close()?;
+            self.escrow.close(self.maker.to_account_info())?;
+        }
+        Ok(())
+    }
+
+    pub fn deposit(&mut self, amount: u64) -> Result<()> {
         let cpi_accounts = TransferChecked {
             from: self.taker_ata_b.to_account_info(),
             to: self.maker_ata_b.to_account_info(),
@@ -78,12 +102,12 @@ impl<'info> Take<'info> {
 
         let ctx = CpiContext::new(self.token_program.to_account_info(), cpi_accounts);
 
-        token::transfer_checked(ctx, self.escrow.receive, self.mint_b.decimals)?;
+        transfer_checked(ctx, amount, self.mint_b.decimals)?;
 
         Ok(())
     }
 
-    pub fn withdraw(&mut self) -> Result<()> {
+    pub fn withdraw(&mut self, amount: u64) -> Result<()> {
         let seed = self.escrow.seed.to_le_bytes();
         let signer_seeds: &[&[&[u8]]] = &[&[
             b"escrow",
@@ -105,7 +129,7 @@ impl<'info> Take<'info> {
             signer_seeds,
         );
 
-        token::transfer_checked(ctx, self.vault.amount, self.mint_a.decimals)?;
+        transfer_checked(ctx, amount, self.mint_a.decimals)?;
 
         Ok(())
     }
@@ -131,6 +155,6 @@ impl<'info> Take<'info> {
             signer_seeds,
         );
 
-        token::close_account(ctx)
+        close_account(ctx)
     }
 }
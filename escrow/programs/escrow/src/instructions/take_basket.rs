@@ -0,0 +1,100 @@
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    associated_token::get_associated_token_address_with_program_id,
+    token_interface::{close_account, transfer_checked, CloseAccount, Mint, TokenAccount, TransferChecked},
+};
+
+use crate::constants::ACCOUNTS_PER_LEG;
+use crate::error::EscrowError;
+use crate::state::BasketEscrow;
+
+#[derive(Accounts)]
+pub struct TakeBasket<'info> {
+    #[account(mut)]
+    pub taker: Signer<'info>,
+    #[account(mut)]
+    pub maker: SystemAccount<'info>,
+    #[account(
+        mut,
+        close = maker,
+        has_one = maker,
+        constraint = basket.can_take(&taker.key()) @ EscrowError::TakerNotAllowed,
+        seeds = [b"basket", basket.maker.as_ref(), basket.seed.to_le_bytes().as_ref()],
+        bump = basket.bump
+    )]
+    pub basket: Account<'info, BasketEscrow>
+}
+
+impl<'info> TakeBasket<'info> {
+    /// Pays every requested leg and receives every offered one in a single
+    /// instruction. `remaining_accounts` holds `[mint, taker_ata, maker_ata,
+    /// token_program]` for each requested leg, then `[mint, vault, taker_ata,
+    /// token_program]` for each offered leg.
+    pub fn take_basket(&mut self, remaining_accounts: &'info [AccountInfo<'info>]) -> Result<()> {
+        self.basket.check_not_expired()?;
+        let (request, offer) = (self.basket.request.clone(), self.basket.offer.clone());
+        require!(
+            remaining_accounts.len() == (request.len() + offer.len()) * ACCOUNTS_PER_LEG,
+            EscrowError::InvalidBasketAccount
+        );
+        let (requested_accounts, offered_accounts) = remaining_accounts.split_at(request.len() * ACCOUNTS_PER_LEG);
+
+        for (leg, accounts) in request.iter().zip(requested_accounts.chunks_exact(ACCOUNTS_PER_LEG)) {
+            let [mint, taker_ata, maker_ata, token_program] = accounts else {
+                return err!(EscrowError::InvalidBasketAccount);
+            };
+            let token_program = leg.token_program(mint, token_program)?;
+            let maker_account = InterfaceAccount::<TokenAccount>::try_from(maker_ata)?;
+            require_keys_eq!(maker_account.owner, self.maker.key(), EscrowError::InvalidBasketAccount);
+            let decimals = InterfaceAccount::<Mint>::try_from(mint)?.decimals;
+
+            let transfer_accounts = TransferChecked {
+                from: taker_ata.clone(),
+                mint: mint.clone(),
+                to: maker_ata.clone(),
+                authority: self.taker.to_account_info()
+            };
+            let cpi_ctx = CpiContext::new(token_program.to_account_info(), transfer_accounts);
+            transfer_checked(cpi_ctx, leg.amount, decimals)?;
+        }
+
+        let seed = self.basket.seed.to_le_bytes();
+        let signer_seeds: &[&[&[u8]]] = &[&[
+            b"basket",
+            self.basket.maker.as_ref(),
+            seed.as_ref(),
+            &[self.basket.bump],
+        ]];
+        for (leg, accounts) in offer.iter().zip(offered_accounts.chunks_exact(ACCOUNTS_PER_LEG)) {
+            let [mint, vault, taker_ata, token_program] = accounts else {
+                return err!(EscrowError::InvalidBasketAccount);
+            };
+            let token_program = leg.token_program(mint, token_program)?;
+            require_keys_eq!(
+                vault.key(),
+                get_associated_token_address_with_program_id(&self.basket.key(), &leg.mint, &token_program.key()),
+                EscrowError::InvalidBasketAccount
+            );
+            let vault_amount = InterfaceAccount::<TokenAccount>::try_from(vault)?.amount;
+            let decimals = InterfaceAccount::<Mint>::try_from(mint)?.decimals;
+
+            let transfer_accounts = TransferChecked {
+                from: vault.clone(),
+                mint: mint.clone(),
+                to: taker_ata.clone(),
+                authority: self.basket.to_account_info()
+            };
+            let cpi_ctx = CpiContext::new_with_signer(token_program.to_account_info(), transfer_accounts, signer_seeds);
+            transfer_checked(cpi_ctx, vault_amount, decimals)?;
+
+            let close_accounts = CloseAccount {
+                account: vault.clone(),
+                destination: self.maker.to_account_info(),
+                authority: self.basket.to_account_info()
+            };
+            let close_cpi_ctx = CpiContext::new_with_signer(token_program.to_account_info(), close_accounts, signer_seeds);
+            close_account(close_cpi_ctx)?;
+        }
+        Ok(())
+    }
+}
@@ -1,14 +1,29 @@
-use anchor_spl::token::{self, CloseAccount, Token, TokenAccount, Mint};
+use anchor_spl::token_interface::{close_account, transfer_checked, CloseAccount, Mint, TokenAccount, TokenInterface, TransferChecked};
 use anchor_lang::prelude::*;
+use crate::error::EscrowError;
 use crate::state::*;
 
 #[derive(Accounts)]
 pub struct TakeClose<'info> {
+    pub taker: Signer<'info>,
+
     #[account(mut)]
     pub maker: SystemAccount<'info>,
 
+    /// Receives anything sent to the vault beyond the offer.
+    #[account(
+        mut,
+        associated_token::mint = mint_a,
+        associated_token::authority = maker,
+        associated_token::token_program = token_program
+    )]
+    pub maker_ata_a: InterfaceAccount<'info, TokenAccount>,
+
     #[account(
         mut,
+        has_one = maker,
+        has_one = mint_a,
+        constraint = escrow.can_take(&taker.key()) @ EscrowError::TakerNotAllowed,
         seeds = [b"escrow", escrow.maker.as_ref(), escrow.seed.to_le_bytes().as_ref()],
         bump = escrow.bump,
         close = maker
@@ -18,16 +33,21 @@ pub struct TakeClose<'info> {
     #[account(
         mut,
         associated_token::authority = escrow,
-        associated_token::mint = mint_a
+        associated_token::mint = mint_a,
+        associated_token::token_program = token_program
     )]
-    pub vault: Account<'info, TokenAccount>,
+    pub vault: InterfaceAccount<'info, TokenAccount>,
 
-    pub mint_a: Account<'info, Mint>,
-    pub token_program: Program<'info, Token>,
+    pub mint_a: InterfaceAccount<'info, Mint>,
+    pub token_program: Interface<'info, TokenInterface>,
 }
 impl<'info> TakeClose<'info> {
+    /// Only once every unit of the offer has been taken; a partly filled
+    /// offer stays open until it is filled or refunded.
     pub fn take_close(&mut self) -> Result<()> {
-    
+        require!(self.escrow.deposit_remaining == 0, EscrowError::NotFilled);
+        require!(self.escrow.pending_withdrawal == 0, EscrowError::PendingWithdrawal);
+
         let seed = self.escrow.seed.to_le_bytes();
         let signer_seeds: &[&[&[u8]]] = &[&[
             b"escrow",
@@ -35,6 +55,21 @@ impl<'info> TakeClose<'info> {
             seed.as_ref(),
             &[self.escrow.bump],
         ]];
+
+        if self.vault.amount > 0 {
+            let transfer_accounts = TransferChecked {
+                from: self.vault.to_account_info(),
+                to: self.maker_ata_a.to_account_info(),
+                mint: self.mint_a.to_account_info(),
+                authority: self.escrow.to_account_info(),
+            };
+            let transfer_ctx = CpiContext::new_with_signer(
+                self.token_program.to_account_info(),
+                transfer_accounts,
+                signer_seeds,
+            );
+            transfer_checked(transfer_ctx, self.vault.amount, self.mint_a.decimals)?;
+        }
     
         let cpi_accounts = CloseAccount {
             account: self.vault.to_account_info(),
@@ -48,6 +83,6 @@ impl<'info> TakeClose<'info> {
             signer_seeds,
         );
     
-        token::close_account(cpi_ctx)
+        close_account(cpi_ctx)
     }
 }
@@ -1,7 +1,8 @@
 use anchor_lang::prelude::*;
-use anchor_spl::token::{self, Mint, Token, TokenAccount, TransferChecked};
+use anchor_spl::token_interface::{transfer_checked, Mint, TokenAccount, TokenInterface, TransferChecked};
 
-use crate::state::Escrow;
+use crate::error::EscrowError;
+use crate::state::{Escrow, TakeReceipt};
 
 #[derive(Accounts)]
 pub struct TakeDeposit<'info> {
@@ -11,35 +12,66 @@ pub struct TakeDeposit<'info> {
     #[account(mut)]
     pub maker: SystemAccount<'info>,
 
-    pub mint_b: Account<'info, Mint>,
+    pub mint_b: InterfaceAccount<'info, Mint>,
 
     #[account(
         mut,
         associated_token::mint = mint_b,
-        associated_token::authority = taker
+        associated_token::authority = taker,
+        associated_token::token_program = token_program
     )]
-    pub taker_ata_b: Account<'info, TokenAccount>,
+    pub taker_ata_b: InterfaceAccount<'info, TokenAccount>,
 
     #[account(
         mut,
         associated_token::mint = mint_b,
-        associated_token::authority = maker
+        associated_token::authority = maker,
+        associated_token::token_program = token_program
     )]
-    pub maker_ata_b: Account<'info, TokenAccount>,
+    pub maker_ata_b: InterfaceAccount<'info, TokenAccount>,
 
     #[account(
+        mut,
         has_one = mint_b,
         has_one = maker,
+        constraint = escrow.can_take(&taker.key()) @ EscrowError::TakerNotAllowed,
         seeds = [b"escrow", escrow.maker.as_ref(), escrow.seed.to_le_bytes().as_ref()],
         bump = escrow.bump
     )]
     pub escrow: Account<'info, Escrow>,
 
-    pub token_program: Program<'info, Token>,
+    #[account(
+        init_if_needed,
+        payer = taker,
+        space = TakeReceipt::INIT_SPACE + TakeReceipt::DISCRIMINATOR.len(),
+        seeds = [b"receipt", escrow.key().as_ref(), taker.key().as_ref()],
+        bump
+    )]
+    pub receipt: Account<'info, TakeReceipt>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
 }
 impl <'info> TakeDeposit<'info> {
-    pub fn take_deposit(&mut self) -> Result<()> {
-    
+    /// Pays the maker for `amount` of the vault and books the fill; the
+    /// tokens stay in the vault until `do_take_withdraw` redeems the receipt.
+    /// Fills made before that add up on the taker's one receipt.
+    pub fn take_deposit(&mut self, amount: u64, expected_version: u64, bumps: &TakeDepositBumps) -> Result<()> {
+        self.escrow.check_version(expected_version)?;
+        self.escrow.check_not_expired()?;
+        let owed = self.escrow.fill(amount)?;
+        self.escrow.pending_withdrawal = self.escrow.pending_withdrawal.checked_add(amount).ok_or(EscrowError::Overflow)?;
+        if self.receipt.taker == Pubkey::default() {
+            self.receipt.set_inner(TakeReceipt {
+                escrow: self.escrow.key(),
+                taker: self.taker.key(),
+                amount,
+                bump: bumps.receipt
+            });
+        } else {
+            self.receipt.amount = self.receipt.amount.checked_add(amount).ok_or(EscrowError::Overflow)?;
+        }
+
         let cpi_accounts = TransferChecked {
             from: self.taker_ata_b.to_account_info(),
             to: self.maker_ata_b.to_account_info(),
@@ -50,7 +82,7 @@ impl <'info> TakeDeposit<'info> {
         let cpi_ctx =
             CpiContext::new(self.token_program.to_account_info(), cpi_accounts);
     
-        token::transfer_checked(cpi_ctx, self.escrow.receive,self.mint_b.decimals)?;
+        transfer_checked(cpi_ctx, owed, self.mint_b.decimals)?;
         Ok(())
     }
 }
@@ -1,24 +1,28 @@
 use anchor_lang::prelude::*;
-use anchor_spl::token::{self, Mint, Token, TokenAccount, TransferChecked};
-use crate::state::*; 
+use anchor_spl::token_interface::{transfer_checked, Mint, TokenAccount, TokenInterface, TransferChecked};
+use crate::error::EscrowError;
+use crate::state::*;
 
 #[derive(Accounts)]
 pub struct TakeWithdraw<'info> {
+    /// Not a signer: once paid for, anyone can push the tokens to the taker.
     #[account(mut)]
-    pub taker: Signer<'info>,
+    pub taker: SystemAccount<'info>,
 
-    pub mint_a: Account<'info, Mint>,
+    pub mint_a: InterfaceAccount<'info, Mint>,
 
     #[account(
         mut,
         associated_token::mint = mint_a,
-        associated_token::authority = taker
+        associated_token::authority = taker,
+        associated_token::token_program = token_program
     )]
-    pub taker_ata_a: Account<'info, TokenAccount>,
+    pub taker_ata_a: InterfaceAccount<'info, TokenAccount>,
 
     #[account(
         mut,
         has_one = mint_a,
+        constraint = escrow.can_take(&taker.key()) @ EscrowError::TakerNotAllowed,
         seeds = [b"escrow", escrow.maker.as_ref(), escrow.seed.to_le_bytes().as_ref()],
         bump = escrow.bump
     )]
@@ -27,16 +31,30 @@ pub struct TakeWithdraw<'info> {
     #[account(
         mut,
         associated_token::mint = mint_a,
-        associated_token::authority = escrow
+        associated_token::authority = escrow,
+        associated_token::token_program = token_program
     )]
-    pub vault: Account<'info, TokenAccount>,
+    pub vault: InterfaceAccount<'info, TokenAccount>,
 
-    pub token_program: Program<'info, Token>,
+    #[account(
+        mut,
+        close = taker,
+        has_one = escrow,
+        has_one = taker,
+        seeds = [b"receipt", escrow.key().as_ref(), taker.key().as_ref()],
+        bump = receipt.bump
+    )]
+    pub receipt: Account<'info, TakeReceipt>,
+
+    pub token_program: Interface<'info, TokenInterface>,
 }
 
 impl<'info> TakeWithdraw<'info> {
+    /// Releases what the receipt paid for, even if the offer has since
+    /// expired.
     pub fn take_withdraw(&mut self) -> Result<()> {
-    
+        self.escrow.pending_withdrawal = self.escrow.pending_withdrawal.checked_sub(self.receipt.amount).ok_or(EscrowError::Overflow)?;
+
         let seed = self.escrow.seed.to_le_bytes();
         let signer_seeds: &[&[&[u8]]] = &[&[
             b"escrow",
@@ -58,7 +76,7 @@ impl<'info> TakeWithdraw<'info> {
             signer_seeds,
         );
     
-        token::transfer_checked(cpi_ctx, self.vault.amount, self.mint_a.decimals)?;
+        transfer_checked(cpi_ctx, self.receipt.amount, self.mint_a.decimals)?;
         Ok(())
     }
 }
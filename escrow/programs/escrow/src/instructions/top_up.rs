@@ -0,0 +1,75 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::{transfer_checked, Mint, TokenAccount, TokenInterface, TransferChecked};
+
+use crate::error::EscrowError;
+use crate::events::OfferUpdated;
+use crate::state::Escrow;
+
+#[derive(Accounts)]
+pub struct TopUp<'info> {
+    pub maker: Signer<'info>,
+    #[account(
+        mint::token_program = token_program
+    )]
+    pub mint_a: InterfaceAccount<'info, Mint>,
+    #[account(
+        mut,
+        associated_token::mint = mint_a,
+        associated_token::authority = maker,
+        associated_token::token_program = token_program
+    )]
+    pub maker_ata_a: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        has_one = mint_a,
+        has_one = maker,
+        seeds = [b"escrow", maker.key().as_ref(), escrow.seed.to_le_bytes().as_ref()],
+        bump = escrow.bump
+    )]
+    pub escrow: Account<'info, Escrow>,
+    #[account(
+        mut,
+        associated_token::mint = mint_a,
+        associated_token::authority = escrow,
+        associated_token::token_program = token_program
+    )]
+    pub vault: InterfaceAccount<'info, TokenAccount>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+impl<'info> TopUp<'info> {
+    /// Adds `amount` of `mint_a` to the offer without changing what is asked
+    /// for it; pair with `update_offer` to reprice.
+    pub fn top_up(&mut self, amount: u64) -> Result<()> {
+        require!(amount > 0, EscrowError::InvalidAmount);
+        let transfer_accounts = TransferChecked {
+            from: self.maker_ata_a.to_account_info(),
+            mint: self.mint_a.to_account_info(),
+            to: self.vault.to_account_info(),
+            authority: self.maker.to_account_info()
+        };
+        let cpi_ctx = CpiContext::new(self.token_program.to_account_info(), transfer_accounts);
+        transfer_checked(cpi_ctx, amount, self.mint_a.decimals)?;
+        let before = self.vault.amount;
+        self.vault.reload()?;
+        let received = self.vault.amount - before;
+        require!(received > 0, EscrowError::InvalidAmount);
+
+        let escrow = &mut self.escrow;
+        escrow.deposit_remaining = escrow
+            .deposit_remaining
+            .checked_add(received)
+            .ok_or(EscrowError::Overflow)?;
+        escrow.version = escrow.version.checked_add(1).ok_or(EscrowError::Overflow)?;
+
+        emit!(OfferUpdated {
+            escrow: escrow.key(),
+            version: escrow.version,
+            deposit_remaining: escrow.deposit_remaining,
+            receive_remaining: escrow.receive_remaining,
+            expires_at: escrow.expires_at,
+        });
+        Ok(())
+    }
+}
@@ -0,0 +1,51 @@
+use anchor_lang::prelude::*;
+
+use crate::error::EscrowError;
+use crate::events::OfferUpdated;
+use crate::state::Escrow;
+
+#[derive(Accounts)]
+pub struct UpdateOffer<'info> {
+    pub maker: Signer<'info>,
+    #[account(
+        mut,
+        has_one = maker,
+        seeds = [b"escrow", maker.key().as_ref(), escrow.seed.to_le_bytes().as_ref()],
+        bump = escrow.bump
+    )]
+    pub escrow: Account<'info, Escrow>,
+}
+
+impl<'info> UpdateOffer<'info> {
+    /// Reprices what is left of the offer to `receive` of `mint_b`. An
+    /// `expires_at` can only push an existing deadline later, or set one on
+    /// an offer that had none; `None` leaves it as is.
+    pub fn update_offer(&mut self, receive: u64, expires_at: Option<i64>) -> Result<()> {
+        require!(receive > 0, EscrowError::InvalidAmount);
+        if let Some(expires_at) = expires_at {
+            require!(
+                expires_at > Clock::get()?.unix_timestamp
+                    && self.escrow.expires_at.is_none_or(|current| expires_at >= current),
+                EscrowError::InvalidExpiry
+            );
+            self.escrow.expires_at = Some(expires_at);
+        }
+        let escrow = &mut self.escrow;
+        escrow.receive = escrow
+            .receive
+            .checked_sub(escrow.receive_remaining)
+            .and_then(|received| received.checked_add(receive))
+            .ok_or(EscrowError::Overflow)?;
+        escrow.receive_remaining = receive;
+        escrow.version = escrow.version.checked_add(1).ok_or(EscrowError::Overflow)?;
+
+        emit!(OfferUpdated {
+            escrow: escrow.key(),
+            version: escrow.version,
+            deposit_remaining: escrow.deposit_remaining,
+            receive_remaining: escrow.receive_remaining,
+            expires_at: escrow.expires_at,
+        });
+        Ok(())
+    }
+}
@@ -1,11 +1,14 @@
 use anchor_lang::prelude::*;
 
+pub mod constants;
 pub mod error;
+pub mod events;
 pub mod instructions;
 pub mod state;
 
 pub use instructions::*;
 pub use error::EscrowError;
+pub use state::BasketLeg;
 
 declare_id!("EWAghmkH9oRqUDUWSQv6Fob8LHyvb3YbHhrnm5R8YtJd");
 
@@ -13,16 +16,50 @@ declare_id!("EWAghmkH9oRqUDUWSQv6Fob8LHyvb3YbHhrnm5R8YtJd");
 pub mod escrow {
     use super::*;
 
-    pub fn do_make(ctx: Context<Make>, seed: u64, deposit: u64, receive: u64) -> Result<()> {
-        ctx.accounts.init_escrow(seed, receive, &ctx.bumps)?;
+    pub fn do_make(
+        ctx: Context<Make>,
+        seed: u64,
+        deposit: u64,
+        receive: u64,
+        expires_at: Option<i64>,
+        allowed_taker: Option<Pubkey>,
+    ) -> Result<()> {
+        ctx.accounts.init_escrow(seed, deposit, receive, expires_at, allowed_taker, &ctx.bumps)?;
         ctx.accounts.deposit(deposit)?;
         Ok(())
     }
+    pub fn make_basket<'info>(
+        ctx: Context<'_, '_, 'info, 'info, MakeBasket<'info>>,
+        params: MakeBasketParams,
+    ) -> Result<()> {
+        ctx.accounts.make_basket(ctx.remaining_accounts, params, &ctx.bumps)
+    }
+    pub fn take_basket<'info>(ctx: Context<'_, '_, 'info, 'info, TakeBasket<'info>>) -> Result<()> {
+        ctx.accounts.take_basket(ctx.remaining_accounts)
+    }
+    pub fn refund_basket<'info>(ctx: Context<'_, '_, 'info, 'info, RefundBasket<'info>>) -> Result<()> {
+        ctx.accounts.refund_basket(ctx.remaining_accounts)
+    }
+    pub fn crank_refund_basket<'info>(ctx: Context<'_, '_, 'info, 'info, CrankRefundBasket<'info>>) -> Result<()> {
+        ctx.accounts.crank_refund_basket(ctx.remaining_accounts)
+    }
     pub fn do_refund(ctx: Context<Refund>) -> Result<()> {
         ctx.accounts.refund_and_close_vault()
     }
-    pub fn do_take_deposit(ctx: Context<TakeDeposit>) -> Result<()> {
-        ctx.accounts.take_deposit()
+    pub fn crank_refund(ctx: Context<CrankRefund>) -> Result<()> {
+        ctx.accounts.crank_refund()
+    }
+    pub fn do_take(ctx: Context<Take>, amount: u64, expected_version: u64) -> Result<()> {
+        ctx.accounts.take(amount, expected_version)
+    }
+    pub fn do_take_deposit(ctx: Context<TakeDeposit>, amount: u64, expected_version: u64) -> Result<()> {
+        ctx.accounts.take_deposit(amount, expected_version, &ctx.bumps)
+    }
+    pub fn update_offer(ctx: Context<UpdateOffer>, receive: u64, expires_at: Option<i64>) -> Result<()> {
+        ctx.accounts.update_offer(receive, expires_at)
+    }
+    pub fn top_up(ctx: Context<TopUp>, amount: u64) -> Result<()> {
+        ctx.accounts.top_up(amount)
     }
     pub fn do_take_withdraw(ctx: Context<TakeWithdraw>) -> Result<()> {
         ctx.accounts.take_withdraw()
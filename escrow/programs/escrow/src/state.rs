@@ -1,4 +1,8 @@
 use anchor_lang::prelude::*;
+use anchor_spl::token_interface::TokenInterface;
+
+use crate::constants::MAX_BASKET_LEGS;
+use crate::error::EscrowError;
 
 #[account]
 #[derive(InitSpace)]
@@ -8,5 +12,141 @@ pub struct Escrow {
     pub mint_a: Pubkey,
     pub mint_b: Pubkey,
     pub receive: u64,
+    pub deposit_remaining: u64,
+    pub receive_remaining: u64,
+    /// `mint_a` paid for through `take_deposit` that receipts have yet to
+    /// withdraw; a refund leaves it in the vault.
+    pub pending_withdrawal: u64,
+    /// Unix timestamp after which the offer can no longer be taken and
+    /// anyone may refund it; `None` never expires.
+    pub expires_at: Option<i64>,
+    /// Only this taker may fill the offer; `None` leaves it open to anyone.
+    pub allowed_taker: Option<Pubkey>,
+    /// Bumped on every change the maker makes, so takers can pin the terms
+    /// they saw.
+    pub version: u64,
+    pub bump: u8
+}
+
+// Expiry and taker rules shared by single and basket offers.
+fn is_expired(expires_at: Option<i64>, now: i64) -> bool {
+    expires_at.is_some_and(|expires_at| now > expires_at)
+}
+
+fn can_take(allowed_taker: Option<Pubkey>, taker: &Pubkey) -> bool {
+    allowed_taker.is_none_or(|allowed| allowed == *taker)
+}
+
+fn check_not_expired(expires_at: Option<i64>) -> Result<()> {
+    require!(!is_expired(expires_at, Clock::get()?.unix_timestamp), EscrowError::Expired);
+    Ok(())
+}
+
+/// Records split-flow payments at `[b"receipt", escrow, taker]` until
+/// `do_take_withdraw` releases the `amount` of `mint_a` they paid for.
+#[account]
+#[derive(InitSpace)]
+pub struct TakeReceipt {
+    pub escrow: Pubkey,
+    pub taker: Pubkey,
+    pub amount: u64,
     pub bump: u8
 }
+
+/// One mint and amount on either side of a basket offer.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, InitSpace)]
+pub struct BasketLeg {
+    pub mint: Pubkey,
+    pub amount: u64
+}
+
+impl BasketLeg {
+    /// Checks the leg's `mint` account and returns the token program that owns
+    /// it, so each leg may be SPL Token or Token-2022.
+    pub fn token_program<'info>(
+        &self,
+        mint: &'info AccountInfo<'info>,
+        token_program: &'info AccountInfo<'info>,
+    ) -> Result<Interface<'info, TokenInterface>> {
+        require_keys_eq!(mint.key(), self.mint, EscrowError::InvalidBasketAccount);
+        let token_program = Interface::<TokenInterface>::try_from(token_program)?;
+        require_keys_eq!(*mint.owner, token_program.key(), EscrowError::InvalidBasketAccount);
+        Ok(token_program)
+    }
+}
+
+/// Offers every `offer` leg for every `request` leg, all-or-nothing. Each
+/// offered mint sits in its own vault, the basket's ATA for that mint.
+#[account]
+#[derive(InitSpace)]
+pub struct BasketEscrow {
+    pub seed: u64,
+    pub maker: Pubkey,
+    #[max_len(MAX_BASKET_LEGS)]
+    pub offer: Vec<BasketLeg>,
+    #[max_len(MAX_BASKET_LEGS)]
+    pub request: Vec<BasketLeg>,
+    pub expires_at: Option<i64>,
+    pub allowed_taker: Option<Pubkey>,
+    pub bump: u8
+}
+
+impl BasketEscrow {
+    /// Legs must name distinct mints, since each offered mint has one vault.
+    pub fn check_legs(legs: &[BasketLeg]) -> Result<()> {
+        require!((1..=MAX_BASKET_LEGS).contains(&legs.len()), EscrowError::InvalidBasket);
+        for (i, leg) in legs.iter().enumerate() {
+            require!(leg.amount > 0, EscrowError::InvalidAmount);
+            require!(legs[..i].iter().all(|other| other.mint != leg.mint), EscrowError::InvalidBasket);
+        }
+        Ok(())
+    }
+    pub fn can_take(&self, taker: &Pubkey) -> bool {
+        can_take(self.allowed_taker, taker)
+    }
+    pub fn is_expired(&self, now: i64) -> bool {
+        is_expired(self.expires_at, now)
+    }
+    pub fn check_not_expired(&self) -> Result<()> {
+        check_not_expired(self.expires_at)
+    }
+}
+
+impl Escrow {
+    pub fn is_expired(&self, now: i64) -> bool {
+        is_expired(self.expires_at, now)
+    }
+    pub fn can_take(&self, taker: &Pubkey) -> bool {
+        can_take(self.allowed_taker, taker)
+    }
+    pub fn check_not_expired(&self) -> Result<()> {
+        check_not_expired(self.expires_at)
+    }
+    /// What a refund returns from a vault holding `vault_amount`: everything
+    /// no receipt has paid for, including tokens sent to the vault directly.
+    pub fn refundable(&self, vault_amount: u64) -> u64 {
+        vault_amount.saturating_sub(self.pending_withdrawal)
+    }
+    pub fn check_version(&self, expected_version: u64) -> Result<()> {
+        require!(self.version == expected_version, EscrowError::VersionMismatch);
+        Ok(())
+    }
+    /// `mint_b` owed for taking `amount` of `mint_a`, pro-rata to what is left
+    /// and rounded up so partial fills never shortchange the maker.
+    pub fn receive_for(&self, amount: u64) -> Result<u64> {
+        require!(amount > 0, EscrowError::InvalidAmount);
+        require!(amount <= self.deposit_remaining, EscrowError::ExceedsRemaining);
+        if amount == self.deposit_remaining {
+            return Ok(self.receive_remaining);
+        }
+        let owed = (amount as u128 * self.receive_remaining as u128)
+            .div_ceil(self.deposit_remaining as u128);
+        Ok(owed as u64)
+    }
+    pub fn fill(&mut self, amount: u64) -> Result<u64> {
+        let owed = self.receive_for(amount)?;
+        self.deposit_remaining -= amount;
+        self.receive_remaining -= owed;
+        Ok(owed)
+    }
+}
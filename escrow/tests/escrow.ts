@@ -2,7 +2,7 @@ import * as anchor from "@coral-xyz/anchor";
 import { Program } from "@coral-xyz/anchor";
 import { Escrow } from "../target/types/escrow";
 import { expect } from "chai";
-import { getAssociatedTokenAddressSync, createAssociatedTokenAccountInstruction, createMint, mintTo, TOKEN_PROGRAM_ID, ASSOCIATED_TOKEN_PROGRAM_ID } from "@solana/spl-token";
+import { getAssociatedTokenAddressSync, createAssociatedTokenAccountInstruction, createMint, mintTo, TOKEN_PROGRAM_ID, TOKEN_2022_PROGRAM_ID, ASSOCIATED_TOKEN_PROGRAM_ID } from "@solana/spl-token";
 
 describe("Escrow", () => {
   const provider = anchor.AnchorProvider.env();
@@ -28,6 +28,12 @@ describe("Escrow", () => {
   const depositAmount = 100;
   const receiveAmount = 200;
 
+  const receiptFor = (escrow: anchor.web3.PublicKey) =>
+    anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("receipt"), escrow.toBuffer(), taker.publicKey.toBuffer()],
+      program.programId
+    )[0];
+
   before(async () => {
     // Airdrop SOL to maker and taker
     await provider.connection.requestAirdrop(maker, 10 * anchor.web3.LAMPORTS_PER_SOL);
@@ -76,7 +82,7 @@ describe("Escrow", () => {
 
     // Make
     await program.methods
-      .doMake(seed1, new anchor.BN(depositAmount), new anchor.BN(receiveAmount))
+      .doMake(seed1, new anchor.BN(depositAmount), new anchor.BN(receiveAmount), null, null)
       .accountsStrict({
         maker: maker,
         mintA: mintA,
@@ -133,7 +139,7 @@ describe("Escrow", () => {
 
     // Make (again for take path)
     await program.methods
-      .doMake(seed2, new anchor.BN(depositAmount), new anchor.BN(receiveAmount))
+      .doMake(seed2, new anchor.BN(depositAmount), new anchor.BN(receiveAmount), null, null)
       .accountsStrict({
         maker: maker,
         mintA: mintA,
@@ -148,8 +154,9 @@ describe("Escrow", () => {
       .rpc();
 
 
-    const ixDeposit = await program.methods
-  .doTakeDeposit()
+    // Two deposits before the withdraw add up on the same receipt
+    const depositIx = (amount: number) => program.methods
+  .doTakeDeposit(new anchor.BN(amount), new anchor.BN(0))
   .accountsStrict({
     taker: taker.publicKey,
     maker: maker,
@@ -157,9 +164,13 @@ describe("Escrow", () => {
     takerAtaB: takerAtaB,
     makerAtaB: makerAtaB,
     escrow: escrowPda,
+    receipt: receiptFor(escrowPda),
     tokenProgram: TOKEN_PROGRAM_ID,
+    systemProgram: anchor.web3.SystemProgram.programId,
   })
   .instruction();
+  const ixDeposit = await depositIx(depositAmount / 2);
+  const ixDepositRest = await depositIx(depositAmount / 2);
 
   const ixWithdraw = await program.methods
   .doTakeWithdraw()
@@ -169,6 +180,7 @@ describe("Escrow", () => {
     takerAtaA: takerAtaA,
     escrow: escrowPda,
     vault: vault,
+    receipt: receiptFor(escrowPda),
     tokenProgram: TOKEN_PROGRAM_ID,
   })
   .instruction();
@@ -176,7 +188,9 @@ describe("Escrow", () => {
   const ixClose = await program.methods
   .doTakeClose()
   .accountsStrict({
+    taker: taker.publicKey,
     maker: maker,
+    makerAtaA: makerAtaA,
     escrow: escrowPda,
     vault: vault,
     mintA: mintA,
@@ -184,7 +198,7 @@ describe("Escrow", () => {
   })
   .instruction();
 
-  const tx = new anchor.web3.Transaction().add(ixDeposit, ixWithdraw, ixClose);
+  const tx = new anchor.web3.Transaction().add(ixDeposit, ixDepositRest, ixWithdraw, ixClose);
   await provider.sendAndConfirm(tx, [taker]); 
 
     // Check closed
@@ -201,4 +215,572 @@ describe("Escrow", () => {
     const makerBalanceB = (await provider.connection.getTokenAccountBalance(makerAtaB)).value.uiAmount;
     expect(makerBalanceB).to.equal(receiveAmount);
   });
+
+  it("Refuses to release the vault without a paid receipt", async () => {
+    const seed6 = new anchor.BN(6666);
+    [escrowPda] = anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("escrow"), maker.toBuffer(), seed6.toArrayLike(Buffer, "le", 8)],
+      program.programId
+    );
+    vault = getAssociatedTokenAddressSync(mintA, escrowPda, true);
+
+    await program.methods
+      .doMake(seed6, new anchor.BN(1), new anchor.BN(1), null, null)
+      .accountsStrict({
+        maker: maker,
+        mintA: mintA,
+        mintB: mintB,
+        makerAtaA: makerAtaA,
+        escrow: escrowPda,
+        vault: vault,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .rpc();
+
+    try {
+      await program.methods
+        .doTakeWithdraw()
+        .accountsStrict({
+          taker: taker.publicKey,
+          mintA: mintA,
+          takerAtaA: takerAtaA,
+          escrow: escrowPda,
+          vault: vault,
+          receipt: receiptFor(escrowPda),
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .rpc();
+      expect.fail("withdrawing without paying should fail");
+    } catch (err) {
+      expect(err.error.errorCode.code).to.equal("AccountNotInitialized");
+    }
+
+    await program.methods
+      .doRefund()
+      .accountsStrict({
+        maker: maker,
+        mintA: mintA,
+        makerAtaA: makerAtaA,
+        escrow: escrowPda,
+        vault: vault,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .rpc();
+  });
+
+  it("Refunds past tokens sent to the vault and keeps paid fills for their receipt", async () => {
+    const seed9 = new anchor.BN(9999);
+    [escrowPda] = anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("escrow"), maker.toBuffer(), seed9.toArrayLike(Buffer, "le", 8)],
+      program.programId
+    );
+    vault = getAssociatedTokenAddressSync(mintA, escrowPda, true);
+    await mintTo(provider.connection, provider.wallet.payer, mintA, makerAtaA, provider.wallet.payer, 10);
+
+    await program.methods
+      .doMake(seed9, new anchor.BN(10), new anchor.BN(20), null, null)
+      .accountsStrict({
+        maker: maker,
+        mintA: mintA,
+        mintB: mintB,
+        makerAtaA: makerAtaA,
+        escrow: escrowPda,
+        vault: vault,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .rpc();
+    // Anyone can send tokens to the vault; it must not lock the refund.
+    await mintTo(provider.connection, provider.wallet.payer, mintA, vault, provider.wallet.payer, 1);
+
+    await program.methods
+      .doTakeDeposit(new anchor.BN(4), new anchor.BN(0))
+      .accountsStrict({
+        taker: taker.publicKey,
+        maker: maker,
+        mintB: mintB,
+        takerAtaB: takerAtaB,
+        makerAtaB: makerAtaB,
+        escrow: escrowPda,
+        receipt: receiptFor(escrowPda),
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .signers([taker])
+      .rpc();
+
+    const balance = async (ata: anchor.web3.PublicKey) =>
+      Number((await provider.connection.getTokenAccountBalance(ata)).value.amount);
+    const makerBefore = await balance(makerAtaA);
+    await program.methods
+      .doRefund()
+      .accountsStrict({
+        maker: maker,
+        mintA: mintA,
+        makerAtaA: makerAtaA,
+        escrow: escrowPda,
+        vault: vault,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .rpc();
+    // The unsold 6 and the donated 1 go back; the 4 paid for stay in the vault.
+    expect(await balance(makerAtaA)).to.equal(makerBefore + 7);
+    expect(await balance(vault)).to.equal(4);
+    const escrowAccount = await program.account.escrow.fetch(escrowPda);
+    expect(escrowAccount.depositRemaining.toNumber()).to.equal(0);
+    expect(escrowAccount.pendingWithdrawal.toNumber()).to.equal(4);
+
+    const takerBefore = await balance(takerAtaA);
+    const ixWithdraw = await program.methods
+      .doTakeWithdraw()
+      .accountsStrict({
+        taker: taker.publicKey,
+        mintA: mintA,
+        takerAtaA: takerAtaA,
+        escrow: escrowPda,
+        vault: vault,
+        receipt: receiptFor(escrowPda),
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .instruction();
+    const ixClose = await program.methods
+      .doTakeClose()
+      .accountsStrict({
+        taker: taker.publicKey,
+        maker: maker,
+        makerAtaA: makerAtaA,
+        escrow: escrowPda,
+        vault: vault,
+        mintA: mintA,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .instruction();
+    await provider.sendAndConfirm(new anchor.web3.Transaction().add(ixWithdraw, ixClose), [taker]);
+
+    expect(await balance(takerAtaA)).to.equal(takerBefore + 4);
+    expect(await provider.connection.getAccountInfo(escrowPda)).to.be.null;
+    expect(await provider.connection.getAccountInfo(vault)).to.be.null;
+  });
+
+  it("Takes an escrow atomically in parts, rounding in the maker's favor", async () => {
+    const seed3 = new anchor.BN(3333);
+    [escrowPda] = anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("escrow"), maker.toBuffer(), seed3.toArrayLike(Buffer, "le", 8)],
+      program.programId
+    );
+    vault = getAssociatedTokenAddressSync(mintA, escrowPda, true);
+
+    await program.methods
+      .doMake(seed3, new anchor.BN(3), new anchor.BN(10), null, null)
+      .accountsStrict({
+        maker: maker,
+        mintA: mintA,
+        mintB: mintB,
+        makerAtaA: makerAtaA,
+        escrow: escrowPda,
+        vault: vault,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .rpc();
+
+    const take = async (amount: number) =>
+      program.methods
+        .doTake(new anchor.BN(amount), new anchor.BN(0))
+        .accountsStrict({
+          taker: taker.publicKey,
+          maker: maker,
+          mintA: mintA,
+          mintB: mintB,
+          takerAtaA: takerAtaA,
+          takerAtaB: takerAtaB,
+          makerAtaB: makerAtaB,
+          escrow: escrowPda,
+          vault: vault,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          systemProgram: anchor.web3.SystemProgram.programId,
+          associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        })
+        .signers([taker])
+        .rpc();
+    const close = await program.methods
+      .doTakeClose()
+      .accountsStrict({
+        taker: taker.publicKey,
+        maker: maker,
+        makerAtaA: makerAtaA,
+        escrow: escrowPda,
+        vault: vault,
+        mintA: mintA,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .instruction();
+    const makerBalanceB = async () =>
+      Number((await provider.connection.getTokenAccountBalance(makerAtaB)).value.amount);
+    const makerBefore = await makerBalanceB();
+
+    // 1 of 3 for 10 costs 3.33, rounded up to 4.
+    await take(1);
+    const escrowAccount = await program.account.escrow.fetch(escrowPda);
+    expect(escrowAccount.depositRemaining.toNumber()).to.equal(2);
+    expect(escrowAccount.receiveRemaining.toNumber()).to.equal(6);
+    expect(await makerBalanceB()).to.equal(makerBefore + 4);
+
+    try {
+      await provider.sendAndConfirm(new anchor.web3.Transaction().add(close), [taker]);
+      expect.fail("closing a partly filled escrow should fail");
+    } catch (err) {
+      expect(String(err)).to.contain("NotFilled");
+    }
+
+    // The last fill closes the vault and the escrow on its own.
+    await take(2);
+    expect(await makerBalanceB()).to.equal(makerBefore + 10);
+    expect(await provider.connection.getAccountInfo(escrowPda)).to.be.null;
+    expect(await provider.connection.getAccountInfo(vault)).to.be.null;
+  });
+
+  it("Reserves an escrow for its allowed taker", async () => {
+    const seed5 = new anchor.BN(5555);
+    [escrowPda] = anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("escrow"), maker.toBuffer(), seed5.toArrayLike(Buffer, "le", 8)],
+      program.programId
+    );
+    vault = getAssociatedTokenAddressSync(mintA, escrowPda, true);
+    const counterparty = anchor.web3.Keypair.generate().publicKey;
+
+    await program.methods
+      .doMake(seed5, new anchor.BN(1), new anchor.BN(1), null, counterparty)
+      .accountsStrict({
+        maker: maker,
+        mintA: mintA,
+        mintB: mintB,
+        makerAtaA: makerAtaA,
+        escrow: escrowPda,
+        vault: vault,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .rpc();
+    const escrowAccount = await program.account.escrow.fetch(escrowPda);
+    expect(escrowAccount.allowedTaker.toBase58()).to.equal(counterparty.toBase58());
+
+    try {
+      await program.methods
+        .doTakeDeposit(new anchor.BN(1), new anchor.BN(0))
+        .accountsStrict({
+          taker: taker.publicKey,
+          maker: maker,
+          mintB: mintB,
+          takerAtaB: takerAtaB,
+          makerAtaB: makerAtaB,
+          escrow: escrowPda,
+          receipt: receiptFor(escrowPda),
+          tokenProgram: TOKEN_PROGRAM_ID,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        })
+        .signers([taker])
+        .rpc();
+      expect.fail("only the allowed taker may fill the escrow");
+    } catch (err) {
+      expect(err.error.errorCode.code).to.equal("TakerNotAllowed");
+    }
+
+    await program.methods
+      .doRefund()
+      .accountsStrict({
+        maker: maker,
+        mintA: mintA,
+        makerAtaA: makerAtaA,
+        escrow: escrowPda,
+        vault: vault,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .rpc();
+  });
+
+  it("Rejects takes after expiry and lets anyone crank the refund", async () => {
+    const seed4 = new anchor.BN(4444);
+    [escrowPda] = anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("escrow"), maker.toBuffer(), seed4.toArrayLike(Buffer, "le", 8)],
+      program.programId
+    );
+    vault = getAssociatedTokenAddressSync(mintA, escrowPda, true);
+    const expiresAt = new anchor.BN(Math.floor(Date.now() / 1000) + 2);
+
+    await program.methods
+      .doMake(seed4, new anchor.BN(depositAmount / 2), new anchor.BN(receiveAmount / 2), expiresAt, null)
+      .accountsStrict({
+        maker: maker,
+        mintA: mintA,
+        mintB: mintB,
+        makerAtaA: makerAtaA,
+        escrow: escrowPda,
+        vault: vault,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .rpc();
+    const makerBalanceA = async () =>
+      Number((await provider.connection.getTokenAccountBalance(makerAtaA)).value.amount);
+    const makerBefore = await makerBalanceA();
+
+    await new Promise(resolve => setTimeout(resolve, 4000));
+
+    try {
+      await program.methods
+        .doTakeDeposit(new anchor.BN(depositAmount / 2), new anchor.BN(0))
+        .accountsStrict({
+          taker: taker.publicKey,
+          maker: maker,
+          mintB: mintB,
+          takerAtaB: takerAtaB,
+          makerAtaB: makerAtaB,
+          escrow: escrowPda,
+          receipt: receiptFor(escrowPda),
+          tokenProgram: TOKEN_PROGRAM_ID,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        })
+        .signers([taker])
+        .rpc();
+      expect.fail("taking an expired escrow should fail");
+    } catch (err) {
+      expect(err.error.errorCode.code).to.equal("Expired");
+    }
+
+    const crankerBefore = await provider.connection.getBalance(taker.publicKey);
+    await program.methods
+      .crankRefund()
+      .accountsStrict({
+        cranker: taker.publicKey,
+        maker: maker,
+        mintA: mintA,
+        makerAtaA: makerAtaA,
+        escrow: escrowPda,
+        vault: vault,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .signers([taker])
+      .rpc();
+
+    expect(await makerBalanceA()).to.equal(makerBefore + depositAmount / 2);
+    expect(await provider.connection.getBalance(taker.publicKey)).to.be.greaterThan(crankerBefore);
+    expect(await provider.connection.getAccountInfo(escrowPda)).to.be.null;
+    expect(await provider.connection.getAccountInfo(vault)).to.be.null;
+  });
+
+  it("Bumps the version on update_offer and top_up so stale takes fail", async () => {
+    const seed8 = new anchor.BN(8888);
+    [escrowPda] = anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("escrow"), maker.toBuffer(), seed8.toArrayLike(Buffer, "le", 8)],
+      program.programId
+    );
+    vault = getAssociatedTokenAddressSync(mintA, escrowPda, true);
+
+    await program.methods
+      .doMake(seed8, new anchor.BN(2), new anchor.BN(4), null, null)
+      .accountsStrict({
+        maker: maker,
+        mintA: mintA,
+        mintB: mintB,
+        makerAtaA: makerAtaA,
+        escrow: escrowPda,
+        vault: vault,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .rpc();
+
+    // The offer had no expiry; update_offer may set one
+    const expiresAt = new anchor.BN(Math.floor(Date.now() / 1000) + 3600);
+    await program.methods
+      .updateOffer(new anchor.BN(6), expiresAt)
+      .accountsStrict({ maker: maker, escrow: escrowPda })
+      .rpc();
+    await program.methods
+      .topUp(new anchor.BN(1))
+      .accountsStrict({
+        maker: maker,
+        mintA: mintA,
+        makerAtaA: makerAtaA,
+        escrow: escrowPda,
+        vault: vault,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .rpc();
+    const escrowAccount = await program.account.escrow.fetch(escrowPda);
+    expect(escrowAccount.version.toNumber()).to.equal(2);
+    expect(escrowAccount.depositRemaining.toNumber()).to.equal(3);
+    expect(escrowAccount.receiveRemaining.toNumber()).to.equal(6);
+    expect(escrowAccount.expiresAt.toNumber()).to.equal(expiresAt.toNumber());
+
+    const take = (expectedVersion: number) =>
+      program.methods
+        .doTake(new anchor.BN(3), new anchor.BN(expectedVersion))
+        .accountsStrict({
+          taker: taker.publicKey,
+          maker: maker,
+          mintA: mintA,
+          mintB: mintB,
+          takerAtaA: takerAtaA,
+          takerAtaB: takerAtaB,
+          makerAtaB: makerAtaB,
+          escrow: escrowPda,
+          vault: vault,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          systemProgram: anchor.web3.SystemProgram.programId,
+          associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        })
+        .signers([taker])
+        .rpc();
+    try {
+      await take(0);
+      expect.fail("a take pinned to the original terms should fail");
+    } catch (err) {
+      expect(err.error.errorCode.code).to.equal("VersionMismatch");
+    }
+    await take(2);
+    expect(await provider.connection.getAccountInfo(escrowPda)).to.be.null;
+  });
+
+  it("Swaps a basket of SPL Token and Token-2022 mints for another atomically", async () => {
+    const basketSeed = new anchor.BN(7777);
+    const [basket] = anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("basket"), maker.toBuffer(), basketSeed.toArrayLike(Buffer, "le", 8)],
+      program.programId
+    );
+    // mintC is a Token-2022 mint; every leg carries its own token program.
+    const mintC = await createMint(provider.connection, provider.wallet.payer, maker, null, 0, undefined, undefined, TOKEN_2022_PROGRAM_ID);
+    const makerAtaC = getAssociatedTokenAddressSync(mintC, maker, false, TOKEN_2022_PROGRAM_ID);
+    const takerAtaC = getAssociatedTokenAddressSync(mintC, taker.publicKey, false, TOKEN_2022_PROGRAM_ID);
+    await provider.sendAndConfirm(new anchor.web3.Transaction().add(
+      createAssociatedTokenAccountInstruction(maker, makerAtaC, maker, mintC, TOKEN_2022_PROGRAM_ID),
+      createAssociatedTokenAccountInstruction(maker, takerAtaC, taker.publicKey, mintC, TOKEN_2022_PROGRAM_ID),
+    ));
+    await mintTo(provider.connection, provider.wallet.payer, mintC, makerAtaC, provider.wallet.payer, 7, [], undefined, TOKEN_2022_PROGRAM_ID);
+    const vaultA = getAssociatedTokenAddressSync(mintA, basket, true);
+    const vaultC = getAssociatedTokenAddressSync(mintC, basket, true, TOKEN_2022_PROGRAM_ID);
+    const account = (pubkey: anchor.web3.PublicKey, isWritable: boolean) => ({ pubkey, isSigner: false, isWritable });
+    const [splToken, token2022] = [account(TOKEN_PROGRAM_ID, false), account(TOKEN_2022_PROGRAM_ID, false)];
+    const balance = async (ata: anchor.web3.PublicKey) =>
+      Number((await provider.connection.getTokenAccountBalance(ata)).value.amount);
+
+    await program.methods
+      .makeBasket({
+        seed: basketSeed,
+        offer: [{ mint: mintA, amount: new anchor.BN(5) }, { mint: mintC, amount: new anchor.BN(7) }],
+        request: [{ mint: mintB, amount: new anchor.BN(9) }],
+        expiresAt: null,
+        allowedTaker: null,
+      })
+      .accountsStrict({
+        maker: maker,
+        basket: basket,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .remainingAccounts([
+        account(mintA, false), account(makerAtaA, true), account(vaultA, true), splToken,
+        account(mintC, false), account(makerAtaC, true), account(vaultC, true), token2022,
+      ])
+      .rpc();
+    expect(await balance(vaultA)).to.equal(5);
+    expect(await balance(vaultC)).to.equal(7);
+
+    const [takerA, takerB, makerB] = [await balance(takerAtaA), await balance(takerAtaB), await balance(makerAtaB)];
+    await program.methods
+      .takeBasket()
+      .accountsStrict({
+        taker: taker.publicKey,
+        maker: maker,
+        basket: basket,
+      })
+      .remainingAccounts([
+        account(mintB, false), account(takerAtaB, true), account(makerAtaB, true), splToken,
+        account(mintA, false), account(vaultA, true), account(takerAtaA, true), splToken,
+        account(mintC, false), account(vaultC, true), account(takerAtaC, true), token2022,
+      ])
+      .signers([taker])
+      .rpc();
+
+    expect(await balance(takerAtaA)).to.equal(takerA + 5);
+    expect(await balance(takerAtaC)).to.equal(7);
+    expect(await balance(takerAtaB)).to.equal(takerB - 9);
+    expect(await balance(makerAtaB)).to.equal(makerB + 9);
+    expect(await provider.connection.getAccountInfo(basket)).to.be.null;
+    expect(await provider.connection.getAccountInfo(vaultA)).to.be.null;
+    expect(await provider.connection.getAccountInfo(vaultC)).to.be.null;
+  });
+
+  it("Lets anyone crank the refund of an expired basket", async () => {
+    const basketSeed = new anchor.BN(6666);
+    const [basket] = anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("basket"), maker.toBuffer(), basketSeed.toArrayLike(Buffer, "le", 8)],
+      program.programId
+    );
+    const vaultA = getAssociatedTokenAddressSync(mintA, basket, true);
+    const account = (pubkey: anchor.web3.PublicKey, isWritable: boolean) => ({ pubkey, isSigner: false, isWritable });
+    const balance = async (ata: anchor.web3.PublicKey) =>
+      Number((await provider.connection.getTokenAccountBalance(ata)).value.amount);
+    await mintTo(provider.connection, provider.wallet.payer, mintA, makerAtaA, provider.wallet.payer, 3);
+    const expiresAt = new anchor.BN(Math.floor(Date.now() / 1000) + 2);
+
+    await program.methods
+      .makeBasket({
+        seed: basketSeed,
+        offer: [{ mint: mintA, amount: new anchor.BN(3) }],
+        request: [{ mint: mintB, amount: new anchor.BN(4) }],
+        expiresAt: expiresAt,
+        allowedTaker: null,
+      })
+      .accountsStrict({
+        maker: maker,
+        basket: basket,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .remainingAccounts([account(mintA, false), account(makerAtaA, true), account(vaultA, true), account(TOKEN_PROGRAM_ID, false)])
+      .rpc();
+    const makerBefore = await balance(makerAtaA);
+
+    const crank = () => program.methods
+      .crankRefundBasket()
+      .accountsStrict({
+        cranker: taker.publicKey,
+        maker: maker,
+        basket: basket,
+      })
+      .remainingAccounts([account(mintA, false), account(vaultA, true), account(makerAtaA, true), account(TOKEN_PROGRAM_ID, false)])
+      .signers([taker])
+      .rpc();
+
+    try {
+      await crank();
+      expect.fail("cranking a live basket should fail");
+    } catch (err) {
+      expect(err.error.errorCode.code).to.equal("NotExpired");
+    }
+
+    await new Promise(resolve => setTimeout(resolve, 4000));
+    const crankerBefore = await provider.connection.getBalance(taker.publicKey);
+    await crank();
+
+    expect(await balance(makerAtaA)).to.equal(makerBefore + 3);
+    expect(await provider.connection.getBalance(taker.publicKey)).to.be.greaterThan(crankerBefore);
+    expect(await provider.connection.getAccountInfo(basket)).to.be.null;
+    expect(await provider.connection.getAccountInfo(vaultA)).to.be.null;
+  });
 });
\ No newline at end of file